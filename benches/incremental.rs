@@ -0,0 +1,43 @@
+//! Full vs incremental re-parse of a large document after a one-word edit.
+//! Run with `cargo bench --bench incremental`.
+
+use mdp::parser::{IncrementalParser, ParseOptions, parse_markdown_with_options};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+fn main() {
+    let mut source = String::new();
+    for i in 0..2000 {
+        source.push_str(&format!(
+            "## Section {i}\n\nSome *text* with a [link](https://example.com) and `code`.\n\n- item one\n- item two\n\n"
+        ));
+    }
+    let options = ParseOptions::default();
+    let edited = source.replacen("Section 1000\n", "Section 1000 (edited)\n", 1);
+
+    let full = time(|| {
+        parse_markdown_with_options(&edited, options);
+    });
+
+    let mut parser = IncrementalParser::new(&source, options);
+    let mut toggle = false;
+    let incremental = time(|| {
+        toggle = !toggle;
+        let input = if toggle { &edited } else { &source };
+        assert!(parser.update(input), "edit should re-parse incrementally");
+    });
+
+    println!("document: {} KiB", source.len() / 1024);
+    println!("full parse:        {:>10.3?}", full);
+    println!("incremental parse: {:>10.3?}", incremental);
+}
+
+/// Average time of `f` over `ITERATIONS` runs
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
@@ -0,0 +1,496 @@
+use crossterm::style::Color;
+use serde::Deserialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::renderer::terminal::{RuleStyle, TerminalStyle, TerminalTheme};
+
+/// User configuration loaded from `config.toml`
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub style: TerminalStyle,
+    pub colors: ColorOverrides,
+}
+
+/// Terminal colors set in the config file, each replacing the one from the
+/// palette picked by `--theme`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColorOverrides {
+    /// From `style.h1` through `style.h6`
+    pub headings: [Option<Color>; 6],
+    pub text: Option<Color>,
+    pub muted: Option<Color>,
+    pub border: Option<Color>,
+    pub accent: Option<Color>,
+    pub link: Option<Color>,
+    pub code: Option<Color>,
+    pub math: Option<Color>,
+    pub label: Option<Color>,
+    pub checked: Option<Color>,
+    /// Note, tip, important, warning and caution alerts
+    pub alerts: [Option<Color>; 5],
+}
+
+impl ColorOverrides {
+    /// `theme` with the overridden colors replaced
+    pub fn apply(&self, mut theme: TerminalTheme) -> TerminalTheme {
+        let set = |color: &mut Color, value: Option<Color>| {
+            if let Some(value) = value {
+                *color = value;
+            }
+        };
+        for (color, value) in theme.headings.iter_mut().zip(self.headings) {
+            set(color, value);
+        }
+        for (color, value) in theme.alerts.iter_mut().zip(self.alerts) {
+            set(color, value);
+        }
+        set(&mut theme.text, self.text);
+        set(&mut theme.muted, self.muted);
+        set(&mut theme.border, self.border);
+        set(&mut theme.accent, self.accent);
+        set(&mut theme.link, self.link);
+        set(&mut theme.code, self.code);
+        set(&mut theme.math, self.math);
+        set(&mut theme.label, self.label);
+        set(&mut theme.checked, self.checked);
+        theme
+    }
+}
+
+/// Raw file layout; every field is optional and falls back to the defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    style: StyleSection,
+    colors: ColorsSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StyleSection {
+    h1: Option<HeadingSection>,
+    h2: Option<HeadingSection>,
+    h3: Option<HeadingSection>,
+    h4: Option<HeadingSection>,
+    h5: Option<HeadingSection>,
+    h6: Option<HeadingSection>,
+    bullets: Option<Vec<String>>,
+    rule: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ColorsSection {
+    text: Option<String>,
+    muted: Option<String>,
+    border: Option<String>,
+    accent: Option<String>,
+    link: Option<String>,
+    code: Option<String>,
+    math: Option<String>,
+    label: Option<String>,
+    checked: Option<String>,
+    note: Option<String>,
+    tip: Option<String>,
+    important: Option<String>,
+    warning: Option<String>,
+    caution: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeadingSection {
+    color: Option<String>,
+    prefix: Option<String>,
+}
+
+impl Config {
+    /// Default config location: `$XDG_CONFIG_HOME/mdp/config.toml` or `~/.config/mdp/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("mdp").join("config.toml"))
+    }
+
+    /// Load the config from `path`, or from the default location when `None`.
+    /// A missing default config is not an error; a missing explicit path is.
+    pub fn load(path: Option<&Path>) -> io::Result<Self> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => Ok(Self::default()),
+            Err(e) => Err(io::Error::new(
+                e.kind(),
+                format!("{}: {}", path.display(), e),
+            )),
+        }
+    }
+
+    /// Parse config file contents
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let file: ConfigFile = toml::from_str(content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.message().to_string()))?;
+
+        let mut colors = file.colors.into_overrides()?;
+        colors.headings = file.style.heading_colors()?;
+        Ok(Self {
+            style: file.style.into_style()?,
+            colors,
+        })
+    }
+}
+
+impl ColorsSection {
+    fn into_overrides(self) -> io::Result<ColorOverrides> {
+        let color = |key: &str, value: Option<String>| {
+            value
+                .map(|value| {
+                    parse_color(&value).ok_or_else(|| {
+                        invalid_data(format!("invalid color '{}' for colors.{}", value, key))
+                    })
+                })
+                .transpose()
+        };
+        Ok(ColorOverrides {
+            headings: Default::default(),
+            text: color("text", self.text)?,
+            muted: color("muted", self.muted)?,
+            border: color("border", self.border)?,
+            accent: color("accent", self.accent)?,
+            link: color("link", self.link)?,
+            code: color("code", self.code)?,
+            math: color("math", self.math)?,
+            label: color("label", self.label)?,
+            checked: color("checked", self.checked)?,
+            alerts: [
+                color("note", self.note)?,
+                color("tip", self.tip)?,
+                color("important", self.important)?,
+                color("warning", self.warning)?,
+                color("caution", self.caution)?,
+            ],
+        })
+    }
+}
+
+impl StyleSection {
+    /// Heading colors from `style.h1` through `style.h6`
+    fn heading_colors(&self) -> io::Result<[Option<Color>; 6]> {
+        let headings = [&self.h1, &self.h2, &self.h3, &self.h4, &self.h5, &self.h6];
+        let mut colors = [None; 6];
+        for (i, section) in headings.into_iter().enumerate() {
+            if let Some(color) = section.as_ref().and_then(|section| section.color.as_ref()) {
+                colors[i] = Some(parse_color(color).ok_or_else(|| {
+                    invalid_data(format!("invalid color '{}' for style.h{}", color, i + 1))
+                })?);
+            }
+        }
+        Ok(colors)
+    }
+
+    fn into_style(self) -> io::Result<TerminalStyle> {
+        let mut style = TerminalStyle::default();
+
+        let headings = [self.h1, self.h2, self.h3, self.h4, self.h5, self.h6];
+        for (i, section) in headings.into_iter().enumerate() {
+            if let Some(prefix) = section.and_then(|section| section.prefix) {
+                style.headings[i].prefix = prefix;
+            }
+        }
+
+        if let Some(bullets) = self.bullets {
+            if bullets.is_empty() {
+                return Err(invalid_data("style.bullets must not be empty".to_string()));
+            }
+            style.bullets = bullets;
+        }
+
+        if let Some(rule) = self.rule {
+            style.rule = match rule.as_str() {
+                "heavy" => RuleStyle::Heavy,
+                "light" => RuleStyle::Light,
+                "dots" => RuleStyle::Dots,
+                _ => {
+                    return Err(invalid_data(format!(
+                        "invalid style.rule '{}' (expected heavy, light, or dots)",
+                        rule
+                    )));
+                }
+            };
+        }
+
+        Ok(style)
+    }
+}
+
+/// Parse a color name (`magenta`, `dark_grey`, ...), ANSI value (`ansi_(208)`) or `#rrggbb`
+fn parse_color(value: &str) -> Option<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    Color::try_from(value.to_lowercase().as_str()).ok()
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Port for browser mode when neither `--port` nor `MDP_PORT` is given
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// Defaults from `MDP_*` environment variables. Precedence is command line flag,
+/// then environment variable, then built-in default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvDefaults {
+    /// `MDP_THEME`, for `--theme`
+    pub theme: Option<String>,
+    /// `MDP_PORT`, for `--port`
+    pub port: Option<u16>,
+    /// `MDP_PAGER`, else `PAGER`
+    pub pager: Option<String>,
+    /// `MDP_NO_PAGER` set to anything but empty, `0` or `false`, for `--no-pager`
+    pub no_pager: bool,
+}
+
+impl EnvDefaults {
+    /// Read the defaults from the process environment
+    pub fn from_env() -> io::Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Read the defaults through `var`; empty variables count as unset
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> io::Result<Self> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+
+        let theme = var("MDP_THEME");
+        if let Some(theme) = &theme
+            && !matches!(theme.as_str(), "dark" | "light" | "auto")
+        {
+            return Err(invalid_data(format!(
+                "invalid MDP_THEME '{}' (expected dark, light, or auto)",
+                theme
+            )));
+        }
+        let port = var("MDP_PORT")
+            .map(|port| {
+                port.parse()
+                    .map_err(|_| invalid_data(format!("invalid MDP_PORT '{}'", port)))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            theme,
+            port,
+            pager: var("MDP_PAGER").or_else(|| var("PAGER")),
+            no_pager: var("MDP_NO_PAGER").is_some_and(|value| value != "0" && value != "false"),
+        })
+    }
+
+    /// Theme from `--theme`, else `MDP_THEME`
+    pub fn theme(&self, flag: Option<String>) -> Option<String> {
+        flag.or_else(|| self.theme.clone())
+    }
+
+    /// Port from `--port`, else `MDP_PORT`, else the default
+    pub fn port(&self, flag: Option<u16>) -> u16 {
+        flag.or(self.port).unwrap_or(DEFAULT_PORT)
+    }
+
+    /// Whether to skip the pager: `--no-pager` or `MDP_NO_PAGER`
+    pub fn no_pager(&self, flag: bool) -> bool {
+        flag || self.no_pager
+    }
+
+    /// Pager command: `MDP_PAGER`, else `PAGER`, else `less`
+    pub fn pager(&self) -> String {
+        self.pager.clone().unwrap_or_else(|| "less".to_string())
+    }
+}
+
+/// Program and arguments of a pager command such as `less -R`, split the way a
+/// shell would. `None` if the command is empty or its quotes are unbalanced.
+pub fn split_pager(pager: &str) -> Option<(String, Vec<String>)> {
+    let mut words = shell_words::split(pager).ok()?.into_iter();
+    Some((words.next()?, words.collect()))
+}
+
+/// Whether `program` is less, by its file name, so it gets mdp's own flags
+pub fn is_less(program: &str) -> bool {
+    Path::new(program)
+        .file_stem()
+        .is_some_and(|name| name == "less")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env_defaults(vars: &[(&str, &str)]) -> io::Result<EnvDefaults> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        EnvDefaults::from_vars(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_env_defaults() {
+        let env = env_defaults(&[
+            ("MDP_THEME", "light"),
+            ("MDP_PORT", "8080"),
+            ("MDP_PAGER", "most"),
+            ("PAGER", "more"),
+            ("MDP_NO_PAGER", "1"),
+        ])
+        .unwrap();
+
+        // Flags win over the environment
+        assert_eq!(env.theme(Some("dark".to_string())).as_deref(), Some("dark"));
+        assert_eq!(env.port(Some(4000)), 4000);
+        // The environment wins over built-in defaults
+        assert_eq!(env.theme(None).as_deref(), Some("light"));
+        assert_eq!(env.port(None), 8080);
+        assert!(env.no_pager(false));
+        assert_eq!(env.pager(), "most");
+
+        // PAGER without MDP_PAGER, then the defaults
+        let env = env_defaults(&[("PAGER", "more"), ("MDP_NO_PAGER", "false")]).unwrap();
+        assert_eq!(env.pager(), "more");
+        assert!(!env.no_pager(false));
+        let env = env_defaults(&[("MDP_THEME", "")]).unwrap();
+        assert_eq!(env, EnvDefaults::default());
+        assert_eq!(env.theme(None), None);
+        assert_eq!(env.port(None), DEFAULT_PORT);
+        assert_eq!(env.pager(), "less");
+    }
+
+    #[test]
+    fn test_split_pager() {
+        assert_eq!(
+            split_pager("less -FRX"),
+            Some(("less".to_string(), vec!["-FRX".to_string()]))
+        );
+        assert_eq!(
+            split_pager("'/opt/my pager/bin/less' -R"),
+            Some(("/opt/my pager/bin/less".to_string(), vec!["-R".to_string()]))
+        );
+        assert_eq!(
+            split_pager("cat -n"),
+            Some(("cat".to_string(), vec!["-n".to_string()]))
+        );
+        assert_eq!(split_pager("  "), None);
+        assert_eq!(split_pager("less 'unclosed"), None);
+
+        assert!(is_less("less"));
+        assert!(is_less("/usr/bin/less"));
+        assert!(!is_less("lesspipe"));
+        assert!(!is_less("bless"));
+        assert!(!is_less("cat"));
+    }
+
+    #[test]
+    fn test_env_defaults_invalid() {
+        let err = env_defaults(&[("MDP_THEME", "blue")]).unwrap_err();
+        assert!(err.to_string().contains("MDP_THEME"));
+        let err = env_defaults(&[("MDP_PORT", "http")]).unwrap_err();
+        assert!(err.to_string().contains("MDP_PORT"));
+    }
+
+    #[test]
+    fn test_parse_style_overrides() {
+        let config = Config::parse(
+            r##"
+[style]
+bullets = ["-", "*"]
+rule = "dots"
+
+[style.h1]
+prefix = "# "
+
+[style.h2]
+color = "#ff8800"
+"##,
+        )
+        .unwrap();
+
+        let defaults = TerminalStyle::default();
+        assert_eq!(config.style.headings[0].prefix, "# ");
+        assert_eq!(config.colors.headings[0], None);
+        assert_eq!(
+            config.colors.headings[1],
+            Some(Color::Rgb {
+                r: 0xff,
+                g: 0x88,
+                b: 0x00
+            })
+        );
+        assert_eq!(config.style.headings[1], defaults.headings[1]);
+        assert_eq!(config.style.headings[2], defaults.headings[2]);
+        assert_eq!(config.style.bullets, vec!["-", "*"]);
+        assert_eq!(config.style.rule, RuleStyle::Dots);
+    }
+
+    #[test]
+    fn test_invalid_color_is_rejected() {
+        let err = Config::parse("[style.h3]\ncolor = \"not-a-color\"").unwrap_err();
+        assert!(err.to_string().contains("style.h3"));
+        assert!(Config::parse("[style]\nbullets = []").is_err());
+        assert!(Config::parse("[style]\nrule = \"wavy\"").is_err());
+        assert!(Config::parse("[style]\nunknown = 1").is_err());
+        let err = Config::parse("[colors]\nlink = \"nope\"").unwrap_err();
+        assert!(err.to_string().contains("colors.link"));
+        assert!(Config::parse("[colors]\nunknown = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_color_overrides() {
+        let config = Config::parse(
+            r##"
+[colors]
+link = "dark_blue"
+warning = "dark_yellow"
+
+[style.h1]
+color = "black"
+"##,
+        )
+        .unwrap();
+
+        let theme = config.colors.apply(TerminalTheme::light());
+        assert_eq!(theme.headings[0], Color::Black);
+        assert_eq!(theme.link, Color::DarkBlue);
+        assert_eq!(theme.alerts[3], Color::DarkYellow);
+        // Everything else comes from the palette
+        assert_eq!(theme.headings[1], TerminalTheme::light().headings[1]);
+        assert_eq!(theme.code, TerminalTheme::light().code);
+        assert_eq!(
+            Config::parse("")
+                .unwrap()
+                .colors
+                .apply(TerminalTheme::dark()),
+            TerminalTheme::dark()
+        );
+    }
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        assert_eq!(Config::parse("").unwrap().style, TerminalStyle::default());
+    }
+}
@@ -0,0 +1,68 @@
+use std::io;
+use std::path::PathBuf;
+
+/// Errors from loading, rendering, and serving markdown
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The file or directory to preview does not exist
+    #[error("path not found: {}", .0.display())]
+    NotFound(PathBuf),
+
+    /// A glob pattern given as a path matched nothing
+    #[error("no files match '{0}'")]
+    NoGlobMatches(String),
+
+    /// A path with glob metacharacters is not a valid pattern
+    #[error("invalid glob pattern '{pattern}': {source}")]
+    InvalidGlob {
+        pattern: String,
+        source: glob::PatternError,
+    },
+
+    /// A directory was given but contains no markdown files
+    #[error("no markdown files found in '{}'", .0.display())]
+    NoMarkdownFiles(PathBuf),
+
+    /// A file or directory exists but could not be read
+    #[error("failed to read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+
+    /// The config file is missing, unreadable, or invalid
+    #[error("failed to load config: {0}")]
+    Config(#[source] io::Error),
+
+    /// No syntax highlighting theme could be loaded
+    #[error("no syntax highlighting theme available for '{0}'")]
+    Theme(String),
+
+    /// Writing rendered output failed
+    #[error("failed to render: {0}")]
+    Render(#[source] io::Error),
+
+    /// `--strict` checks found problems, already reported
+    #[error("{problems} problem(s) found in {files} file(s)")]
+    Lint { problems: usize, files: usize },
+
+    /// The preview server could not listen on its address
+    #[error("failed to bind {addr}: {source}")]
+    Bind { addr: String, source: io::Error },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Classify an I/O error on `path`, separating "does not exist" from other failures
+    pub fn from_io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        if source.kind() == io::ErrorKind::NotFound {
+            Error::NotFound(path.into())
+        } else {
+            Error::Read {
+                path: path.into(),
+                source,
+            }
+        }
+    }
+}
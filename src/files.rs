@@ -1,6 +1,11 @@
+use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use walkdir::WalkDir;
 
+use crate::error::{Error, Result};
+use crate::parser::split_frontmatter;
+
 /// Represents a markdown file with its relative path
 #[derive(Debug, Clone)]
 pub struct MarkdownFile {
@@ -10,138 +15,527 @@ pub struct MarkdownFile {
     pub relative_path: PathBuf,
     /// Display name (filename without extension)
     pub name: String,
+    /// `title` from the file's front matter
+    pub title: Option<String>,
+    /// `tags` from the file's front matter
+    pub tags: Vec<String>,
+    /// Last modification time, when the file system reports one
+    pub modified: Option<SystemTime>,
+}
+
+impl MarkdownFile {
+    fn new(absolute_path: PathBuf, relative_path: PathBuf) -> Self {
+        let name = absolute_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("untitled")
+            .to_string();
+
+        // Unreadable files still get listed; they just have no metadata
+        let frontmatter = read_lossy(&absolute_path)
+            .ok()
+            .and_then(|(content, _)| split_frontmatter(&content).0);
+        let title = frontmatter
+            .as_ref()
+            .and_then(|f| f.title())
+            .map(String::from);
+        let tags = frontmatter.map(|f| f.tags()).unwrap_or_default();
+        let modified = modified_time(&absolute_path);
+
+        MarkdownFile {
+            absolute_path,
+            relative_path,
+            name,
+            title,
+            tags,
+            modified,
+        }
+    }
+
+    /// Last modification as seconds since the Unix epoch
+    pub fn modified_secs(&self) -> Option<u64> {
+        self.modified
+            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_secs())
+    }
+
+    /// Whether the file was modified within `window` of now (a time in the future counts)
+    pub fn modified_within(&self, window: Duration) -> bool {
+        self.modified.is_some_and(
+            |modified| match SystemTime::now().duration_since(modified) {
+                Ok(age) => age <= window,
+                Err(_) => true,
+            },
+        )
+    }
+}
+
+/// Last modification time of a file, if it can be read
+pub fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Parse a duration such as `90s`, `30m`, `24h`, `7d`, or `2w`
+pub fn parse_duration(text: &str) -> std::result::Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => 0,
+    };
+    match number.parse::<u64>() {
+        Ok(number) if seconds > 0 => Ok(Duration::from_secs(number.saturating_mul(seconds))),
+        _ => Err("expected a number followed by s, m, h, d, or w (e.g. 24h, 7d)".to_string()),
+    }
+}
+
+/// Date of `time` in the local timezone, as `YYYY-MM-DD`
+pub fn format_date(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// Read a markdown file for rendering.
+/// Invalid UTF-8 is replaced with U+FFFD and reported as a warning instead of failing the read.
+pub fn read_markdown(path: &Path) -> io::Result<String> {
+    let (content, lossy) = read_lossy(path)?;
+    warn_if_lossy(path, lossy);
+    Ok(content)
+}
+
+/// Warn that a file read with `read_lossy` had invalid UTF-8 replaced
+pub(crate) fn warn_if_lossy(path: &Path, lossy: bool) {
+    if lossy {
+        crate::warning!(
+            "Warning: {} is not valid UTF-8; invalid bytes are shown as \u{FFFD}",
+            path.display()
+        );
+    }
+}
+
+/// Read a file as UTF-8, also returning whether any bytes had to be replaced
+pub(crate) fn read_lossy(path: &Path) -> io::Result<(String, bool)> {
+    let bytes = std::fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(content) => (content, false),
+        Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), true),
+    })
+}
+
+/// File extensions recognized as markdown unless more are configured
+pub const DEFAULT_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+/// The default markdown extensions as owned strings
+pub fn default_extensions() -> Vec<String> {
+    DEFAULT_EXTENSIONS
+        .iter()
+        .map(|ext| ext.to_string())
+        .collect()
+}
+
+/// Check whether a path has one of the given markdown extensions (without the dot)
+pub fn is_markdown_path(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .is_some_and(|ext| extensions.iter().any(|known| ext == known.as_str()))
+}
+
+/// Expand path arguments that don't exist but contain glob metacharacters
+/// (`docs/**/*.md`) into the matching paths, sorted. Existing paths are kept as
+/// given; a missing path without metacharacters is `Error::NotFound`.
+pub fn expand_globs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for path in paths {
+        if path.exists() {
+            expanded.push(path.clone());
+            continue;
+        }
+        let pattern = path.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) {
+            return Err(Error::NotFound(path.clone()));
+        }
+        let matches = glob::glob(&pattern).map_err(|source| Error::InvalidGlob {
+            pattern: pattern.to_string(),
+            source,
+        })?;
+        let mut matches: Vec<PathBuf> = matches.filter_map(|entry| entry.ok()).collect();
+        if matches.is_empty() {
+            return Err(Error::NoGlobMatches(pattern.to_string()));
+        }
+        matches.sort();
+        expanded.extend(matches);
+    }
+    Ok(expanded)
+}
+
+/// Resolve `path` to an absolute path, reporting a missing path as `Error::NotFound`
+fn canonicalize(path: &Path) -> Result<PathBuf> {
+    path.canonicalize().map_err(|e| Error::from_io(path, e))
+}
+
+/// Options for building a [`FileTree`] from paths given on the command line
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Extensions recognized as markdown when scanning directories
+    pub extensions: Vec<String>,
+    /// A lone file brings the other markdown files of its directory along,
+    /// with itself as the default
+    pub context: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            extensions: default_extensions(),
+            context: false,
+        }
+    }
+}
+
+/// A directory (or explicitly listed file) scanned into a tree
+#[derive(Debug, Clone)]
+pub struct TreeRoot {
+    /// Absolute path of the directory or file
+    pub path: PathBuf,
+    /// Prefix of its files' relative paths: empty for a single root,
+    /// the directory or file name when several roots are merged
+    pub prefix: PathBuf,
+}
+
+/// Relative path of `rest` under a root's `prefix`; a file root is the prefix itself
+fn join_prefix(prefix: &Path, rest: &Path) -> PathBuf {
+    if rest.as_os_str().is_empty() {
+        prefix.to_path_buf()
+    } else {
+        prefix.join(rest)
+    }
 }
 
 /// Represents a directory structure of markdown files
 #[derive(Debug, Clone)]
 pub struct FileTree {
-    /// Base directory path
+    /// Base directory path (the common ancestor when several directories are merged)
     pub base_path: PathBuf,
     /// All markdown files found
     pub files: Vec<MarkdownFile>,
+    /// Extensions recognized as markdown when scanning
+    pub extensions: Vec<String>,
+    /// Scanned directories and listed files; empty for a single file
+    pub roots: Vec<TreeRoot>,
+    /// Relative path of an explicitly chosen default file, kept first across rescans
+    pub default_path: Option<PathBuf>,
 }
 
 impl FileTree {
     /// Create a FileTree from a directory path
-    pub fn from_directory(path: &Path) -> std::io::Result<Self> {
-        let base_path = path.canonicalize()?;
-        let mut files = Vec::new();
-
-        // Don't follow symlinks to avoid infinite loops with circular symlinks
-        for entry in WalkDir::new(&base_path)
-            .follow_links(false)
-            .into_iter()
-            .filter_map(|e| e.ok())
+    pub fn from_directory(path: &Path) -> Result<Self> {
+        Self::from_directory_with_extensions(path, &default_extensions())
+    }
+
+    /// Create a FileTree from a directory path, recognizing the given markdown extensions
+    pub fn from_directory_with_extensions(path: &Path, extensions: &[String]) -> Result<Self> {
+        Self::from_directories(&[path.to_path_buf()], extensions)
+    }
+
+    /// Create a FileTree merging several directories. With more than one, each
+    /// directory's files are grouped under its name (`notes/todo.md`, `blog/post.md`).
+    pub fn from_directories(paths: &[PathBuf], extensions: &[String]) -> Result<Self> {
+        Self::from_paths(
+            paths,
+            &ScanOptions {
+                extensions: extensions.to_vec(),
+                context: false,
+            },
+        )
+    }
+
+    /// Create a FileTree from any mix of files and directories. A lone file is shown
+    /// by itself (or with its directory under `options.context`); otherwise each path
+    /// becomes a root: directories are grouped under their names when there are
+    /// several, and listed files keep their path below the common base directory
+    /// whatever their extension.
+    pub fn from_paths(paths: &[PathBuf], options: &ScanOptions) -> Result<Self> {
+        if let [path] = paths
+            && !path.is_dir()
         {
-            let entry_path = entry.path();
+            return Self::single_file(path, options);
+        }
 
-            // Skip directories and non-markdown files
-            if entry_path.is_dir() {
+        let mut roots: Vec<TreeRoot> = Vec::new();
+        for path in paths {
+            let path = canonicalize(path)?;
+            if roots.iter().any(|root| root.path == path) {
                 continue;
             }
+            roots.push(TreeRoot {
+                path,
+                prefix: PathBuf::new(),
+            });
+        }
+        // A listed file inside a listed directory is already covered by it
+        let dirs: Vec<PathBuf> = roots
+            .iter()
+            .filter(|root| root.path.is_dir())
+            .map(|root| root.path.clone())
+            .collect();
+        roots.retain(|root| {
+            root.path.is_dir() || !dirs.iter().any(|dir| root.path.starts_with(dir))
+        });
+
+        let base_path = roots
+            .iter()
+            .map(|root| match root.path.parent() {
+                Some(parent) if !root.path.is_dir() => parent.to_path_buf(),
+                _ => root.path.clone(),
+            })
+            .reduce(|ancestor, path| {
+                ancestor
+                    .ancestors()
+                    .find(|a| path.starts_with(a))
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+
+        if roots.len() > 1 {
+            let mut used: Vec<PathBuf> = Vec::new();
+            for root in &mut roots {
+                // Listed files keep their path below the common base directory
+                if !root.path.is_dir()
+                    && let Ok(relative) = root.path.strip_prefix(&base_path)
+                {
+                    root.prefix = relative.to_path_buf();
+                    continue;
+                }
+                let name = root
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "root".to_string());
+                let mut prefix = PathBuf::from(&name);
+                let mut n = 2;
+                while used.contains(&prefix) {
+                    prefix = PathBuf::from(format!("{}-{}", name, n));
+                    n += 1;
+                }
+                used.push(prefix.clone());
+                root.prefix = prefix;
+            }
+        }
+
+        let mut tree = FileTree {
+            base_path,
+            files: Vec::new(),
+            extensions: options.extensions.clone(),
+            roots,
+            default_path: None,
+        };
+        tree.scan();
+        Ok(tree)
+    }
 
-            if let Some(ext) = entry_path.extension()
-                && (ext == "md" || ext == "markdown")
+    /// A fresh scan of the same roots, e.g. after directories were added or removed
+    pub fn rescan(&self) -> Result<Self> {
+        let mut tree = self.clone();
+        for root in &tree.roots {
+            if !root.path.exists() {
+                return Err(Error::NotFound(root.path.clone()));
+            }
+        }
+        tree.scan();
+        Ok(tree)
+    }
+
+    /// Collect the markdown files under every root
+    fn scan(&mut self) {
+        self.files.clear();
+        for root in &self.roots {
+            // Don't follow symlinks to avoid infinite loops with circular symlinks
+            for entry in WalkDir::new(&root.path)
+                .follow_links(false)
+                .into_iter()
+                .filter_map(|e| e.ok())
             {
-                let relative_path = entry_path
-                    .strip_prefix(&base_path)
-                    .unwrap_or(entry_path)
-                    .to_path_buf();
-
-                let name = entry_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("untitled")
-                    .to_string();
-
-                files.push(MarkdownFile {
-                    absolute_path: entry_path.to_path_buf(),
-                    relative_path,
-                    name,
-                });
+                let entry_path = entry.path();
+
+                // Skip directories and non-markdown files, except a listed file itself
+                if entry_path.is_dir()
+                    || (entry.depth() > 0 && !is_markdown_path(entry_path, &self.extensions))
+                {
+                    continue;
+                }
+                // Files of a nested root are listed under that root
+                if self.root_of(entry_path).map(|r| &r.path) != Some(&root.path) {
+                    continue;
+                }
+
+                let relative_path = join_prefix(
+                    &root.prefix,
+                    entry_path.strip_prefix(&root.path).unwrap_or(entry_path),
+                );
+                self.files
+                    .push(MarkdownFile::new(entry_path.to_path_buf(), relative_path));
             }
         }
+        self.sort_files();
+    }
+
+    /// The innermost root containing `path`
+    fn root_of(&self, path: &Path) -> Option<&TreeRoot> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(&root.path))
+            .max_by_key(|root| root.path.components().count())
+    }
+
+    /// Relative path in this tree of an absolute path, if it is inside the tree
+    pub fn relative_path_of(&self, path: &Path) -> Option<PathBuf> {
+        match self.root_of(path) {
+            Some(root) => Some(join_prefix(
+                &root.prefix,
+                path.strip_prefix(&root.path).ok()?,
+            )),
+            None if self.roots.is_empty() => path
+                .strip_prefix(&self.base_path)
+                .ok()
+                .map(Path::to_path_buf),
+            None => None,
+        }
+    }
 
-        // Sort files: README first, then alphabetically
-        files.sort_by(|a, b| {
+    /// Sort files: the default file set by `--open` or `--index` first, then README,
+    /// then alphabetically
+    fn sort_files(&mut self) {
+        let default_path = self.default_path.clone();
+        self.files.sort_by(|a, b| {
+            let a_is_default = default_path.as_ref() == Some(&a.relative_path);
+            let b_is_default = default_path.as_ref() == Some(&b.relative_path);
             let a_is_readme = a.name.to_lowercase() == "readme";
             let b_is_readme = b.name.to_lowercase() == "readme";
 
-            match (a_is_readme, b_is_readme) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.relative_path.cmp(&b.relative_path),
-            }
+            b_is_default
+                .cmp(&a_is_default)
+                .then(b_is_readme.cmp(&a_is_readme))
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
         });
-
-        Ok(FileTree { base_path, files })
     }
 
-    /// Create a FileTree from a single file
-    pub fn from_file(path: &Path) -> std::io::Result<Self> {
-        let absolute_path = path.canonicalize()?;
-        let base_path = absolute_path
-            .parent()
-            .unwrap_or(&absolute_path)
-            .to_path_buf();
+    /// Update the tree in place from changed absolute paths (e.g. file watcher events).
+    /// Markdown files are added or removed individually. Returns `false` when a
+    /// directory was created or removed, in which case the caller should rescan.
+    pub fn apply_changes(&mut self, changed: &[PathBuf]) -> bool {
+        for path in changed {
+            let Some(relative_path) = self.relative_path_of(path) else {
+                continue;
+            };
+            let relative_path = relative_path.as_path();
 
-        let relative_path = absolute_path
-            .file_name()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| absolute_path.clone());
+            if path.is_dir() {
+                return false;
+            }
 
-        let name = absolute_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("untitled")
-            .to_string();
+            if !is_markdown_path(path, &self.extensions) {
+                // A removed directory takes its markdown files with it
+                let removed_dir = !path.exists()
+                    && self
+                        .files
+                        .iter()
+                        .any(|f| f.relative_path.starts_with(relative_path));
+                if removed_dir {
+                    return false;
+                }
+                continue;
+            }
 
-        let files = vec![MarkdownFile {
-            absolute_path,
-            relative_path,
-            name,
-        }];
+            // Modified files are re-read too, so front matter edits are picked up
+            self.files.retain(|f| f.relative_path != relative_path);
+            if path.is_file() {
+                self.files
+                    .push(MarkdownFile::new(path.clone(), relative_path.to_path_buf()));
+            }
+        }
 
-        Ok(FileTree { base_path, files })
+        self.sort_files();
+        true
+    }
+
+    /// Create a FileTree from a single file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::from_paths(&[path.to_path_buf()], &ScanOptions::default())
     }
 
     /// Create a FileTree from a file with context (sibling/child markdown files)
     /// This scans the file's parent directory recursively for related markdown files
-    pub fn from_file_with_context(path: &Path) -> std::io::Result<Self> {
-        let absolute_path = path.canonicalize()?;
+    pub fn from_file_with_context(path: &Path, extensions: &[String]) -> Result<Self> {
+        Self::from_paths(
+            &[path.to_path_buf()],
+            &ScanOptions {
+                extensions: extensions.to_vec(),
+                context: true,
+            },
+        )
+    }
+
+    /// Tree for one file given on its own, optionally with its directory's files
+    fn single_file(path: &Path, options: &ScanOptions) -> Result<Self> {
+        let absolute_path = canonicalize(path)?;
         let base_path = absolute_path
             .parent()
             .unwrap_or(&absolute_path)
             .to_path_buf();
 
-        // Use from_directory to get all markdown files in the parent directory
-        let mut tree = Self::from_directory(&base_path)?;
+        if options.context {
+            // Scan the parent directory recursively for related markdown files
+            let mut tree = Self::from_directories(&[base_path], &options.extensions)?;
+
+            // Ensure the specified file is the default (first in list), adding it
+            // when its extension kept it out of the scan
+            if let Some(name) = absolute_path.file_name()
+                && !tree.set_default_file(&name.to_string_lossy())
+            {
+                tree.files.push(MarkdownFile::new(
+                    absolute_path.clone(),
+                    PathBuf::from(name),
+                ));
+                tree.set_default_file(&name.to_string_lossy());
+            }
+            return Ok(tree);
+        }
 
-        // Ensure the specified file is the default (first in list)
-        let target_relative = absolute_path
+        let relative_path = absolute_path
             .file_name()
             .map(PathBuf::from)
             .unwrap_or_else(|| absolute_path.clone());
 
-        // Re-sort: specified file first, then README, then alphabetically
-        tree.files.sort_by(|a, b| {
-            let a_is_target = a.relative_path == target_relative;
-            let b_is_target = b.relative_path == target_relative;
-            let a_is_readme = a.name.to_lowercase() == "readme";
-            let b_is_readme = b.name.to_lowercase() == "readme";
+        let files = vec![MarkdownFile::new(absolute_path, relative_path)];
 
-            match (a_is_target, b_is_target) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => match (a_is_readme, b_is_readme) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a.relative_path.cmp(&b.relative_path),
-                },
-            }
-        });
+        Ok(FileTree {
+            base_path,
+            files,
+            extensions: options.extensions.clone(),
+            roots: Vec::new(),
+            default_path: None,
+        })
+    }
 
-        Ok(tree)
+    /// Make the file at `relative_path` the default by moving it to the front.
+    /// Returns `false` (leaving the tree unchanged) if no such file exists.
+    pub fn set_default_file(&mut self, relative_path: &str) -> bool {
+        match self.position(relative_path) {
+            Some(index) => {
+                self.default_path = Some(self.files[index].relative_path.clone());
+                self.sort_files();
+                true
+            }
+            None => false,
+        }
     }
 
     /// Get the default file to display (README or first file)
@@ -149,6 +543,26 @@ impl FileTree {
         self.files.first()
     }
 
+    /// File to show as the landing page: the explicitly chosen default file, or a
+    /// top-level README or index. `None` when a directory has neither.
+    pub fn index_file(&self) -> Option<&MarkdownFile> {
+        if self.roots.is_empty() || self.default_path.is_some() {
+            return self.default_file();
+        }
+        let top_level = |file: &&MarkdownFile| match file.relative_path.parent() {
+            Some(parent) => {
+                parent.as_os_str().is_empty() || self.roots.iter().any(|r| r.prefix == parent)
+            }
+            None => true,
+        };
+        ["readme", "index"].iter().find_map(|name| {
+            self.files
+                .iter()
+                .filter(top_level)
+                .find(|f| f.name.to_lowercase() == *name)
+        })
+    }
+
     /// Find a file by its relative path
     /// Normalizes the path to handle cases like "./a.md" vs "a.md"
     /// Rejects paths containing ".." segments for security (path traversal prevention)
@@ -156,6 +570,11 @@ impl FileTree {
     /// Note: This check assumes the input is NOT URL-encoded. If URL-encoded paths
     /// (e.g., "%2e%2e" for "..") need to be handled, the caller should decode them first.
     pub fn find_file(&self, relative_path: &str) -> Option<&MarkdownFile> {
+        self.position(relative_path).map(|index| &self.files[index])
+    }
+
+    /// Index of the file at `relative_path`, with the same checks as `find_file`
+    fn position(&self, relative_path: &str) -> Option<usize> {
         // Security: reject paths with ".." as a path segment to prevent directory traversal
         // Check both / and \ as separators, also check start/end of string
         let normalized_for_check = relative_path.replace('\\', "/");
@@ -172,7 +591,7 @@ impl FileTree {
             .trim_start_matches(".\\")
             .replace('\\', "/");
 
-        self.files.iter().find(|f| {
+        self.files.iter().position(|f| {
             let file_path = f.relative_path.to_string_lossy().replace('\\', "/");
             file_path == normalized_input
         })
@@ -190,6 +609,46 @@ mod tests {
     use std::fs;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("24h"), Ok(Duration::from_secs(86_400)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(604_800)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(1_209_600)));
+        for invalid in ["", "7", "d", "1.5h", "-1d", "3y"] {
+            assert!(parse_duration(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_modified_within() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "# A").unwrap();
+        let mut file = MarkdownFile::new(path.clone(), PathBuf::from("a.md"));
+        assert!(file.modified_within(Duration::from_secs(3600)));
+
+        file.modified = Some(SystemTime::now() - Duration::from_secs(7200));
+        assert!(!file.modified_within(Duration::from_secs(3600)));
+        file.modified = None;
+        assert!(!file.modified_within(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_read_markdown_invalid_utf8() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("latin1.md");
+        // "# Caf\xe9" in Latin-1
+        fs::write(&path, b"# Caf\xe9\n\nna\xefve text\n").unwrap();
+
+        let content = read_markdown(&path).unwrap();
+        assert_eq!(content, "# Caf\u{FFFD}\n\nna\u{FFFD}ve text\n");
+
+        let html = crate::renderer::html::HtmlRenderer::new("Test").render_content(&content);
+        assert!(html.contains("Caf\u{FFFD}</h1>"));
+        assert!(html.contains("ve text"));
+    }
+
     #[test]
     fn test_from_directory() {
         let dir = tempdir().unwrap();
@@ -209,4 +668,337 @@ mod tests {
         // README should be first
         assert_eq!(tree.files[0].name, "README");
     }
+
+    #[test]
+    fn test_missing_path_is_not_found() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("missing.md");
+
+        let err = FileTree::from_file(&missing).unwrap_err();
+        assert!(matches!(&err, Error::NotFound(path) if *path == missing));
+        assert!(matches!(
+            FileTree::from_directory(&dir.path().join("missing")),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_frontmatter_metadata() {
+        let dir = tempdir().unwrap();
+        let post = dir.path().join("post.md");
+        fs::write(&post, "---\ntitle: First Post\ntags: [rust]\n---\n# Post").unwrap();
+
+        let mut tree = FileTree::from_directory(dir.path()).unwrap();
+        assert_eq!(tree.files[0].title.as_deref(), Some("First Post"));
+        assert_eq!(tree.files[0].tags, ["rust"]);
+
+        // Edits are picked up by watch updates
+        fs::write(&post, "---\ntags: [rust, cli]\n---\n# Post").unwrap();
+        assert!(tree.apply_changes(&[post.canonicalize().unwrap()]));
+        assert_eq!(tree.files.len(), 1);
+        assert_eq!(tree.files[0].title, None);
+        assert_eq!(tree.files[0].tags, ["rust", "cli"]);
+    }
+
+    #[test]
+    fn test_modified_time() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "# A").unwrap();
+        // Midday UTC, so the local date is the same in nearly every timezone
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_717_243_200);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+
+        let tree = FileTree::from_file(&path).unwrap();
+        assert_eq!(tree.files[0].modified, Some(time));
+        assert_eq!(format_date(time), "2024-06-01");
+    }
+
+    #[test]
+    fn test_from_file_with_context_opens_file_first() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "# README").unwrap();
+        fs::write(dir.path().join("a.md"), "# A").unwrap();
+        fs::write(dir.path().join("guide.md"), "# Guide").unwrap();
+
+        let tree =
+            FileTree::from_file_with_context(&dir.path().join("guide.md"), &default_extensions())
+                .unwrap();
+        let names: Vec<_> = tree.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["guide", "README", "a"]);
+        assert_eq!(tree.base_path, dir.path().canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_extra_extensions() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "# README").unwrap();
+        fs::write(dir.path().join("page.mdx"), "# Page").unwrap();
+
+        let tree = FileTree::from_directory(dir.path()).unwrap();
+        assert_eq!(tree.files.len(), 1);
+
+        let mut extensions = default_extensions();
+        extensions.push("mdx".to_string());
+        let mut tree = FileTree::from_directory_with_extensions(dir.path(), &extensions).unwrap();
+        assert!(tree.find_file("page.mdx").is_some());
+
+        // Watch updates use the same extensions
+        let new_file = dir.path().canonicalize().unwrap().join("new.mdx");
+        fs::write(&new_file, "# New").unwrap();
+        assert!(tree.apply_changes(&[new_file]));
+        assert!(tree.find_file("new.mdx").is_some());
+    }
+
+    #[test]
+    fn test_set_default_file() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "# README").unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs/guide.md"), "# Guide").unwrap();
+
+        let mut tree = FileTree::from_directory(dir.path()).unwrap();
+        assert!(tree.set_default_file("./docs/guide.md"));
+        assert_eq!(
+            tree.default_file().unwrap().relative_path,
+            PathBuf::from("docs/guide.md")
+        );
+        assert_eq!(tree.files.len(), 2);
+
+        assert!(!tree.set_default_file("missing.md"));
+        assert!(!tree.set_default_file("../README.md"));
+        assert_eq!(tree.default_file().unwrap().name, "guide");
+    }
+
+    #[test]
+    fn test_apply_changes_adds_and_removes_files() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "# README").unwrap();
+        let mut tree = FileTree::from_directory(dir.path()).unwrap();
+        let base = tree.base_path.clone();
+
+        // Only reported paths are touched: an unreported new file stays unknown
+        fs::write(base.join("b.md"), "# B").unwrap();
+        fs::write(base.join("unreported.md"), "# U").unwrap();
+        assert!(tree.apply_changes(&[base.join("b.md")]));
+        let names: Vec<&str> = tree.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["README", "b"]);
+
+        fs::remove_file(base.join("b.md")).unwrap();
+        assert!(tree.apply_changes(&[base.join("b.md"), base.join("image.png")]));
+        assert_eq!(tree.files.len(), 1);
+        assert!(tree.find_file("b.md").is_none());
+    }
+
+    #[test]
+    fn test_apply_changes_requests_rescan_for_directories() {
+        let dir = tempdir().unwrap();
+        let docs = dir.path().join("docs");
+        fs::create_dir(&docs).unwrap();
+        fs::write(docs.join("api.md"), "# API").unwrap();
+        let mut tree = FileTree::from_directory(dir.path()).unwrap();
+        let base = tree.base_path.clone();
+
+        fs::create_dir(base.join("guides")).unwrap();
+        assert!(!tree.apply_changes(&[base.join("guides")]));
+
+        fs::remove_dir_all(base.join("docs")).unwrap();
+        assert!(!tree.apply_changes(&[base.join("docs")]));
+    }
+
+    #[test]
+    fn test_from_directories() {
+        let dir = tempdir().unwrap();
+        let notes = dir.path().join("notes");
+        let blog = dir.path().join("site/blog");
+        fs::create_dir(&notes).unwrap();
+        fs::create_dir_all(&blog).unwrap();
+        fs::write(notes.join("todo.md"), "# Todo").unwrap();
+        fs::write(blog.join("post.md"), "# Post").unwrap();
+
+        let mut tree =
+            FileTree::from_directories(&[notes.clone(), blog.clone()], &default_extensions())
+                .unwrap();
+        assert_eq!(tree.base_path, dir.path().canonicalize().unwrap());
+        let paths: Vec<_> = tree.files.iter().map(|f| f.relative_path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("blog/post.md"),
+                PathBuf::from("notes/todo.md")
+            ]
+        );
+        assert!(tree.find_file("notes/todo.md").is_some());
+
+        // Changes map back through the root the file lives under
+        let draft = blog.canonicalize().unwrap().join("draft.md");
+        fs::write(&draft, "# Draft").unwrap();
+        assert!(tree.apply_changes(std::slice::from_ref(&draft)));
+        assert_eq!(
+            tree.relative_path_of(&draft),
+            Some(PathBuf::from("blog/draft.md"))
+        );
+        assert!(tree.find_file("blog/draft.md").is_some());
+        assert_eq!(tree.rescan().unwrap().files.len(), 3);
+    }
+
+    #[test]
+    fn test_index_file() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs/README.md"), "# Docs").unwrap();
+        fs::write(dir.path().join("a.md"), "# A").unwrap();
+        let mut tree = FileTree::from_directory(dir.path()).unwrap();
+        // A README in a subdirectory is not a landing page
+        assert!(tree.index_file().is_none());
+
+        fs::write(dir.path().join("index.md"), "# Index").unwrap();
+        tree = tree.rescan().unwrap();
+        assert_eq!(tree.index_file().unwrap().name, "index");
+
+        // An explicit default wins and survives rescans
+        assert!(tree.set_default_file("a.md"));
+        tree = tree.rescan().unwrap();
+        assert_eq!(tree.index_file().unwrap().name, "a");
+        assert_eq!(tree.default_file().unwrap().name, "a");
+    }
+
+    #[test]
+    fn test_from_directories_same_name() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a/docs");
+        let b = dir.path().join("b/docs");
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(a.join("x.md"), "# X").unwrap();
+        fs::write(b.join("x.md"), "# X").unwrap();
+
+        let tree = FileTree::from_directories(&[a, b], &default_extensions()).unwrap();
+        let paths: Vec<_> = tree.files.iter().map(|f| f.relative_path.clone()).collect();
+        assert_eq!(
+            paths,
+            [PathBuf::from("docs/x.md"), PathBuf::from("docs-2/x.md")]
+        );
+    }
+
+    #[test]
+    fn test_from_paths_mixed() {
+        let dir = tempdir().unwrap();
+        let notes = dir.path().join("notes");
+        fs::create_dir(&notes).unwrap();
+        fs::write(notes.join("todo.md"), "# Todo").unwrap();
+        fs::write(notes.join("skip.txt"), "not markdown").unwrap();
+        fs::write(dir.path().join("CHANGELOG.md"), "# Changes").unwrap();
+        fs::write(dir.path().join("plan.txt"), "# Plan").unwrap();
+
+        let paths = [
+            notes.clone(),
+            dir.path().join("CHANGELOG.md"),
+            dir.path().join("plan.txt"),
+            notes.join("todo.md"),
+        ];
+        let mut tree = FileTree::from_paths(&paths, &ScanOptions::default()).unwrap();
+        assert_eq!(tree.base_path, dir.path().canonicalize().unwrap());
+        assert_eq!(tree.roots.len(), 3);
+
+        // Listed files keep their path below the common base, whatever the
+        // extension, and a file inside a listed directory stays in it
+        let paths: Vec<_> = tree.files.iter().map(|f| f.relative_path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("CHANGELOG.md"),
+                PathBuf::from("notes/todo.md"),
+                PathBuf::from("plan.txt"),
+            ]
+        );
+        assert!(tree.index_file().is_none());
+
+        // Watcher changes and rescans map back through the listed file
+        let changelog = dir.path().canonicalize().unwrap().join("CHANGELOG.md");
+        assert_eq!(
+            tree.relative_path_of(&changelog),
+            Some(PathBuf::from("CHANGELOG.md"))
+        );
+        fs::remove_file(&changelog).unwrap();
+        assert!(tree.apply_changes(std::slice::from_ref(&changelog)));
+        assert!(tree.find_file("CHANGELOG.md").is_none());
+        assert!(matches!(tree.rescan(), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_from_paths_single_file() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.md"), "# A").unwrap();
+        fs::write(dir.path().join("notes.txt"), "# Notes").unwrap();
+        let file = dir.path().join("notes.txt");
+
+        let tree =
+            FileTree::from_paths(std::slice::from_ref(&file), &ScanOptions::default()).unwrap();
+        assert!(tree.is_single_file());
+        assert!(tree.roots.is_empty());
+
+        // With context, the file is first even though the scan skips its extension
+        let options = ScanOptions {
+            context: true,
+            ..ScanOptions::default()
+        };
+        let tree = FileTree::from_paths(&[file], &options).unwrap();
+        let names: Vec<_> = tree.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["notes", "a"]);
+    }
+
+    #[test]
+    fn test_expand_globs() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("docs/sub")).unwrap();
+        fs::write(dir.path().join("docs/b.md"), "# B").unwrap();
+        fs::write(dir.path().join("docs/sub/a.md"), "# A").unwrap();
+        fs::write(dir.path().join("docs/notes.txt"), "notes").unwrap();
+        let pattern = dir.path().join("docs/**/*.md");
+
+        let paths = expand_globs(std::slice::from_ref(&pattern)).unwrap();
+        assert_eq!(
+            paths,
+            [
+                dir.path().join("docs/b.md"),
+                dir.path().join("docs/sub/a.md")
+            ]
+        );
+
+        // Matches keep their layout below the directory they share
+        let tree = FileTree::from_paths(&paths, &ScanOptions::default()).unwrap();
+        assert_eq!(
+            tree.base_path,
+            dir.path().join("docs").canonicalize().unwrap()
+        );
+        let relative: Vec<_> = tree.files.iter().map(|f| f.relative_path.clone()).collect();
+        assert_eq!(relative, [PathBuf::from("b.md"), PathBuf::from("sub/a.md")]);
+
+        // Existing paths are kept literally, even with metacharacters
+        let literal = dir.path().join("docs/[draft].md");
+        fs::write(&literal, "# Draft").unwrap();
+        assert_eq!(
+            expand_globs(std::slice::from_ref(&literal)).unwrap(),
+            [literal]
+        );
+
+        assert!(matches!(
+            expand_globs(&[dir.path().join("missing/*.md")]),
+            Err(Error::NoGlobMatches(_))
+        ));
+        assert!(matches!(
+            expand_globs(&[dir.path().join("missing.md")]),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            expand_globs(&[dir.path().join("[.md")]),
+            Err(Error::InvalidGlob { .. })
+        ));
+    }
 }
@@ -0,0 +1,183 @@
+//! Discovery of running browser-mode servers, so `--reuse` can hand a file to one
+//! instead of starting another
+
+use serde::{Deserialize, Serialize};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long to wait for a recorded server to answer before treating it as gone
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A running server, recorded in the discovery directory while it is up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instance {
+    pub port: u16,
+    pub pid: u32,
+    /// Base directory of the served file tree
+    pub base_path: PathBuf,
+}
+
+/// Body of `POST /api/open`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenRequest {
+    pub base_path: PathBuf,
+    /// Relative path of the file to show, or the default file
+    pub file: Option<String>,
+}
+
+/// Response to `POST /api/open`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenResponse {
+    /// Whether an open browser tab was told to show the file
+    pub shown: bool,
+}
+
+/// Discovery file of a running server, removed when dropped
+pub struct Registration {
+    path: PathBuf,
+    instance: Instance,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        // Leave the file alone if another server has since taken it over
+        if read_instance(&self.path).as_ref() == Some(&self.instance) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Instance {
+    /// A record for this process serving `base_path` on `port`
+    pub fn new(port: u16, base_path: &Path) -> Self {
+        Self {
+            port,
+            pid: std::process::id(),
+            base_path: base_path.to_path_buf(),
+        }
+    }
+
+    /// Where discovery files are kept: `mdp` in the system temp directory
+    pub fn default_dir() -> PathBuf {
+        std::env::temp_dir().join("mdp")
+    }
+
+    /// Write the discovery file for this server into `dir`
+    pub fn register(self, dir: &Path) -> io::Result<Registration> {
+        std::fs::create_dir_all(dir)?;
+        let path = discovery_file(dir, &self.base_path);
+        let json = serde_json::to_string(&self).map_err(io::Error::other)?;
+        std::fs::write(&path, json)?;
+        Ok(Registration {
+            path,
+            instance: self,
+        })
+    }
+
+    /// The server recorded in `dir` for `base_path`, if any. A record left behind
+    /// by a server that no longer answers is removed.
+    pub fn find(dir: &Path, base_path: &Path) -> Option<Self> {
+        let path = discovery_file(dir, base_path);
+        let instance = read_instance(&path).filter(|instance| instance.base_path == base_path)?;
+        if TcpStream::connect_timeout(&instance.addr(), CONNECT_TIMEOUT).is_err() {
+            tracing::debug!("Removing stale discovery file {}", path.display());
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        Some(instance)
+    }
+
+    /// Ask the server to show `file`; `None` if it is not serving that file (or
+    /// not the same tree), so a new server is needed
+    pub fn open(&self, file: Option<&str>) -> Option<OpenResponse> {
+        let body = serde_json::to_string(&OpenRequest {
+            base_path: self.base_path.clone(),
+            file: file.map(str::to_string),
+        })
+        .ok()?;
+        let response = self.post("/api/open", &body).ok()?;
+        let (status, body) = response.split_once("\r\n\r\n")?;
+        if !status.starts_with("HTTP/1.1 200") {
+            tracing::debug!("Running server declined: {}", status.lines().next()?);
+            return None;
+        }
+        serde_json::from_str(body).ok()
+    }
+
+    fn addr(&self) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, self.port))
+    }
+
+    /// Minimal HTTP/1.1 POST, returning the raw response
+    fn post(&self, path: &str, body: &str) -> io::Result<String> {
+        let mut stream = TcpStream::connect_timeout(&self.addr(), CONNECT_TIMEOUT)?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            self.addr(),
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    }
+}
+
+/// One file per base directory, named after a hash of its path
+fn discovery_file(dir: &Path, base_path: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    base_path.hash(&mut hasher);
+    dir.join(format!("{:016x}.json", hasher.finish()))
+}
+
+fn read_instance(path: &Path) -> Option<Instance> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let base = Path::new("/docs/project");
+
+        assert_eq!(Instance::find(dir.path(), base), None);
+        let registration = Instance::new(port, base).register(dir.path()).unwrap();
+        let found = Instance::find(dir.path(), base).unwrap();
+        assert_eq!(found.port, port);
+        assert_eq!(found.pid, std::process::id());
+        assert_eq!(Instance::find(dir.path(), Path::new("/docs/other")), None);
+
+        // Dropping the registration removes the file
+        drop(registration);
+        assert_eq!(Instance::find(dir.path(), base), None);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_stale_instance_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let base = Path::new("/docs/project");
+        let registration = Instance::new(port, base).register(dir.path()).unwrap();
+
+        // Nothing listens on the port any more
+        assert_eq!(Instance::find(dir.path(), base), None);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+        drop(registration);
+    }
+}
@@ -1,5 +1,13 @@
+pub mod config;
+pub mod error;
 pub mod files;
+pub mod instance;
+pub mod lint;
+pub mod manifest;
+pub mod output;
 pub mod parser;
 pub mod renderer;
 pub mod server;
 pub mod watcher;
+
+pub use error::{Error, Result};
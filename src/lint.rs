@@ -0,0 +1,292 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use syntect::parsing::SyntaxSet;
+
+use crate::error::{Error, Result};
+use crate::files::{FileTree, read_lossy};
+use crate::parser::{
+    Document, Element, InlineElement, ParseOptions, generate_toc, parse_markdown_with_options,
+};
+use crate::renderer::DiagramKind;
+
+/// Languages the terminal can highlight, loaded on first use
+static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+/// Code block languages that are plain text on purpose, or that the browser
+/// highlights although the terminal's syntax set lacks them
+const KNOWN_LANGUAGES: &[&str] = &[
+    "text",
+    "plain",
+    "plaintext",
+    "txt",
+    "toml",
+    "ini",
+    "typescript",
+    "ts",
+    "tsx",
+    "jsx",
+    "kotlin",
+    "swift",
+    "dart",
+    "elixir",
+    "zig",
+    "dockerfile",
+    "powershell",
+    "ps1",
+    "nginx",
+    "graphql",
+    "protobuf",
+    "scss",
+    "hcl",
+];
+
+/// A problem found in one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    /// The file, relative to the tree's base directory
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Problems found by `--strict` across all files, reported together: everything
+/// that is otherwise a warning, plus unknown code languages, links to missing
+/// anchors, and missing local images
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    pub issues: Vec<LintIssue>,
+    /// Number of files checked
+    pub files: usize,
+}
+
+impl LintReport {
+    /// Check every file of the tree
+    pub fn check_tree(file_tree: &FileTree, options: ParseOptions) -> Result<Self> {
+        let mut report = Self::default();
+        for file in &file_tree.files {
+            let (content, lossy) = read_lossy(&file.absolute_path)
+                .map_err(|e| Error::from_io(&file.absolute_path, e))?;
+            let dir = file.absolute_path.parent().unwrap_or(Path::new(""));
+            report.check(&file.relative_path, &content, lossy, dir, options);
+        }
+        Ok(report)
+    }
+
+    /// Check one file's content; local images are looked up relative to `dir`
+    pub fn check(
+        &mut self,
+        path: &Path,
+        content: &str,
+        lossy: bool,
+        dir: &Path,
+        options: ParseOptions,
+    ) {
+        self.files += 1;
+        let document = parse_markdown_with_options(content, options);
+        let mut messages = Vec::new();
+        if lossy {
+            messages.push("not valid UTF-8".to_string());
+        }
+        messages.extend(document.warnings.iter().cloned());
+        messages.extend(check_document(&document, dir));
+        self.issues
+            .extend(messages.into_iter().map(|message| LintIssue {
+                path: path.to_path_buf(),
+                message,
+            }));
+    }
+
+    /// Whether no problems were found
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}: {}", issue.path.display(), issue.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Problems beyond the parser's warnings: code languages without highlighting,
+/// `#fragment` links to no heading, and local images missing from `dir`
+fn check_document(document: &Document, dir: &Path) -> Vec<String> {
+    let anchors: Vec<String> = generate_toc(document)
+        .into_iter()
+        .map(|entry| entry.anchor)
+        .collect();
+    let mut checker = Checker {
+        anchors,
+        dir,
+        messages: Vec::new(),
+    };
+    checker.elements(&document.elements);
+    checker.messages
+}
+
+struct Checker<'a> {
+    anchors: Vec<String>,
+    dir: &'a Path,
+    messages: Vec<String>,
+}
+
+impl Checker<'_> {
+    fn elements(&mut self, elements: &[Element]) {
+        for element in elements {
+            match element {
+                Element::Paragraph { content } => self.inlines(content),
+                Element::CodeBlock {
+                    language: Some(language),
+                    ..
+                } => self.language(language),
+                Element::List { items, .. } => {
+                    for item in items {
+                        self.elements(&item.content);
+                    }
+                }
+                Element::BlockQuote { content }
+                | Element::Alert { content, .. }
+                | Element::FootnoteDefinition { content, .. } => self.elements(content),
+                Element::Table { headers, rows, .. } => {
+                    for cell in headers.iter().chain(rows.iter().flatten()) {
+                        self.inlines(cell);
+                    }
+                }
+                Element::Image { url, .. } => self.image(url),
+                _ => {}
+            }
+        }
+    }
+
+    fn inlines(&mut self, inlines: &[InlineElement]) {
+        for inline in inlines {
+            match inline {
+                InlineElement::Strong(content)
+                | InlineElement::Emphasis(content)
+                | InlineElement::Strikethrough(content) => self.inlines(content),
+                InlineElement::Link { url, content, .. } => {
+                    if let Some(fragment) = url.strip_prefix('#')
+                        && !fragment.is_empty()
+                        && !self.anchors.iter().any(|anchor| anchor == fragment)
+                    {
+                        self.messages
+                            .push(format!("link to missing anchor #{}", fragment));
+                    }
+                    self.inlines(content);
+                }
+                InlineElement::Image { url, .. } => self.image(url),
+                _ => {}
+            }
+        }
+    }
+
+    fn language(&mut self, info: &str) {
+        let language = info.split_whitespace().next().unwrap_or_default();
+        if language.is_empty()
+            || KNOWN_LANGUAGES.contains(&language)
+            || DiagramKind::from_language(language).is_some()
+            || SYNTAX_SET.find_syntax_by_token(language).is_some()
+        {
+            return;
+        }
+        self.messages
+            .push(format!("unknown code block language '{}'", language));
+    }
+
+    fn image(&mut self, url: &str) {
+        // Only relative paths can be checked on disk
+        if url.is_empty() || url.contains("://") || url.starts_with("data:") || url.starts_with('/')
+        {
+            return;
+        }
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        if !self.dir.join(percent_decode(path)).exists() {
+            self.messages.push(format!("missing image file: {}", url));
+        }
+    }
+}
+
+/// Decode `%XX` escapes in a URL path, so `my%20diagram.png` names the file on disk
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_report() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("logo.png"), b"").unwrap();
+        std::fs::write(dir.path().join("my diagram.png"), b"").unwrap();
+        std::fs::write(
+            dir.path().join("good.md"),
+            "# Setup\n\nSee [setup](#setup).\n\n![Logo](logo.png)\n\n![Diagram](my%20diagram.png)\n\n```rust\nfn main() {}\n```\n\n```mermaid\ngraph TD\n```\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("bad.md"),
+            "# Title\n\nSee [usage](#usage) and[^missing].\n\n| Key | See |\n|---|---|\n| a | [options](#options) |\n\n- ![Chart](img/chart.png?v=2)\n\n```klingon\nQapla'\n```\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("latin1.md"), b"caf\xe9\n").unwrap();
+
+        let tree = FileTree::from_directory(dir.path()).unwrap();
+        let report = LintReport::check_tree(&tree, ParseOptions::default()).unwrap();
+        assert_eq!(report.files, 3);
+        assert!(!report.is_clean());
+
+        // All problems are collected, not just the first
+        let messages: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| format!("{}: {}", issue.path.display(), issue.message))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "bad.md: undefined footnote reference [^missing]",
+                "bad.md: link to missing anchor #usage",
+                "bad.md: link to missing anchor #options",
+                "bad.md: missing image file: img/chart.png?v=2",
+                "bad.md: unknown code block language 'klingon'",
+                "latin1.md: not valid UTF-8",
+            ]
+        );
+        assert_eq!(report.to_string(), messages.join("\n") + "\n");
+
+        let mut report = LintReport::default();
+        let good = std::fs::read_to_string(dir.path().join("good.md")).unwrap();
+        report.check(
+            Path::new("good.md"),
+            &good,
+            false,
+            dir.path(),
+            ParseOptions::default(),
+        );
+        assert!(report.is_clean());
+    }
+}
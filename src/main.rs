@@ -1,15 +1,43 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::env;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{self, Command, Stdio};
+use std::time::Duration;
 use tokio::sync::broadcast;
-
-use mdp::files::FileTree;
-use mdp::parser::parse_markdown;
-use mdp::renderer::terminal::TerminalRenderer;
-use mdp::server::{find_available_port, start_server};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+use mdp::Error;
+use mdp::config::{Config, DEFAULT_PORT, EnvDefaults, is_less, split_pager};
+use mdp::files::{
+    FileTree, MarkdownFile, ScanOptions, default_extensions, expand_globs, is_markdown_path,
+    modified_time, parse_duration, read_markdown,
+};
+use mdp::instance::Instance;
+use mdp::lint::LintReport;
+use mdp::manifest::Manifest;
+use mdp::output::{Verbosity, set_verbosity};
+use mdp::parser::{
+    Document, FootnoteMode, FrontMatterMode, IncrementalParser, ParseOptions,
+    parse_markdown_with_options,
+};
+use mdp::renderer::html::HtmlRenderer;
+use mdp::renderer::terminal::{CodeStyle, ContentAlign, TerminalRenderer, TerminalTheme};
+use mdp::server::{find_available_port, open_in_browser, page_url, start_server};
 use mdp::watcher::watch_file;
+use mdp::{status, warning};
+
+/// Output format for non-browser modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Rich terminal rendering
+    Terminal,
+    /// Heading anchors, one per line (`anchor<TAB>heading`)
+    Anchors,
+    /// HTML fragment of the document (a full page with `--standalone`)
+    Html,
+}
 
 #[derive(Parser, Debug)]
 #[command(name = "mdp")]
@@ -19,9 +47,10 @@ use mdp::watcher::watch_file;
     about = "A rich Markdown previewer for the terminal and browser"
 )]
 struct Args {
-    /// Markdown file or directory to preview
-    #[arg(required = true)]
-    path: PathBuf,
+    /// Markdown files, directories, or glob patterns (`docs/**/*.md`) to preview;
+    /// several are shown together
+    #[arg(required_unless_present = "list_syntax_themes", value_name = "PATH")]
+    paths: Vec<PathBuf>,
 
     /// Watch for file changes and re-render
     #[arg(short, long)]
@@ -35,195 +64,711 @@ struct Args {
     #[arg(long)]
     toc: bool,
 
-    /// Show sidebar with related markdown files (for single file mode)
-    #[arg(short, long)]
+    /// Print only the heading outline, with each heading's line in the rendered
+    /// document and the start of the text below it, then exit
+    #[arg(long, conflicts_with = "browser")]
+    outline: bool,
+
+    /// Show sidebar with related markdown files (for single file browser mode)
+    #[arg(short, long, visible_alias = "context")]
     sidebar: bool,
 
-    /// Theme (dark or light)
-    #[arg(long, default_value = "dark")]
-    theme: String,
+    /// Show two files side by side in browser mode, each pane picked from the
+    /// sidebar (implies --sidebar)
+    #[arg(long)]
+    split: bool,
+
+    /// Theme: dark or light, or auto to follow the OS in the browser (default:
+    /// $MDP_THEME; terminal: dark; browser: the last toggled theme, else the OS)
+    #[arg(long, value_parser = ["dark", "light", "auto"])]
+    theme: Option<String>,
 
-    /// Disable pager (output directly to stdout)
+    /// Disable pager (output directly to stdout); also set by $MDP_NO_PAGER
     #[arg(long)]
     no_pager: bool,
 
-    /// Port for browser mode (default: 3000, auto-increments if busy)
-    #[arg(short, long, default_value = "3000")]
-    port: u16,
+    /// Pager command, from $MDP_PAGER, else $PAGER, else less
+    #[arg(skip)]
+    pager: String,
+
+    /// Port for browser mode (default: $MDP_PORT, else 3000; auto-increments if busy)
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Don't open a browser in browser mode, just print the URL (also skipped when
+    /// there is no display)
+    #[arg(long)]
+    no_open: bool,
+
+    /// In browser mode, show the file in an mdp server already running for the
+    /// same directory instead of starting another
+    #[arg(long)]
+    reuse: bool,
+
+    /// Maximum content width in columns for terminal mode (clamped to the terminal width)
+    #[arg(long, value_name = "COLS")]
+    width: Option<usize>,
+
+    /// Placement of the content column in terminal mode
+    #[arg(long, value_enum, default_value = "left")]
+    align: ContentAlign,
+
+    /// Decoration around code blocks in terminal mode (default: boxed, or plain when
+    /// the output is piped)
+    #[arg(long, value_enum)]
+    code_style: Option<CodeStyle>,
+
+    /// Plain output without colors or styling (implied when stdout is not a terminal)
+    #[arg(long)]
+    plain: bool,
+
+    /// Highlighting language for code blocks without one, such as indented blocks
+    #[arg(long, value_name = "LANG")]
+    default_lang: Option<String>,
+
+    /// Syntax highlighting theme for code blocks in terminal mode (default:
+    /// base16-ocean.dark, or base16-ocean.light with --theme light)
+    #[arg(long, value_name = "NAME")]
+    syntax_theme: Option<String>,
+
+    /// Print the available syntax highlighting themes, then exit
+    #[arg(long)]
+    list_syntax_themes: bool,
+
+    /// Output format (ignored in browser mode)
+    #[arg(long, value_enum, default_value = "terminal")]
+    format: OutputFormat,
+
+    /// Use only ASCII characters for borders, bullets, and icons
+    #[arg(long)]
+    ascii: bool,
+
+    /// Typographic punctuation: curly quotes, dashes, and ellipses
+    #[arg(long)]
+    smart: bool,
+
+    /// Combine all files (in sidebar order) into one self-contained HTML page
+    #[arg(long)]
+    combine: bool,
+
+    /// Write a JSON manifest of every file (path, title, heading anchors, word
+    /// count, last-modified time) for search indexers and site generators, then exit
+    #[arg(long, value_name = "FILE", conflicts_with = "browser")]
+    manifest: Option<PathBuf>,
+
+    /// Write output to a file instead of stdout (used with --combine and --format html)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// With --format html, write a self-contained page instead of a fragment
+    #[arg(long)]
+    standalone: bool,
+
+    /// Show a header with the file path and document title (terminal only, not when
+    /// piped or with --plain)
+    #[arg(long)]
+    header: bool,
+
+    /// File to open first in browser mode, relative to the previewed directory
+    #[arg(long, value_name = "RELPATH")]
+    open: Option<String>,
+
+    /// Landing page in browser mode, relative to the previewed directory (default: the
+    /// README or index, else a generated list of files)
+    #[arg(long, value_name = "RELPATH")]
+    index: Option<String>,
+
+    /// Show an "Edit this page" link in browser mode: this URL followed by the file's
+    /// relative path (e.g. `https://github.com/org/repo/edit/main/`)
+    #[arg(long, value_name = "URL")]
+    edit_url_base: Option<String>,
+
+    /// Mark sidebar files modified within this window as updated in browser mode
+    /// (e.g. `24h`, `7d`; units s, m, h, d, w)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    since: Option<Duration>,
+
+    /// Hide the file's last-modified date (shown in browser mode and on terminals)
+    #[arg(long)]
+    no_modified: bool,
+
+    /// Highlight authoring problems in the HTML output, such as images without alt text
+    #[arg(long)]
+    authoring: bool,
+
+    /// Draw Graphviz (`dot`) diagrams in the browser with viz.js, loaded from a CDN
+    /// (mermaid diagrams are always drawn)
+    #[arg(long)]
+    diagrams: bool,
+
+    /// Front matter: hidden, shown as a key/value table, or shown raw as a code block
+    #[arg(long, value_enum, value_name = "MODE", default_value = "hide")]
+    frontmatter: FrontMatterMode,
+
+    /// Footnotes: numbered with a section at the end, inline in parentheses, or as
+    /// tooltips on the references in the browser
+    #[arg(long, value_enum, value_name = "MODE", default_value = "section")]
+    footnotes: FootnoteMode,
+
+    /// Highlight inline code in the HTML output as this language (e.g. `rust`); a
+    /// `{.lang}` attribute after a code span picks the language for that span
+    #[arg(long, value_name = "LANG")]
+    inline_code_lang: Option<String>,
+
+    /// Prefix root-relative links and images in the HTML output with this URL
+    /// (e.g. `/docs/`), for pages published below the site root
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Insert this file's contents verbatim at the end of the HTML `<head>`, e.g. a
+    /// font link or analytics snippet. The markup is not checked or escaped.
+    #[arg(long, value_name = "FILE")]
+    head_include: Option<PathBuf>,
+
+    /// Stylesheet applied after the built-in GitHub styles to override them; repeat
+    /// to stack several in order
+    #[arg(long, value_name = "FILE")]
+    css: Vec<PathBuf>,
+
+    /// Additional markdown file extensions, comma-separated (e.g. `mdx,mkd`)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    ext: Vec<String>,
+
+    /// Config file (default: $XDG_CONFIG_HOME/mdp/config.toml or ~/.config/mdp/config.toml)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Check every file first and fail on problems that are otherwise warnings
+    /// (undefined footnotes, unknown code languages, links to missing anchors,
+    /// missing images, invalid UTF-8), reporting them all
+    #[arg(long)]
+    strict: bool,
+
+    /// Print errors only, without status messages or warnings
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print debug details such as changed paths and the chosen port
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+impl Args {
+    /// Fill in flags that weren't given from `MDP_*` environment variables
+    fn apply_env(&mut self, env: &EnvDefaults) {
+        self.theme = env.theme(self.theme.take());
+        self.port = Some(env.port(self.port));
+        self.no_pager = env.no_pager(self.no_pager);
+        self.pager = env.pager();
+    }
 }
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let verbosity = if args.quiet {
+        Verbosity::Quiet
+    } else if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    set_verbosity(verbosity);
+    init_logging(verbosity, args.quiet || args.verbose);
+
+    let result = EnvDefaults::from_env()
+        .map_err(Error::Config)
+        .and_then(|env| {
+            args.apply_env(&env);
+            run(&args)
+        });
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(exit_code(&e));
+    }
+}
+
+/// Log to stderr; `RUST_LOG` applies unless `--quiet` or `--verbose` was given.
+/// Request spans log their duration when they close.
+fn init_logging(verbosity: Verbosity, explicit: bool) {
+    let default = match verbosity {
+        Verbosity::Quiet => "mdp=error",
+        Verbosity::Normal => "mdp=info",
+        Verbosity::Verbose => "mdp=debug",
+    };
+    let filter = if explicit {
+        EnvFilter::new(default)
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default))
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(atty::is(atty::Stream::Stderr))
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+}
 
-    // Check if path exists
-    if !args.path.exists() {
-        eprintln!("Error: Path not found: {}", args.path.display());
-        process::exit(1);
+/// Exit status for an error: 2 not found, 3 render failure, 4 server bind failure,
+/// 5 `--strict` problems, 1 otherwise
+fn exit_code(error: &Error) -> i32 {
+    match error {
+        Error::NotFound(_) | Error::NoGlobMatches(_) | Error::NoMarkdownFiles(_) => 2,
+        Error::Render(_) | Error::Theme(_) => 3,
+        Error::Bind { .. } => 4,
+        Error::Lint { .. } => 5,
+        _ => 1,
     }
+}
 
-    // Build file tree (works for both file and directory)
-    let file_tree = if args.path.is_dir() {
-        match FileTree::from_directory(&args.path) {
-            Ok(tree) => {
-                if tree.files.is_empty() {
-                    eprintln!(
-                        "Error: No markdown files found in '{}'",
-                        args.path.display()
-                    );
-                    process::exit(1);
-                }
-                tree
-            }
-            Err(e) => {
-                eprintln!("Error: Failed to scan directory: {}", e);
-                process::exit(1);
-            }
+fn run(args: &Args) -> mdp::Result<()> {
+    if args.list_syntax_themes {
+        for name in TerminalRenderer::syntax_themes() {
+            println!("{}", name);
         }
-    } else {
-        // Single file mode
+        return Ok(());
+    }
+
+    // Check if paths exist, expanding glob patterns the shell left alone
+    let paths = expand_globs(&args.paths)?;
+    let path = &paths[0];
+    let single_file = paths.len() == 1 && !path.is_dir();
+
+    let config = Config::load(args.config.as_deref()).map_err(Error::Config)?;
+
+    let extensions = markdown_extensions(args);
+    tracing::debug!("Markdown extensions: {}", extensions.join(", "));
+
+    if single_file {
         // Warn if file is not .md
-        if let Some(ext) = args.path.extension() {
-            if ext != "md" && ext != "markdown" {
-                eprintln!(
-                    "Warning: '{}' is not a markdown file (.md)",
-                    args.path.display()
+        if path.extension().is_some() {
+            if !is_markdown_path(path, &extensions) {
+                warning!(
+                    "Warning: '{}' is not a markdown file ({})",
+                    path.display(),
+                    extensions
+                        .iter()
+                        .map(|ext| format!(".{}", ext))
+                        .collect::<Vec<_>>()
+                        .join(", ")
                 );
-                eprintln!("         Proceeding anyway...\n");
+                warning!("         Proceeding anyway...\n");
             }
         } else {
-            eprintln!(
+            warning!(
                 "Warning: '{}' has no extension, treating as markdown\n",
-                args.path.display()
+                path.display()
             );
         }
+    }
 
-        // Use context mode if sidebar option is enabled
-        if args.sidebar {
-            match FileTree::from_file_with_context(&args.path) {
-                Ok(tree) => tree,
-                Err(e) => {
-                    eprintln!("Error: Failed to scan directory: {}", e);
-                    process::exit(1);
-                }
-            }
+    // Build file tree from any mix of files and directories. A single file gets its
+    // directory as context with the sidebar; the terminal shows just the file.
+    let options = ScanOptions {
+        extensions,
+        context: (args.sidebar || args.split) && args.browser,
+    };
+    let mut file_tree = FileTree::from_paths(&paths, &options)?;
+    if file_tree.files.is_empty() {
+        return Err(Error::NoMarkdownFiles(path.clone()));
+    }
+
+    if let Some(index) = args.index.as_deref()
+        && !file_tree.set_default_file(index)
+    {
+        warning!(
+            "Warning: '{}' not found in '{}', using the default landing page",
+            index,
+            file_tree.base_path.display()
+        );
+    }
+
+    let open_file = args.open.as_deref().and_then(|path| {
+        if file_tree.set_default_file(path) {
+            file_tree
+                .default_file()
+                .map(|f| f.relative_path.to_string_lossy().replace('\\', "/"))
         } else {
-            match FileTree::from_file(&args.path) {
-                Ok(tree) => tree,
-                Err(e) => {
-                    eprintln!("Error: Failed to read file: {}", e);
-                    process::exit(1);
-                }
-            }
+            warning!(
+                "Warning: '{}' not found in '{}', opening the default file",
+                path,
+                file_tree.base_path.display()
+            );
+            None
         }
-    };
+    });
 
-    // Get title from directory name or filename
-    let title = if args.path.is_dir() {
-        args.path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Markdown Preview")
-            .to_string()
-    } else {
-        args.path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Markdown Preview")
-            .to_string()
-    };
+    // Get title from directory name or filename; a glob pattern is named after its
+    // only match or the directory its matches share
+    let title = args
+        .paths
+        .iter()
+        .map(|arg| {
+            let path = if arg.exists() {
+                arg.as_path()
+            } else if single_file {
+                path.as_path()
+            } else {
+                file_tree.base_path.as_path()
+            };
+            let name = if path.is_dir() {
+                path.file_name()
+            } else {
+                path.file_stem()
+            };
+            name.and_then(|s| s.to_str()).unwrap_or("Markdown Preview")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if args.strict {
+        let report = LintReport::check_tree(&file_tree, parse_options(args))?;
+        if !report.is_clean() {
+            eprint!("{}", report);
+            return Err(Error::Lint {
+                problems: report.issues.len(),
+                files: report.files,
+            });
+        }
+    }
 
     // Render based on mode
-    if args.browser {
-        // Browser mode (with optional watch)
-        let port = find_available_port(args.port);
-        let rt = tokio::runtime::Runtime::new().expect("Failed to create runtime");
-        if let Err(e) = rt.block_on(start_server(file_tree, &title, port, args.watch, args.toc)) {
-            eprintln!("Error: Server failed: {}", e);
-            process::exit(1);
+    if let Some(path) = &args.manifest {
+        let manifest = Manifest::from_tree(&file_tree, parse_options(args))?;
+        std::fs::write(path, manifest.to_json()).map_err(Error::Render)?;
+    } else if args.combine {
+        write_combined(&file_tree, &title, args)?;
+    } else if args.format == OutputFormat::Anchors {
+        print_anchors(&file_tree, parse_options(args))?;
+    } else if args.outline {
+        if let Some(file) = file_tree.default_file() {
+            print_outline(file, args, &config)?;
+        }
+    } else if args.format == OutputFormat::Html && !args.browser {
+        // The default file (README in directory mode) as HTML
+        if let Some(file) = file_tree.default_file() {
+            write_html(&file.absolute_path, &title, args)?;
         }
+    } else if args.browser {
+        if args.reuse && reuse_server(&file_tree, open_file.as_deref(), args) {
+            return Ok(());
+        }
+
+        // Browser mode (with optional watch)
+        let port = find_available_port(args.port.unwrap_or(DEFAULT_PORT));
+        tracing::debug!("Using port {}", port);
+        let renderer = HtmlRenderer::new(&title)
+            .with_toc(args.toc)
+            .with_parse_options(parse_options(args))
+            .with_edit_url_base(args.edit_url_base.clone())
+            .with_modified(!args.no_modified)
+            .with_authoring(args.authoring)
+            .with_theme(args.theme.clone())
+            .with_frontmatter(args.frontmatter)
+            .with_since(args.since)
+            .with_diagrams(args.diagrams)
+            .with_footnotes(args.footnotes)
+            .with_inline_code_lang(args.inline_code_lang.clone())
+            .with_head_extra(head_extra(args)?)
+            .with_custom_css(custom_css(args)?)
+            .with_split(args.split);
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(start_server(
+            file_tree,
+            renderer,
+            port,
+            args.watch,
+            open_file.as_deref(),
+            !args.no_open,
+        ))?;
     } else if args.watch {
         // Terminal watch mode (single file only for now)
         if let Some(file) = file_tree.default_file() {
-            run_terminal_watch_mode(&file.absolute_path, &args.theme, args.toc);
+            run_terminal_watch_mode(file, args, &config);
         }
     } else {
         // Normal terminal mode
         if file_tree.is_single_file() {
             if let Some(file) = file_tree.default_file() {
-                run_terminal_mode(&file.absolute_path, &args.theme, args.no_pager, args.toc);
+                run_terminal_mode(file, args, &config)?;
             }
         } else {
             // Directory mode in terminal - list files
             println!(
                 "Found {} markdown files in '{}':\n",
                 file_tree.files.len(),
-                args.path.display()
+                args.paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("', '")
             );
             for (i, file) in file_tree.files.iter().enumerate() {
                 println!("  {}. {}", i + 1, file.relative_path.display());
             }
-            println!("\nUse -b flag for browser mode with navigation sidebar.");
+            status!("\nUse -b flag for browser mode with navigation sidebar.");
         }
     }
+
+    Ok(())
 }
 
-fn run_terminal_mode(file_path: &PathBuf, theme: &str, no_pager: bool, show_toc: bool) {
-    let content = match std::fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error: Failed to read file: {}", e);
-            process::exit(1);
+/// Hand the file to a server already running for this tree, opening a tab when
+/// it has none. `false` when there is no such server and a new one is needed.
+fn reuse_server(file_tree: &FileTree, open_file: Option<&str>, args: &Args) -> bool {
+    let Some(instance) = Instance::find(&Instance::default_dir(), &file_tree.base_path) else {
+        tracing::debug!(
+            "No running server found for {}",
+            file_tree.base_path.display()
+        );
+        return false;
+    };
+    // A single file is always named, so the server can check it serves that file
+    let default_file = file_tree
+        .default_file()
+        .filter(|_| file_tree.is_single_file())
+        .map(|f| f.relative_path.to_string_lossy().replace('\\', "/"));
+    let file = open_file.or(default_file.as_deref());
+    let Some(response) = instance.open(file) else {
+        return false;
+    };
+
+    let url = page_url(&format!("127.0.0.1:{}", instance.port), file);
+    if response.shown {
+        status!("Showing in the running server at {}", url);
+    } else {
+        open_in_browser(&url, !args.no_open);
+    }
+    true
+}
+
+/// Print the heading ids of every file's HTML, for linking in the browser.
+/// In directory mode each anchor is prefixed with the file's relative path (`guide.md#setup`).
+fn print_anchors(file_tree: &FileTree, options: ParseOptions) -> mdp::Result<()> {
+    let renderer = HtmlRenderer::new("").with_parse_options(options);
+    for file in &file_tree.files {
+        let content = read_markdown(&file.absolute_path)
+            .map_err(|e| Error::from_io(&file.relative_path, e))?;
+
+        let prefix = if file_tree.is_single_file() {
+            String::new()
+        } else {
+            file.relative_path.to_string_lossy().replace('\\', "/")
+        };
+
+        let (_, toc) = renderer.render_to_html_string(&content);
+        for entry in toc {
+            if prefix.is_empty() {
+                println!("{}\t{}", entry.anchor, entry.text);
+            } else {
+                println!("{}#{}\t{}", prefix, entry.anchor, entry.text);
+            }
         }
+    }
+
+    Ok(())
+}
+
+/// Print the heading outline of one file; line numbers match the paged terminal output
+fn print_outline(file: &MarkdownFile, args: &Args, config: &Config) -> mdp::Result<()> {
+    let file_path = &file.absolute_path;
+    let content = read_markdown(file_path).map_err(|e| Error::from_io(file_path, e))?;
+    let document = parse_markdown_with_options(&content, parse_options(args));
+    terminal_renderer(file, args, config).render_outline(&mut io::stdout(), &document, args.toc)
+}
+
+/// Render every file of the tree into one HTML page and write it to `--output` or stdout
+fn write_combined(file_tree: &FileTree, title: &str, args: &Args) -> mdp::Result<()> {
+    let mut files = Vec::new();
+    for file in &file_tree.files {
+        let content = read_markdown(&file.absolute_path)
+            .map_err(|e| Error::from_io(&file.relative_path, e))?;
+        files.push((file.relative_path.clone(), content));
+    }
+
+    let html = HtmlRenderer::new(title)
+        .with_toc(args.toc)
+        .with_parse_options(parse_options(args))
+        .with_frontmatter(args.frontmatter)
+        .with_diagrams(args.diagrams)
+        .with_footnotes(args.footnotes)
+        .with_inline_code_lang(args.inline_code_lang.clone())
+        .with_base_url(args.base_url.clone())
+        .with_head_extra(head_extra(args)?)
+        .with_custom_css(custom_css(args)?)
+        .render_combined(&files);
+    write_output(&html, args)
+}
+
+/// Render one file as an HTML fragment, or a self-contained page with `--standalone`,
+/// and write it to `--output` or stdout
+fn write_html(file_path: &Path, title: &str, args: &Args) -> mdp::Result<()> {
+    let content = read_markdown(file_path).map_err(|e| Error::from_io(file_path, e))?;
+    let renderer = HtmlRenderer::new(title)
+        .with_toc(args.toc)
+        .with_parse_options(parse_options(args))
+        .with_authoring(args.authoring)
+        .with_theme(args.theme.clone())
+        .with_frontmatter(args.frontmatter)
+        .with_diagrams(args.diagrams)
+        .with_footnotes(args.footnotes)
+        .with_inline_code_lang(args.inline_code_lang.clone())
+        .with_base_url(args.base_url.clone())
+        .with_head_extra(head_extra(args)?)
+        .with_custom_css(custom_css(args)?);
+    let html = if args.standalone {
+        renderer.render_standalone(&content)
+    } else {
+        renderer.render_content(&content)
     };
+    write_output(&html, args)
+}
 
-    let document = parse_markdown(&content);
-    let renderer = TerminalRenderer::new(theme);
+/// Contents of the `--head-include` file, or an empty string without one
+fn head_extra(args: &Args) -> mdp::Result<String> {
+    match &args.head_include {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| Error::from_io(path, e)),
+        None => Ok(String::new()),
+    }
+}
+
+/// Contents of the `--css` files in order, or an empty string without any
+fn custom_css(args: &Args) -> mdp::Result<String> {
+    let mut css = String::new();
+    for path in &args.css {
+        css.push_str(&std::fs::read_to_string(path).map_err(|e| Error::from_io(path, e))?);
+        css.push('\n');
+    }
+    Ok(css)
+}
 
-    if no_pager || !atty::is(atty::Stream::Stdout) {
-        if let Err(e) = renderer.render(&document, show_toc) {
-            eprintln!("Error: Failed to render: {}", e);
-            process::exit(1);
+/// Write generated output to `--output` or stdout
+fn write_output(output: &str, args: &Args) -> mdp::Result<()> {
+    let result = match &args.output {
+        Some(path) => std::fs::write(path, output),
+        None => io::stdout().write_all(output.as_bytes()),
+    };
+    result.map_err(Error::Render)
+}
+
+/// Default markdown file extensions plus any added with `--ext`
+fn markdown_extensions(args: &Args) -> Vec<String> {
+    let mut extensions = default_extensions();
+    for ext in &args.ext {
+        let ext = ext.trim().trim_start_matches('.');
+        if !ext.is_empty() && !extensions.iter().any(|known| known == ext) {
+            extensions.push(ext.to_string());
         }
-    } else if let Err(e) = render_with_pager(&renderer, &document, show_toc) {
-        eprintln!("Error: Failed to render: {}", e);
-        process::exit(1);
     }
+    extensions
 }
 
-fn run_terminal_watch_mode(file_path: &PathBuf, theme: &str, show_toc: bool) {
+/// Markdown extensions selected on the command line
+fn parse_options(args: &Args) -> ParseOptions {
+    ParseOptions {
+        smart_punctuation: args.smart,
+    }
+}
+
+/// Build a terminal renderer for `file` configured from the command-line options
+/// and config file
+fn terminal_renderer(file: &MarkdownFile, args: &Args, config: &Config) -> TerminalRenderer {
+    let interactive = atty::is(atty::Stream::Stdout);
+    let theme = args.theme.as_deref().unwrap_or("dark");
+    TerminalRenderer::new(theme)
+        .with_style(config.style.clone())
+        .with_colors(config.colors.apply(TerminalTheme::named(theme)))
+        .with_width(args.width)
+        .with_align(args.align)
+        .with_frontmatter(args.frontmatter)
+        .with_footnotes(args.footnotes)
+        .with_code_style(
+            args.code_style
+                .unwrap_or_else(|| CodeStyle::default_for(interactive)),
+        )
+        .with_color(use_color(args))
+        .with_default_lang(args.default_lang.clone())
+        .with_syntax_theme(args.syntax_theme.clone())
+        .with_ascii(args.ascii)
+        .with_header(
+            (args.header && !args.plain && interactive)
+                .then(|| file.relative_path.to_string_lossy().replace('\\', "/")),
+        )
+        .with_modified(
+            (!args.no_modified && interactive)
+                .then(|| modified_time(&file.absolute_path))
+                .flatten(),
+        )
+}
+
+/// Colors are used only when writing to a terminal, unless disabled by `--plain` or `NO_COLOR`
+fn use_color(args: &Args) -> bool {
+    !args.plain && env::var_os("NO_COLOR").is_none() && atty::is(atty::Stream::Stdout)
+}
+
+fn run_terminal_mode(file: &MarkdownFile, args: &Args, config: &Config) -> mdp::Result<()> {
+    let file_path = &file.absolute_path;
+    let content = read_markdown(file_path).map_err(|e| Error::from_io(file_path, e))?;
+
+    let document = parse_markdown_with_options(&content, parse_options(args));
+    print_warnings(file_path, &document);
+    let renderer = terminal_renderer(file, args, config);
+
+    if args.no_pager || !atty::is(atty::Stream::Stdout) {
+        renderer.render(&document, args.toc)
+    } else {
+        let title = document
+            .display_title()
+            .map(String::from)
+            .unwrap_or_else(|| {
+                file_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+        render_with_pager(&renderer, &document, args.toc, &title, &args.pager)
+    }
+}
+
+fn run_terminal_watch_mode(file: &MarkdownFile, args: &Args, config: &Config) {
     use crossterm::{
         ExecutableCommand, cursor,
         event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
         terminal::{self, ClearType},
     };
-    use std::time::Duration;
 
     let (tx, mut rx) = broadcast::channel::<()>(16);
 
+    // Kept across renders so a save re-parses only the edited blocks
+    let mut parser = None;
+
     // Initial render
-    render_terminal_content(file_path, theme, show_toc);
+    render_terminal_content(file, args, config, &mut parser);
 
     // Start file watcher in a separate thread
-    let watch_path = file_path.clone();
+    let watch_path = file.absolute_path.clone();
     std::thread::spawn(move || {
         if let Err(e) = watch_file(&watch_path, tx) {
-            eprintln!("Watcher error: {}", e);
+            tracing::error!("Watcher error: {}", e);
         }
     });
 
-    println!("\n--- Watching for changes (Press q or Ctrl+C to exit) ---\n");
+    status!("\n--- Watching for changes (Press q or Ctrl+C to exit) ---\n");
 
     // Enable raw mode for keyboard input
     let _ = terminal::enable_raw_mode();
 
+    // Width of the last render, to reflow when the terminal is resized
+    let mut width = terminal::size().ok().map(|(w, _)| w);
+
     loop {
-        // Poll for keyboard events (non-blocking with 100ms timeout)
+        let mut redraw = false;
+
+        // Poll for keyboard and resize events (non-blocking with 100ms timeout)
         if event::poll(Duration::from_millis(100)).unwrap_or(false) {
-            if let Ok(Event::Key(KeyEvent {
-                code, modifiers, ..
-            })) = event::read()
-            {
-                match (code, modifiers) {
+            match event::read() {
+                Ok(Event::Key(KeyEvent {
+                    code, modifiers, ..
+                })) => match (code, modifiers) {
                     // Exit on 'q' or 'Q'
                     (KeyCode::Char('q'), KeyModifiers::NONE)
                     | (KeyCode::Char('Q'), KeyModifiers::SHIFT) => {
@@ -234,19 +779,29 @@ fn run_terminal_watch_mode(file_path: &PathBuf, theme: &str, show_toc: bool) {
                         break;
                     }
                     _ => {}
+                },
+                Ok(Event::Resize(new_width, _)) if width != Some(new_width) => {
+                    tracing::debug!("Terminal resized to {} columns", new_width);
+                    width = Some(new_width);
+                    redraw = true;
                 }
+                _ => {}
             }
         }
 
         // Check for file changes (non-blocking)
-        if let Ok(()) = rx.try_recv() {
-            // Clear screen and re-render
+        if rx.try_recv().is_ok() {
+            redraw = true;
+        }
+
+        if redraw {
+            // Clear screen and re-render with a renderer sized to the terminal
             let mut stdout = io::stdout();
             let _ = stdout.execute(terminal::Clear(ClearType::All));
             let _ = stdout.execute(cursor::MoveTo(0, 0));
 
-            render_terminal_content(file_path, theme, show_toc);
-            println!("\n--- Watching for changes (Press q or Ctrl+C to exit) ---\n");
+            render_terminal_content(file, args, config, &mut parser);
+            status!("\n--- Watching for changes (Press q or Ctrl+C to exit) ---\n");
         }
     }
 
@@ -254,8 +809,14 @@ fn run_terminal_watch_mode(file_path: &PathBuf, theme: &str, show_toc: bool) {
     let _ = terminal::disable_raw_mode();
 }
 
-fn render_terminal_content(file_path: &PathBuf, theme: &str, show_toc: bool) {
-    let content = match std::fs::read_to_string(file_path) {
+fn render_terminal_content(
+    file: &MarkdownFile,
+    args: &Args,
+    config: &Config,
+    parser: &mut Option<IncrementalParser>,
+) {
+    let file_path = &file.absolute_path;
+    let content = match read_markdown(file_path) {
         Ok(content) => content,
         Err(e) => {
             eprintln!("Error: Failed to read file: {}", e);
@@ -263,33 +824,74 @@ fn render_terminal_content(file_path: &PathBuf, theme: &str, show_toc: bool) {
         }
     };
 
-    let document = parse_markdown(&content);
-    let renderer = TerminalRenderer::new(theme);
+    let document = match parser {
+        Some(parser) => {
+            if !parser.update(&content) {
+                tracing::debug!("Re-parsed {} in full", file_path.display());
+            }
+            parser.document()
+        }
+        None => parser
+            .insert(IncrementalParser::new(&content, parse_options(args)))
+            .document(),
+    };
+    print_warnings(file_path, document);
+    let renderer = terminal_renderer(file, args, config);
 
-    if let Err(e) = renderer.render(&document, show_toc) {
+    if let Err(e) = renderer.render(document, args.toc) {
         eprintln!("Error: Failed to render: {}", e);
     }
 }
 
+fn print_warnings(file_path: &Path, document: &Document) {
+    for warning in &document.warnings {
+        warning!("Warning: {}: {}", file_path.display(), warning);
+    }
+}
+
+/// `less` short prompt showing the document title, line range, and position
+fn less_prompt(title: &str) -> String {
+    let mut escaped = String::with_capacity(title.len());
+    for c in title.chars() {
+        // Characters with special meaning in less prompts
+        if matches!(c, '?' | ':' | '.' | '%' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    format!(
+        "-Ps{}?lt  lines %lt-%lb?L/%L..?e  (END):?pB  %pB\\%..",
+        escaped
+    )
+}
+
 fn render_with_pager(
     renderer: &TerminalRenderer,
-    document: &mdp::parser::Document,
+    document: &Document,
     show_toc: bool,
-) -> io::Result<()> {
+    title: &str,
+    pager: &str,
+) -> mdp::Result<()> {
     // Render to buffer first
     let mut buffer = Vec::new();
     renderer.render_to_writer(&mut buffer, document, show_toc)?;
-
-    // Get pager from environment or default to less
-    let pager = env::var("PAGER").unwrap_or_else(|_| "less".to_string());
-    let pager_args: Vec<&str> = if pager.contains("less") {
-        vec!["-R", "-F", "-X"] // -R: raw control chars, -F: quit if one screen, -X: no init
-    } else {
-        vec![]
+    let Some((program, mut pager_args)) = split_pager(pager) else {
+        warning!("Warning: invalid pager command '{}'", pager);
+        io::stdout().write_all(&buffer).map_err(Error::Render)?;
+        return Ok(());
     };
+    if is_less(&program) {
+        // -R: raw control chars, -F: quit if one screen, -X: no init, -P: prompt
+        pager_args.extend([
+            "-R".to_string(),
+            "-F".to_string(),
+            "-X".to_string(),
+            less_prompt(title),
+        ]);
+    }
 
     // Try to spawn pager
-    match Command::new(&pager)
+    match Command::new(&program)
         .args(&pager_args)
         .stdin(Stdio::piped())
         .spawn()
@@ -300,7 +902,7 @@ fn render_with_pager(
                 if stdin.write_all(&buffer).is_err() {
                     drop(stdin);
                     let _ = child.kill();
-                    io::stdout().write_all(&buffer)?;
+                    io::stdout().write_all(&buffer).map_err(Error::Render)?;
                     return Ok(());
                 }
             }
@@ -316,19 +918,20 @@ fn render_with_pager(
                             // Signal termination (e.g., SIGPIPE) - not an error for pagers
                             if signal != 13 {
                                 // 13 = SIGPIPE, which is normal
-                                eprintln!("Pager terminated by signal {}", signal);
+                                warning!("Pager terminated by signal {}", signal);
                             }
                         }
                     }
                 }
                 Err(e) => {
-                    eprintln!("Warning: Failed to wait for pager: {}", e);
+                    warning!("Warning: Failed to wait for pager: {}", e);
                 }
             }
         }
-        Err(_) => {
+        Err(e) => {
             // Fallback to direct output if pager fails to spawn
-            io::stdout().write_all(&buffer)?;
+            warning!("Warning: failed to start pager '{}': {}", program, e);
+            io::stdout().write_all(&buffer).map_err(Error::Render)?;
         }
     }
 
@@ -0,0 +1,105 @@
+//! Machine-readable description of a documentation set, for search indexers and
+//! site generators
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::files::{FileTree, MarkdownFile, read_lossy};
+use crate::parser::{ParseOptions, TocEntry, generate_toc, parse_markdown_with_options};
+
+/// One file of the tree
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    /// Relative path with `/` separators
+    pub path: String,
+    /// Front matter `title`, else the first level-1 heading
+    pub title: Option<String>,
+    /// Every heading with its anchor, in document order
+    pub headings: Vec<TocEntry>,
+    /// Words of prose, not counting code blocks
+    pub words: usize,
+    /// Last modification as seconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+/// Every file of a tree, in sidebar order
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn from_tree(file_tree: &FileTree, options: ParseOptions) -> Result<Self> {
+        let files = file_tree
+            .files
+            .iter()
+            .map(|file| ManifestEntry::from_file(file, options))
+            .collect::<Result<_>>()?;
+        Ok(Self { files })
+    }
+
+    /// Pretty-printed JSON, ending with a newline
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("manifest serializes to JSON");
+        json.push('\n');
+        json
+    }
+}
+
+impl ManifestEntry {
+    fn from_file(file: &MarkdownFile, options: ParseOptions) -> Result<Self> {
+        let (content, _) =
+            read_lossy(&file.absolute_path).map_err(|e| Error::from_io(&file.absolute_path, e))?;
+        let document = parse_markdown_with_options(&content, options);
+        Ok(Self {
+            path: file.relative_path.to_string_lossy().replace('\\', "/"),
+            title: document.display_title().map(String::from),
+            headings: generate_toc(&document),
+            words: document.word_count(),
+            modified: file.modified_secs(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("README.md"),
+            "# Project\n\nA *small* tool.\n\n## Usage\n\n```sh\nmdp --help\n```\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("guide")).unwrap();
+        std::fs::write(
+            dir.path().join("guide/setup.md"),
+            "---\ntitle: Getting Started\n---\n# Setup\n\n- one two\n- three\n",
+        )
+        .unwrap();
+
+        let tree = FileTree::from_directory(dir.path()).unwrap();
+        let manifest = Manifest::from_tree(&tree, ParseOptions::default()).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+
+        let readme = &manifest.files[0];
+        assert_eq!(readme.path, "README.md");
+        assert_eq!(readme.title.as_deref(), Some("Project"));
+        let anchors: Vec<_> = readme.headings.iter().map(|h| h.anchor.as_str()).collect();
+        assert_eq!(anchors, ["project", "usage"]);
+        // The code block is not prose
+        assert_eq!(readme.words, 5);
+        assert!(readme.modified.is_some());
+
+        let setup = &manifest.files[1];
+        assert_eq!(setup.path, "guide/setup.md");
+        assert_eq!(setup.title.as_deref(), Some("Getting Started"));
+        assert_eq!(setup.words, 4);
+
+        let json: serde_json::Value = serde_json::from_str(&manifest.to_json()).unwrap();
+        assert_eq!(json["files"][1]["headings"][0]["text"], "Setup");
+        assert_eq!(json["files"][0]["words"], 5);
+    }
+}
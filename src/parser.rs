@@ -1,9 +1,297 @@
-use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use pulldown_cmark::{
+    CodeBlockKind, CowStr, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd,
+};
+use regex::Regex;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+/// Bare URLs (`https://...`, `www....`) and email addresses
+static AUTOLINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b(?:https?://|www\.)[^\s<>]+|\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)+",
+    )
+    .expect("valid autolink regex")
+});
+
+/// `title="..."` (or single-quoted, or unquoted) in a fenced code block's info string
+static CODE_TITLE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:^|\s)title=(?:"([^"]*)"|'([^']*)'|(\S+))"#).expect("valid code title regex")
+});
+
+/// `[^label]` left as plain text because no definition matched it
+static UNDEFINED_FOOTNOTE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\^([^\]\s]+)\]").expect("valid footnote regex"));
 
 /// Represents a parsed Markdown document
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Document {
     pub elements: Vec<Element>,
+    /// Source byte range of each of `elements`, `None` where it could not be
+    /// found, or empty for hand-built documents. Offsets are into the input after
+    /// BOM removal and CRLF normalization.
+    pub spans: Vec<Option<Range<usize>>>,
+    /// Front matter at the start of the file, if any
+    pub frontmatter: Option<FrontMatter>,
+    /// The front matter `tags` list
+    pub tags: Vec<String>,
+    /// Problems found while parsing, such as undefined footnotes
+    pub warnings: Vec<String>,
+}
+
+impl Document {
+    /// Document title: the text of the first level-1 heading
+    pub fn title(&self) -> Option<&str> {
+        self.elements.iter().find_map(|element| match element {
+            Element::Heading { level: 1, content } => Some(content.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Title to show for the document: the front matter title, else the first
+    /// level-1 heading
+    pub fn display_title(&self) -> Option<&str> {
+        self.frontmatter
+            .as_ref()
+            .and_then(FrontMatter::title)
+            .or_else(|| self.title())
+    }
+
+    /// Source byte range of the top-level element at `index`, if known. Only
+    /// top-level elements have ranges; nested ones are covered by their parent's.
+    pub fn span(&self, index: usize) -> Option<Range<usize>> {
+        self.spans.get(index).cloned().flatten()
+    }
+
+    /// Number of words of prose: headings, paragraphs, lists, quotes, tables and
+    /// footnotes, but not code blocks or raw HTML
+    pub fn word_count(&self) -> usize {
+        fn words(text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+        fn count(elements: &[Element]) -> usize {
+            elements
+                .iter()
+                .map(|element| match element {
+                    Element::Heading { content, .. } => words(content),
+                    Element::Paragraph { content } => words(&inline_text(content)),
+                    Element::List { items, .. } => {
+                        items.iter().map(|item| count(&item.content)).sum()
+                    }
+                    Element::Table { headers, rows, .. } => headers
+                        .iter()
+                        .chain(rows.iter().flatten())
+                        .map(|cell| words(&inline_text(cell)))
+                        .sum(),
+                    Element::BlockQuote { content }
+                    | Element::Alert { content, .. }
+                    | Element::FootnoteDefinition { content, .. } => count(content),
+                    _ => 0,
+                })
+                .sum()
+        }
+        count(&self.elements)
+    }
+}
+
+/// Metadata from a `---` YAML or `+++` TOML block at the very start of a file
+#[derive(Debug, Clone, Default)]
+pub struct FrontMatter {
+    /// Top-level keys in source order
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    /// The block as written, without delimiters
+    pub raw: String,
+    /// Syntax the block is written in
+    pub format: FrontMatterFormat,
+}
+
+/// Syntax of a front matter block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrontMatterFormat {
+    /// Delimited by `---`
+    #[default]
+    Yaml,
+    /// Delimited by `+++`
+    Toml,
+}
+
+impl FrontMatterFormat {
+    /// Code block language for the raw block
+    pub fn language(self) -> &'static str {
+        match self {
+            FrontMatterFormat::Yaml => "yaml",
+            FrontMatterFormat::Toml => "toml",
+        }
+    }
+}
+
+/// Where renderers put footnote text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FootnoteMode {
+    /// Numbered references, with the definitions in a section at the end
+    #[default]
+    Section,
+    /// The definition in parentheses in place of the reference, without a section
+    Inline,
+    /// The definition as a tooltip on the reference in the browser (a section in the terminal)
+    Hover,
+}
+
+/// How renderers present a document's front matter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FrontMatterMode {
+    /// Leave it out
+    #[default]
+    Hide,
+    /// Key/value table at the top
+    Show,
+    /// The block as written, in a code block
+    Raw,
+}
+
+impl FrontMatter {
+    /// The `title` field, if it is a string
+    pub fn title(&self) -> Option<&str> {
+        self.fields.get("title").and_then(|title| title.as_str())
+    }
+
+    /// The `tags` field, given as a list or a comma-separated string
+    pub fn tags(&self) -> Vec<String> {
+        let tags: Vec<String> = match self.fields.get("tags") {
+            Some(serde_json::Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| match item {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    serde_json::Value::Number(n) => Some(n.to_string()),
+                    serde_json::Value::Bool(b) => Some(b.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            Some(serde_json::Value::String(s)) => s.split(',').map(String::from).collect(),
+            _ => Vec::new(),
+        };
+        tags.into_iter()
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    /// Fields as display text: strings as written, lists joined with commas, and
+    /// anything else as JSON
+    pub fn display_fields(&self) -> Vec<(String, String)> {
+        self.fields
+            .iter()
+            .map(|(key, value)| (key.clone(), display_value(value)))
+            .collect()
+    }
+
+    /// The `author` (or `authors`) and `date` fields as one line, like
+    /// `Jane Doe · 2024-01-02`
+    pub fn byline(&self) -> Option<String> {
+        let parts: Vec<String> = ["author", "authors", "date"]
+            .iter()
+            .filter_map(|key| self.fields.get(*key))
+            .map(display_value)
+            .map(|part| part.trim().to_string())
+            .filter(|part| !part.is_empty())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+/// A front matter value as display text
+fn display_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::Array(items) if !items.iter().any(|item| item.is_object()) => items
+            .iter()
+            .map(display_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+const BOM: char = '\u{feff}';
+
+/// Strip a leading byte order mark and convert CRLF line endings to LF
+pub fn normalize_input(input: &str) -> Cow<'_, str> {
+    let input = input.strip_prefix(BOM).unwrap_or(input);
+    if input.contains('\r') {
+        Cow::Owned(input.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// Split a leading front matter block from the markdown body.
+/// The block must open on the first line with `---` (YAML, closed by `---` or `...`)
+/// or `+++` (TOML, closed by `+++`) and parse as a mapping; anything else (such as a
+/// document starting with a horizontal rule) is left as-is.
+pub fn split_frontmatter(input: &str) -> (Option<FrontMatter>, &str) {
+    let input = input.strip_prefix(BOM).unwrap_or(input);
+    let Some((first_line, rest)) = input.split_once('\n') else {
+        return (None, input);
+    };
+    let toml = match first_line.trim_end() {
+        "---" => false,
+        "+++" => true,
+        _ => return (None, input),
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let delimiter = line.trim_end();
+        let closes = if toml {
+            delimiter == "+++"
+        } else {
+            delimiter == "---" || delimiter == "..."
+        };
+        if closes {
+            let raw = &rest[..offset];
+            let fields = if raw.trim().is_empty() {
+                Some(serde_json::Map::new())
+            } else if toml {
+                toml::from_str(raw).ok().map(toml_table_to_json)
+            } else {
+                serde_yaml::from_str(raw).ok()
+            };
+            let Some(fields) = fields else {
+                return (None, input);
+            };
+            let frontmatter = FrontMatter {
+                fields,
+                raw: raw.to_string(),
+                format: if toml {
+                    FrontMatterFormat::Toml
+                } else {
+                    FrontMatterFormat::Yaml
+                },
+            };
+            return (Some(frontmatter), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+
+    (None, input)
+}
+
+/// TOML values as JSON, with dates and times as their TOML text
+fn toml_table_to_json(table: toml::Table) -> serde_json::Map<String, serde_json::Value> {
+    fn convert(value: toml::Value) -> serde_json::Value {
+        match value {
+            toml::Value::String(s) => s.into(),
+            toml::Value::Integer(i) => i.into(),
+            toml::Value::Float(f) => f.into(),
+            toml::Value::Boolean(b) => b.into(),
+            toml::Value::Datetime(d) => d.to_string().into(),
+            toml::Value::Array(items) => items.into_iter().map(convert).collect(),
+            toml::Value::Table(table) => toml_table_to_json(table).into(),
+        }
+    }
+    table.into_iter().map(|(k, v)| (k, convert(v))).collect()
 }
 
 /// Represents a single element in the document
@@ -18,6 +306,8 @@ pub enum Element {
     },
     CodeBlock {
         language: Option<String>,
+        /// File name from `title="..."` in the info string
+        title: Option<String>,
         content: String,
     },
     List {
@@ -25,14 +315,20 @@ pub enum Element {
         start: Option<u64>,
         items: Vec<ListItem>,
     },
+    /// Cells hold inline content, with `<br>` as hard breaks
     Table {
-        headers: Vec<String>,
+        headers: Vec<Vec<InlineElement>>,
         alignments: Vec<Alignment>,
-        rows: Vec<Vec<String>>,
+        rows: Vec<Vec<Vec<InlineElement>>>,
     },
     BlockQuote {
         content: Vec<Element>,
     },
+    /// Blockquote starting with a `[!NOTE]`-style marker line, without the marker
+    Alert {
+        kind: AlertKind,
+        content: Vec<Element>,
+    },
     HorizontalRule,
     Image {
         url: String,
@@ -45,6 +341,46 @@ pub enum Element {
     },
     /// Raw HTML block
     Html(String),
+    /// `$$...$$` display math alone in a paragraph
+    MathBlock {
+        content: String,
+    },
+}
+
+/// Kind of a GitHub alert, named by the `> [!NOTE]` line that starts a blockquote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl AlertKind {
+    /// The kind named by a `[!NOTE]`-style marker, in any case
+    pub fn from_marker(text: &str) -> Option<Self> {
+        let name = text.trim().strip_prefix("[!")?.strip_suffix(']')?;
+        match name.to_ascii_lowercase().as_str() {
+            "note" => Some(Self::Note),
+            "tip" => Some(Self::Tip),
+            "important" => Some(Self::Important),
+            "warning" => Some(Self::Warning),
+            "caution" => Some(Self::Caution),
+            _ => None,
+        }
+    }
+
+    /// Title shown above the content
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Tip => "Tip",
+            Self::Important => "Important",
+            Self::Warning => "Warning",
+            Self::Caution => "Caution",
+        }
+    }
 }
 
 /// A list item containing zero or more block elements
@@ -76,6 +412,13 @@ pub enum InlineElement {
     TaskListMarker(bool),
     /// Inline HTML (e.g., <br>, <span>)
     InlineHtml(String),
+    /// Key from `<kbd>...</kbd>` inline HTML
+    Kbd(String),
+    /// TeX between `$` delimiters, or `$$` when `display`
+    Math {
+        tex: String,
+        display: bool,
+    },
     SoftBreak,
     HardBreak,
 }
@@ -100,7 +443,7 @@ impl From<pulldown_cmark::Alignment> for Alignment {
 }
 
 /// Entry in the table of contents
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TocEntry {
     pub level: u8,
     pub text: String,
@@ -152,22 +495,320 @@ impl AnchorGenerator {
 
 /// Generate table of contents from a document
 pub fn generate_toc(document: &Document) -> Vec<TocEntry> {
+    // Headings are numbered in source order like the HTML ids, including those
+    // nested in quotes and lists; headings inside footnotes only take up an anchor
+    fn collect(
+        elements: &[Element],
+        anchor_gen: &mut AnchorGenerator,
+        entries: &mut Vec<TocEntry>,
+        in_footnote: bool,
+    ) {
+        for element in elements {
+            match element {
+                Element::Heading { level, content } => {
+                    let anchor = anchor_gen.generate(content);
+                    if !in_footnote {
+                        entries.push(TocEntry {
+                            level: *level,
+                            text: content.clone(),
+                            anchor,
+                        });
+                    }
+                }
+                Element::BlockQuote { content } | Element::Alert { content, .. } => {
+                    collect(content, anchor_gen, entries, in_footnote)
+                }
+                Element::List { items, .. } => {
+                    for item in items {
+                        collect(&item.content, anchor_gen, entries, in_footnote);
+                    }
+                }
+                Element::FootnoteDefinition { content, .. } => {
+                    collect(content, anchor_gen, entries, true)
+                }
+                _ => {}
+            }
+        }
+    }
+
     let mut entries = Vec::new();
-    let mut anchor_gen = AnchorGenerator::new();
+    collect(
+        &document.elements,
+        &mut AnchorGenerator::new(),
+        &mut entries,
+        false,
+    );
+    entries
+}
 
-    for element in &document.elements {
-        if let Element::Heading { level, content } = element {
-            let anchor = anchor_gen.generate(content);
+/// Language and title of a fenced code block from its info string, such as
+/// `rust title="main.rs"`. The language is the first word unless that is the title.
+pub fn parse_code_info(info: &str) -> (Option<String>, Option<String>) {
+    let language = info
+        .split_whitespace()
+        .next()
+        .filter(|word| !word.starts_with("title="))
+        .map(str::to_string);
+    let title = CODE_TITLE_RE.captures(info).and_then(|caps| {
+        caps.iter()
+            .skip(1)
+            .flatten()
+            .next()
+            .map(|m| m.as_str().to_string())
+    });
+    (language, title.filter(|title| !title.is_empty()))
+}
 
-            entries.push(TocEntry {
-                level: *level,
-                text: content.clone(),
-                anchor,
-            });
+/// Add a heading event's plain text to `text`, which feeds both TOC entries and
+/// anchors in every renderer: text and inline code, with line breaks as spaces
+pub fn push_heading_text(text: &mut String, event: &Event) {
+    match event {
+        Event::Text(s) | Event::Code(s) | Event::InlineMath(s) | Event::DisplayMath(s) => {
+            text.push_str(s)
         }
+        Event::SoftBreak | Event::HardBreak => text.push(' '),
+        _ => {}
     }
+}
 
-    entries
+/// Plain text of inline content, without formatting; line breaks become spaces
+pub fn inline_text(inlines: &[InlineElement]) -> String {
+    fn push(text: &mut String, inlines: &[InlineElement]) {
+        for inline in inlines {
+            match inline {
+                InlineElement::Text(s)
+                | InlineElement::Code(s)
+                | InlineElement::Kbd(s)
+                | InlineElement::Math { tex: s, .. } => text.push_str(s),
+                InlineElement::Strong(content)
+                | InlineElement::Emphasis(content)
+                | InlineElement::Strikethrough(content)
+                | InlineElement::Link { content, .. } => push(text, content),
+                InlineElement::SoftBreak | InlineElement::HardBreak => text.push(' '),
+                _ => {}
+            }
+        }
+    }
+
+    let mut text = String::new();
+    push(&mut text, inlines);
+    text
+}
+
+/// Turn bare URLs and email addresses in text into links, like GFM's autolink extension.
+/// Text already inside links, images, and code blocks is left untouched.
+pub fn autolink_events(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut skip_depth = 0usize;
+    // pulldown-cmark may split a URL across several text events, so merge them first
+    let mut pending = String::new();
+
+    for event in events {
+        if let Event::Text(text) = &event
+            && skip_depth == 0
+        {
+            pending.push_str(text);
+            continue;
+        }
+        push_autolinked_text(&mut result, &mut pending);
+
+        match &event {
+            Event::Start(Tag::Link { .. } | Tag::Image { .. } | Tag::CodeBlock(_)) => {
+                skip_depth += 1;
+            }
+            Event::End(TagEnd::Link | TagEnd::Image | TagEnd::CodeBlock) => {
+                skip_depth = skip_depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        result.push(event);
+    }
+    push_autolinked_text(&mut result, &mut pending);
+
+    result
+}
+
+/// Emit `text` as text and link events, then clear it
+fn push_autolinked_text(result: &mut Vec<Event<'_>>, text: &mut String) {
+    if text.is_empty() {
+        return;
+    }
+
+    let mut last = 0;
+    for m in AUTOLINK_RE.find_iter(text) {
+        let candidate = trim_autolink(m.as_str());
+        if candidate.is_empty() || m.start() < last {
+            continue;
+        }
+
+        let (url, link_type) = if candidate.contains("://") {
+            (candidate.to_string(), LinkType::Autolink)
+        } else if candidate.starts_with("www.") {
+            (format!("http://{}", candidate), LinkType::Autolink)
+        } else if candidate.contains('@') {
+            (format!("mailto:{}", candidate), LinkType::Email)
+        } else {
+            continue;
+        };
+
+        if m.start() > last {
+            result.push(Event::Text(CowStr::from(text[last..m.start()].to_string())));
+        }
+        result.push(Event::Start(Tag::Link {
+            link_type,
+            dest_url: CowStr::from(url),
+            title: CowStr::Borrowed(""),
+            id: CowStr::Borrowed(""),
+        }));
+        result.push(Event::Text(CowStr::from(candidate.to_string())));
+        result.push(Event::End(TagEnd::Link));
+        last = m.start() + candidate.len();
+    }
+
+    if last < text.len() {
+        result.push(Event::Text(CowStr::from(text[last..].to_string())));
+    }
+    text.clear();
+}
+
+/// Drop trailing punctuation that ends the sentence rather than the URL,
+/// including a closing parenthesis without a matching opening one
+fn trim_autolink(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let trimmed =
+            url.trim_end_matches(['?', '!', '.', ',', ':', ';', '*', '_', '~', '\'', '"']);
+        let trimmed = if trimmed.ends_with(')')
+            && trimmed.matches(')').count() > trimmed.matches('(').count()
+        {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// Drop repeated footnote definitions (the first one wins) and report footnote problems:
+/// duplicates, references without a definition, and definitions that are never referenced.
+/// Expects text merged by [`autolink_events`], since pulldown-cmark leaves an undefined
+/// reference as split text events.
+pub fn check_footnotes(events: Vec<Event<'_>>) -> (Vec<Event<'_>>, Vec<String>) {
+    let mut result = Vec::with_capacity(events.len());
+    let mut warnings = Vec::new();
+    let mut defined: Vec<String> = Vec::new();
+    let mut referenced: Vec<String> = Vec::new();
+    let mut undefined: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    // Nesting depth inside a duplicate definition being skipped
+    let mut skip_depth = 0usize;
+
+    for event in events {
+        if skip_depth > 0 {
+            match &event {
+                Event::Start(Tag::FootnoteDefinition(_)) => skip_depth += 1,
+                Event::End(TagEnd::FootnoteDefinition) => skip_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+        match &event {
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                if defined.iter().any(|l| l == label.as_ref()) {
+                    warnings.push(format!(
+                        "duplicate footnote definition [^{}]; keeping the first",
+                        label
+                    ));
+                    skip_depth = 1;
+                    continue;
+                }
+                defined.push(label.to_string());
+            }
+            Event::FootnoteReference(label) if !referenced.iter().any(|l| l == label.as_ref()) => {
+                referenced.push(label.to_string());
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) if !in_code_block => {
+                for caps in UNDEFINED_FOOTNOTE_RE.captures_iter(text) {
+                    if !undefined.iter().any(|l| l == &caps[1]) {
+                        undefined.push(caps[1].to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+        result.push(event);
+    }
+
+    for label in undefined
+        .iter()
+        .chain(referenced.iter().filter(|l| !defined.contains(l)))
+    {
+        warnings.push(format!("undefined footnote reference [^{}]", label));
+    }
+    for label in defined.iter().filter(|l| !referenced.contains(l)) {
+        warnings.push(format!("unused footnote definition [^{}]", label));
+    }
+
+    (result, warnings)
+}
+
+/// Report images without alt text, which screen readers cannot describe
+pub fn check_image_alt(events: &[Event<'_>]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    // URL and whether alt text was seen, while inside an image
+    let mut image: Option<(&str, bool)> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::Image { dest_url, .. }) => image = Some((dest_url, false)),
+            Event::Text(text) | Event::Code(text) if !text.trim().is_empty() => {
+                if let Some((_, has_alt)) = &mut image {
+                    *has_alt = true;
+                }
+            }
+            Event::End(TagEnd::Image) => {
+                if let Some((url, false)) = image {
+                    warnings.push(format!("image without alt text: {}", url));
+                }
+                image = None;
+            }
+            _ => {}
+        }
+    }
+    warnings
+}
+
+/// An alert if the quote's first line is only a `[!NOTE]`-style marker, which is
+/// removed, and the quote unchanged otherwise
+fn alert_or_quote(mut content: Vec<Element>) -> Element {
+    let kind = match content.first() {
+        Some(Element::Paragraph { content }) => match &content[..] {
+            [InlineElement::Text(marker)]
+            | [
+                InlineElement::Text(marker),
+                InlineElement::SoftBreak | InlineElement::HardBreak,
+                ..,
+            ] => AlertKind::from_marker(marker),
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(kind) = kind else {
+        return Element::BlockQuote { content };
+    };
+
+    // Drop the marker line, and the paragraph if nothing else is in it
+    if let Some(Element::Paragraph { content: inlines }) = content.first_mut() {
+        inlines.drain(..inlines.len().min(2));
+        if inlines.is_empty() {
+            content.remove(0);
+        }
+    }
+    Element::Alert { kind, content }
 }
 
 fn heading_level_to_u8(level: HeadingLevel) -> u8 {
@@ -181,29 +822,347 @@ fn heading_level_to_u8(level: HeadingLevel) -> u8 {
     }
 }
 
+/// Markdown extensions shared by the terminal parser and the HTML renderer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Curly quotes, en/em dashes, and ellipses (`"a" -- b...`)
+    pub smart_punctuation: bool,
+}
+
+impl ParseOptions {
+    /// pulldown-cmark options for these settings
+    pub fn to_pulldown(self) -> Options {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_TASKLISTS);
+        options.insert(Options::ENABLE_FOOTNOTES);
+        if self.smart_punctuation {
+            options.insert(Options::ENABLE_SMART_PUNCTUATION);
+        }
+        options
+    }
+}
+
 /// Parse a Markdown string into a Document
 pub fn parse_markdown(input: &str) -> Document {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
-    options.insert(Options::ENABLE_FOOTNOTES);
+    parse_markdown_with_options(input, ParseOptions::default())
+}
+
+/// Parse a Markdown string into a Document with the given extensions
+pub fn parse_markdown_with_options(input: &str, options: ParseOptions) -> Document {
+    parse_document(input, options).0
+}
+
+/// Parse a Document, also returning the warnings of each top-level element
+fn parse_document(input: &str, options: ParseOptions) -> (Document, Vec<Vec<String>>) {
+    let input = normalize_input(input);
+    let (frontmatter, body) = split_frontmatter(&input);
+    let body_start = input.len() - body.len();
+    let ParsedBody {
+        elements,
+        spans,
+        mut warnings,
+        block_warnings,
+    } = parse_body(body, options);
+    warnings.extend(block_warnings.iter().flatten().cloned());
+
+    let document = Document {
+        elements,
+        spans: spans
+            .into_iter()
+            .map(|span| span.map(|span| span.start + body_start..span.end + body_start))
+            .collect(),
+        tags: frontmatter
+            .as_ref()
+            .map(FrontMatter::tags)
+            .unwrap_or_default(),
+        frontmatter,
+        warnings,
+    };
+    (document, block_warnings)
+}
 
-    let parser = Parser::new_ext(input, options);
-    let events: Vec<Event> = parser.collect();
+/// Top-level elements of markdown without front matter
+struct ParsedBody {
+    elements: Vec<Element>,
+    /// Source range of each element, if it could be matched up
+    spans: Vec<Option<Range<usize>>>,
+    /// Warnings about the document as a whole, such as undefined footnotes
+    warnings: Vec<String>,
+    /// Warnings about each element, such as images without alt text
+    block_warnings: Vec<Vec<String>>,
+}
+
+fn parse_body(input: &str, options: ParseOptions) -> ParsedBody {
+    let (raw, ranges): (Vec<_>, Vec<_>) = Parser::new_ext(input, options.to_pulldown())
+        .into_offset_iter()
+        .unzip();
+    let blocks = top_level_blocks(&raw, &ranges);
+    let raw = math_events(raw, &ranges, input);
+    let (events, mut warnings) = check_footnotes(autolink_events(raw));
 
     let mut elements = Vec::new();
+    let mut spans = Vec::new();
+    let mut block_warnings = Vec::new();
+    // Blocks before this one have been matched to an element
+    let mut next_block = 0;
     let mut index = 0;
 
     while index < events.len() {
+        // Rewriting events only drops whole blocks, so the remaining ones still
+        // appear in source order. A block that can't be found leaves the later
+        // ones to match.
+        let span = blocks[next_block..]
+            .iter()
+            .position(|(event, _)| *event == events[index])
+            .map(|k| {
+                next_block += k + 1;
+                blocks[next_block - 1].1.clone()
+            });
         let (element, new_index) = parse_element(&events, index);
+        let alt_warnings = check_image_alt(&events[index..new_index]);
         if let Some(el) = element {
             elements.push(el);
+            block_warnings.push(alt_warnings);
+            spans.push(span);
+        } else {
+            warnings.extend(alt_warnings);
         }
         index = new_index;
     }
 
-    Document { elements }
+    ParsedBody {
+        elements,
+        spans,
+        warnings,
+        block_warnings,
+    }
+}
+
+/// The first event and source range of every top-level block, in document order
+pub(crate) fn top_level_blocks<'a>(
+    events: &[Event<'a>],
+    ranges: &[Range<usize>],
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut blocks = Vec::new();
+    let mut depth = 0usize;
+    for (event, range) in events.iter().zip(ranges) {
+        if depth == 0 {
+            blocks.push((event.clone(), range.clone()));
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    blocks
+}
+
+fn line_start(source: &str, pos: usize) -> usize {
+    source[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Footnotes and link reference definitions resolve across the whole document
+const REFERENCE_MARKERS: [&str; 2] = ["[^", "]:"];
+
+/// Fences and HTML blocks can swallow the text after the re-parsed blocks
+const OPEN_ENDED_MARKERS: [&str; 3] = ["```", "~~~", "<"];
+
+/// Keeps a parsed document up to date across edits, re-parsing only the top-level
+/// blocks around the changed text when that is safe and the whole input otherwise.
+#[derive(Debug, Clone)]
+pub struct IncrementalParser {
+    options: ParseOptions,
+    /// Normalized source of `document`
+    source: String,
+    /// Where the body starts after any front matter
+    body_start: usize,
+    document: Document,
+    /// Warnings of each top-level element, spliced along with the elements
+    block_warnings: Vec<Vec<String>>,
+}
+
+impl IncrementalParser {
+    pub fn new(input: &str, options: ParseOptions) -> Self {
+        let mut parser = Self {
+            options,
+            source: String::new(),
+            body_start: 0,
+            document: Document::default(),
+            block_warnings: Vec::new(),
+        };
+        parser.parse_full(normalize_input(input).into_owned());
+        parser
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Update the document to `input`. Returns `false` when it had to be parsed in full.
+    pub fn update(&mut self, input: &str) -> bool {
+        let input = normalize_input(input).into_owned();
+        if input == self.source {
+            return true;
+        }
+        if self.parse_changed(&input).is_some() {
+            self.source = input;
+            true
+        } else {
+            self.parse_full(input);
+            false
+        }
+    }
+
+    fn parse_full(&mut self, source: String) {
+        (self.document, self.block_warnings) = parse_document(&source, self.options);
+        self.body_start = source.len() - split_frontmatter(&source).1.len();
+        self.source = source;
+    }
+
+    /// Whether block `i + 1` parses the same no matter what block `i` contains:
+    /// a blank line separates them, and it is neither indented nor a list that could
+    /// continue a list before it
+    fn independent(&self, blocks: &[Range<usize>], i: usize) -> bool {
+        let source = self.source.as_str();
+        let end = blocks[i].end;
+        let after_block = source[end - 1..]
+            .find('\n')
+            .map_or(source.len(), |k| end + k);
+        let next = line_start(source, blocks[i + 1].start);
+        after_block < next
+            && !source[next..].starts_with([' ', '\t'])
+            && !matches!(self.document.elements[i + 1], Element::List { .. })
+    }
+
+    /// Re-parse the blocks around the change, or `None` when a full parse is needed
+    fn parse_changed(&mut self, new: &str) -> Option<()> {
+        let old = self.source.as_str();
+        // Every block's range is needed to find the ones around the change
+        let blocks: Vec<Range<usize>> =
+            self.document.spans.iter().cloned().collect::<Option<_>>()?;
+        if blocks.is_empty() || REFERENCE_MARKERS.iter().any(|m| new.contains(m)) {
+            return None;
+        }
+
+        // The changed text is old[prefix..old_end], now new[prefix..new_end]
+        let mut prefix = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let mut suffix = old
+            .bytes()
+            .rev()
+            .zip(new.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) {
+            suffix -= 1;
+        }
+        let old_end = old.len() - suffix;
+        let new_body_start = new.len() - split_frontmatter(new).1.len();
+        if prefix < self.body_start || new_body_start != self.body_start {
+            return None;
+        }
+
+        // Changed blocks plus one neighbor on each side, widened until the blocks
+        // outside can no longer merge with the re-parsed ones
+        let last = blocks.len() - 1;
+        let first_changed = blocks.partition_point(|b| b.end < prefix);
+        let after_changed = blocks.partition_point(|b| b.start <= old_end);
+        let mut lo = first_changed.min(after_changed).saturating_sub(1);
+        let mut hi = after_changed.min(last);
+        while lo > 0 && !self.independent(&blocks, lo - 1) {
+            lo -= 1;
+        }
+        while hi < last && !self.independent(&blocks, hi) {
+            hi += 1;
+        }
+
+        let start = if lo == 0 {
+            self.body_start
+        } else {
+            line_start(old, blocks[lo].start)
+        };
+        let end = if hi == last {
+            old.len()
+        } else {
+            blocks[hi].end
+        };
+        let new_end = end + new.len() - old.len();
+
+        let slice = &new[start..new_end];
+        if OPEN_ENDED_MARKERS
+            .iter()
+            .any(|m| slice.contains(m) || old[start..end].contains(m))
+        {
+            return None;
+        }
+        // Without reference markers there are no document-wide warnings
+        let ParsedBody {
+            elements,
+            spans: changed,
+            block_warnings,
+            ..
+        } = parse_body(slice, self.options);
+        let changed: Vec<Range<usize>> = changed.into_iter().collect::<Option<_>>()?;
+        if elements.len() != changed.len() {
+            return None;
+        }
+
+        let tail: Vec<Range<usize>> = blocks[hi + 1..]
+            .iter()
+            .map(|b| b.start + new.len() - old.len()..b.end + new.len() - old.len())
+            .collect();
+        let spans = &mut self.document.spans;
+        spans.truncate(lo);
+        spans.extend(
+            changed
+                .into_iter()
+                .map(|r| Some(r.start + start..r.end + start)),
+        );
+        spans.extend(tail.into_iter().map(Some));
+        self.document.elements.splice(lo..=hi, elements);
+        self.block_warnings.splice(lo..=hi, block_warnings);
+        self.document.warnings = self.block_warnings.iter().flatten().cloned().collect();
+        Some(())
+    }
+}
+
+/// Flip the `index`th task list marker (in document order) between `[ ]` and `[x]`
+///
+/// Returns `None` when the document has fewer task markers.
+pub fn toggle_task(input: &str, index: usize) -> Option<String> {
+    let body = split_frontmatter(input).1;
+    let body_start = input.len() - body.len();
+    let range = Parser::new_ext(body, ParseOptions::default().to_pulldown())
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::TaskListMarker(_)))
+        .nth(index)?
+        .1;
+    let range = range.start + body_start..range.end + body_start;
+
+    // The marker span starts at `[`; only the single character inside it changes
+    let start = range.start + input[range.clone()].find('[')?;
+    let new_mark = match input.get(start..start + 3)? {
+        "[ ]" => "x",
+        "[x]" | "[X]" => " ",
+        _ => return None,
+    };
+
+    let mut output = String::with_capacity(input.len());
+    output.push_str(&input[..start + 1]);
+    output.push_str(new_mark);
+    output.push_str(&input[start + 2..]);
+    Some(output)
 }
 
 /// Helper to compare TagEnd variants properly (handles variants with data)
@@ -223,9 +1182,185 @@ fn tag_end_matches(actual: &TagEnd, expected: &TagEnd) -> bool {
             // Block boundaries that contain inline content
             | (TagEnd::Paragraph, TagEnd::Paragraph)
             | (TagEnd::Item, TagEnd::Item)
+            | (TagEnd::TableCell, TagEnd::TableCell)
     )
 }
 
+/// Whether inline HTML is a `<br>` tag (`<br>`, `<br/>`, `<br />`, any case)
+fn is_line_break_tag(html: &str) -> bool {
+    let tag = html.trim().to_ascii_lowercase();
+    tag.strip_prefix("<br")
+        .and_then(|rest| rest.strip_suffix('>'))
+        .is_some_and(|rest| rest.trim_end_matches('/').trim().is_empty())
+}
+
+/// Text of a `<kbd>` element starting at `events[index]`, and the index of its
+/// closing `</kbd>`
+fn parse_kbd(events: &[Event], index: usize) -> Option<(String, usize)> {
+    let Event::InlineHtml(open) = &events[index] else {
+        return None;
+    };
+    if !open.trim().eq_ignore_ascii_case("<kbd>") {
+        return None;
+    }
+    let mut key = String::new();
+    for (offset, event) in events[index + 1..].iter().enumerate() {
+        match event {
+            Event::Text(text) => key.push_str(text),
+            Event::InlineHtml(close) if close.trim().eq_ignore_ascii_case("</kbd>") => {
+                return Some((key, index + 1 + offset));
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Replace `$...$` and `$$...$$` math in text with inline and display math events.
+/// pulldown-cmark's own math syntax is left disabled for these rules. Text and
+/// soft breaks are joined first so display math can span lines. A `$` escaped
+/// with a backslash (or written as an entity) in `source` stays literal, and the
+/// TeX keeps the backslashes of escapes like `\\` and `\{`.
+pub(crate) fn math_events<'a>(
+    events: Vec<Event<'a>>,
+    ranges: &[Range<usize>],
+    source: &str,
+) -> Vec<Event<'a>> {
+    if !source.contains('$') {
+        return events;
+    }
+
+    let mut result = Vec::with_capacity(events.len());
+    let mut run: Vec<Event<'a>> = Vec::new();
+    // Offsets in the joined run text of characters escaped with a backslash (or a
+    // dollar written as an entity), which never delimit math and keep their
+    // backslash inside it
+    let mut escaped = Vec::new();
+    let mut text = String::new();
+    let mut skip_depth = 0usize;
+
+    let flush = |result: &mut Vec<Event<'a>>,
+                 run: &mut Vec<Event<'a>>,
+                 escaped: &mut Vec<usize>,
+                 text: &mut String| {
+        let spans = math_spans(text, escaped);
+        if spans.is_empty() {
+            result.append(run);
+        } else {
+            run.clear();
+            let mut pos = 0;
+            for (start, end, display) in spans {
+                push_text_events(result, &text[pos..start]);
+                let delimiter = if display { 2 } else { 1 };
+                let mut tex = String::new();
+                for (i, c) in text[start + delimiter..end - delimiter].char_indices() {
+                    if escaped.contains(&(start + delimiter + i)) {
+                        tex.push('\\');
+                    }
+                    tex.push(c);
+                }
+                // Display math keeps its lines, for environments like `aligned`
+                let tex = tex.trim();
+                let tex = if display {
+                    tex.to_string()
+                } else {
+                    tex.replace('\n', " ")
+                };
+                let tex = CowStr::Boxed(tex.into_boxed_str());
+                result.push(if display {
+                    Event::DisplayMath(tex)
+                } else {
+                    Event::InlineMath(tex)
+                });
+                pos = end;
+            }
+            push_text_events(result, &text[pos..]);
+        }
+        escaped.clear();
+        text.clear();
+    };
+
+    for (event, range) in events.into_iter().zip(ranges) {
+        match &event {
+            Event::Start(Tag::CodeBlock(_) | Tag::Image { .. }) => skip_depth += 1,
+            Event::End(TagEnd::CodeBlock | TagEnd::Image) => skip_depth -= 1,
+            Event::Text(s) if skip_depth == 0 => {
+                let escape = source[..range.start].ends_with('\\')
+                    || (s.starts_with('$') && !source[range.clone()].starts_with('$'));
+                if escape {
+                    escaped.push(text.len());
+                }
+                text.push_str(s);
+                run.push(event);
+                continue;
+            }
+            Event::SoftBreak if skip_depth == 0 => {
+                text.push('\n');
+                run.push(event);
+                continue;
+            }
+            _ => {}
+        }
+        flush(&mut result, &mut run, &mut escaped, &mut text);
+        result.push(event);
+    }
+    flush(&mut result, &mut run, &mut escaped, &mut text);
+    result
+}
+
+/// Text events for `text`, with soft breaks at its newlines
+fn push_text_events(result: &mut Vec<Event<'_>>, text: &str) {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            result.push(Event::SoftBreak);
+        }
+        if !line.is_empty() {
+            result.push(Event::Text(line.to_string().into()));
+        }
+    }
+}
+
+/// Byte ranges of math in `text`, delimiters included, with whether each is `$$`
+/// display math. Like pandoc, an inline `$` must not be followed by a space, and
+/// the closing `$` must not follow a space or precede a digit, so `$5 and $6` is text.
+/// Dollars at the `escaped` offsets never delimit math.
+fn math_spans(text: &str, escaped: &[usize]) -> Vec<(usize, usize, bool)> {
+    let bytes = text.as_bytes();
+    let dollar = |pos: usize| bytes.get(pos) == Some(&b'$') && !escaped.contains(&pos);
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !dollar(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if dollar(start + 1) {
+            i = start + 2;
+            if let Some(close) = (i..bytes.len()).find(|&p| dollar(p) && dollar(p + 1))
+                && !text[i..close].trim().is_empty()
+            {
+                spans.push((start, close + 2, true));
+                i = close + 2;
+            }
+            continue;
+        }
+        i = start + 1;
+        if text[i..].starts_with(char::is_whitespace) {
+            continue;
+        }
+        if let Some(close) = (i + 1..bytes.len()).find(|&p| {
+            dollar(p)
+                && !text[..p].ends_with(char::is_whitespace)
+                && !text[p + 1..].starts_with(|c: char| c.is_ascii_digit())
+        }) {
+            spans.push((start, close + 1, false));
+            i = close + 1;
+        }
+    }
+    spans
+}
+
 /// Parse inline elements recursively, handling nested structures like **[link](url)**
 fn parse_inline_elements(
     events: &[Event],
@@ -250,7 +1385,7 @@ fn parse_inline_elements(
             // This ensures nested lists, code blocks, etc. within list items are parsed correctly
             Event::Start(Tag::List(_))
             | Event::Start(Tag::CodeBlock(_))
-            | Event::Start(Tag::BlockQuote)
+            | Event::Start(Tag::BlockQuote(_))
             | Event::Start(Tag::Table(_)) => {
                 return (elements, index);
             }
@@ -260,14 +1395,14 @@ fn parse_inline_elements(
             // When end_tag is Some (parsing nested inline), we skip these and let parent handle
             Event::End(TagEnd::Paragraph)
             | Event::End(TagEnd::Item)
-            | Event::End(TagEnd::BlockQuote)
-            | Event::End(TagEnd::FootnoteDefinition) => {
-                if end_tag.is_none() {
-                    // Top-level parsing, this is our boundary
-                    return (elements, index);
-                }
-                // Inside nested inline element - skip and continue
-                // This shouldn't happen in well-formed markdown, but handle gracefully
+            | Event::End(TagEnd::BlockQuote(_))
+            | Event::End(TagEnd::FootnoteDefinition)
+                if end_tag.is_none() =>
+            {
+                // Top-level parsing, this is our boundary
+                // Inside a nested inline element these fall through and are skipped
+                // (this shouldn't happen in well-formed markdown, but handle gracefully)
+                return (elements, index);
             }
 
             Event::Text(text) => {
@@ -334,8 +1469,26 @@ fn parse_inline_elements(
                 elements.push(InlineElement::TaskListMarker(*checked));
             }
 
-            Event::InlineHtml(html) => {
-                elements.push(InlineElement::InlineHtml(html.to_string()));
+            Event::InlineHtml(html) => match parse_kbd(events, index) {
+                Some((key, end_index)) => {
+                    elements.push(InlineElement::Kbd(key));
+                    index = end_index;
+                }
+                None => elements.push(InlineElement::InlineHtml(html.to_string())),
+            },
+
+            Event::InlineMath(tex) => {
+                elements.push(InlineElement::Math {
+                    tex: tex.to_string(),
+                    display: false,
+                });
+            }
+
+            Event::DisplayMath(tex) => {
+                elements.push(InlineElement::Math {
+                    tex: tex.to_string(),
+                    display: true,
+                });
             }
 
             Event::Start(Tag::Image {
@@ -394,10 +1547,7 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
                     Event::End(TagEnd::Heading(_)) => {
                         break;
                     }
-                    Event::Text(text) | Event::Code(text) => {
-                        content.push_str(text);
-                    }
-                    _ => {}
+                    event => push_heading_text(&mut content, event),
                 }
                 index += 1;
             }
@@ -406,26 +1556,23 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
         }
 
         Event::Start(Tag::Paragraph) => {
-            let (inline_elements, end_index) =
+            let (mut inline_elements, end_index) =
                 parse_inline_elements(events, start + 1, Some(TagEnd::Paragraph));
-            (
-                Some(Element::Paragraph {
+            let element = match &mut inline_elements[..] {
+                [InlineElement::Math { tex, display: true }] => Element::MathBlock {
+                    content: std::mem::take(tex),
+                },
+                _ => Element::Paragraph {
                     content: inline_elements,
-                }),
-                end_index + 1,
-            )
+                },
+            };
+            (Some(element), end_index + 1)
         }
 
         Event::Start(Tag::CodeBlock(kind)) => {
-            let language = match kind {
-                CodeBlockKind::Fenced(lang) => {
-                    if lang.is_empty() {
-                        None
-                    } else {
-                        Some(lang.to_string())
-                    }
-                }
-                CodeBlockKind::Indented => None,
+            let (language, title) = match kind {
+                CodeBlockKind::Fenced(info) => parse_code_info(info),
+                CodeBlockKind::Indented => (None, None),
             };
 
             let mut content = String::new();
@@ -444,7 +1591,14 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
                 index += 1;
             }
 
-            (Some(Element::CodeBlock { language, content }), index + 1)
+            (
+                Some(Element::CodeBlock {
+                    language,
+                    title,
+                    content,
+                }),
+                index + 1,
+            )
         }
 
         Event::Start(Tag::List(first_item_number)) => {
@@ -470,9 +1624,10 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
                                 }
                                 // Block elements: parse recursively
                                 Event::Start(Tag::List(_))
+                                | Event::Start(Tag::Heading { .. })
                                 | Event::Start(Tag::Paragraph)
                                 | Event::Start(Tag::CodeBlock(_))
-                                | Event::Start(Tag::BlockQuote)
+                                | Event::Start(Tag::BlockQuote(_))
                                 | Event::Start(Tag::Table(_)) => {
                                     let (element, new_index) = parse_element(events, index);
                                     if let Some(el) = element {
@@ -489,7 +1644,12 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
                                 | Event::Start(Tag::Strong)
                                 | Event::Start(Tag::Emphasis)
                                 | Event::Start(Tag::Strikethrough)
-                                | Event::Start(Tag::Link { .. }) => {
+                                | Event::Start(Tag::Link { .. })
+                                | Event::Start(Tag::Image { .. })
+                                | Event::InlineHtml(_)
+                                | Event::InlineMath(_)
+                                | Event::DisplayMath(_)
+                                | Event::FootnoteReference(_) => {
                                     let (inline_content, new_index) =
                                         parse_inline_elements(events, index, Some(TagEnd::Item));
                                     if !inline_content.is_empty() {
@@ -532,7 +1692,6 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
             let mut rows = Vec::new();
             let mut index = start + 1;
             let mut current_row = Vec::new();
-            let mut current_cell = String::new();
 
             while index < events.len() {
                 match &events[index] {
@@ -543,7 +1702,7 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
                         current_row = Vec::new();
                     }
                     Event::End(TagEnd::TableHead) => {
-                        // TableHead contains cells directly without TableRow in pulldown-cmark 0.10
+                        // TableHead contains cells directly without TableRow in pulldown-cmark
                         headers = current_row.clone();
                     }
                     Event::Start(Tag::TableRow) => {
@@ -553,16 +1712,20 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
                         rows.push(current_row.clone());
                     }
                     Event::Start(Tag::TableCell) => {
-                        current_cell = String::new();
-                    }
-                    Event::End(TagEnd::TableCell) => {
-                        current_row.push(current_cell.clone());
-                    }
-                    Event::Text(text) => {
-                        current_cell.push_str(text);
-                    }
-                    Event::Code(code) => {
-                        current_cell.push_str(&format!("`{}`", code));
+                        let (cell, end_index) =
+                            parse_inline_elements(events, index + 1, Some(TagEnd::TableCell));
+                        // GitHub-style multi-line cells
+                        let cell = cell
+                            .into_iter()
+                            .map(|inline| match inline {
+                                InlineElement::InlineHtml(html) if is_line_break_tag(&html) => {
+                                    InlineElement::HardBreak
+                                }
+                                inline => inline,
+                            })
+                            .collect();
+                        current_row.push(cell);
+                        index = end_index;
                     }
                     _ => {}
                 }
@@ -579,20 +1742,20 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
             )
         }
 
-        Event::Start(Tag::BlockQuote) => {
+        Event::Start(Tag::BlockQuote(_)) => {
             let mut content = Vec::new();
             let mut index = start + 1;
             let mut depth = 1;
 
             while index < events.len() {
                 match &events[index] {
-                    Event::End(TagEnd::BlockQuote) => {
+                    Event::End(TagEnd::BlockQuote(_)) => {
                         depth -= 1;
                         if depth == 0 {
                             break;
                         }
                     }
-                    Event::Start(Tag::BlockQuote) => {
+                    Event::Start(Tag::BlockQuote(_)) => {
                         depth += 1;
                     }
                     _ => {
@@ -606,7 +1769,7 @@ fn parse_element(events: &[Event], start: usize) -> (Option<Element>, usize) {
                 index += 1;
             }
 
-            (Some(Element::BlockQuote { content }), index + 1)
+            (Some(alert_or_quote(content)), index + 1)
         }
 
         Event::Rule => (Some(Element::HorizontalRule), start + 1),
@@ -705,6 +1868,46 @@ mod tests {
         assert!(has_footnote_def, "Should have footnote definition");
     }
 
+    #[test]
+    fn test_footnote_undefined_reference() {
+        let doc =
+            parse_markdown("Text[^x] and[^1].\n\n[^1]: Defined.\n\n[^unused]: Never cited.\n");
+        assert_eq!(
+            doc.warnings,
+            [
+                "undefined footnote reference [^x]",
+                "unused footnote definition [^unused]"
+            ]
+        );
+
+        let doc = parse_markdown("Text[^1].\n\n[^1]: Defined.\n");
+        assert!(doc.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_footnote_duplicate_definition() {
+        let doc = parse_markdown("Text[^a].\n\n[^a]: First.\n\n[^a]: Second.\n");
+        assert_eq!(
+            doc.warnings,
+            ["duplicate footnote definition [^a]; keeping the first"]
+        );
+
+        let definitions: Vec<_> = doc
+            .elements
+            .iter()
+            .filter_map(|el| match el {
+                Element::FootnoteDefinition { label, content } => Some((label, content)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(definitions.len(), 1);
+        assert!(matches!(
+            &definitions[0].1[0],
+            Element::Paragraph { content }
+                if matches!(&content[0], InlineElement::Text(t) if t == "First.")
+        ));
+    }
+
     #[test]
     fn test_footnote_definition_content() {
         let input = "[^note]: This is the **footnote** content.";
@@ -744,6 +1947,120 @@ mod tests {
         assert_eq!(anchor_gen.generate("Hello"), "hello-3");
     }
 
+    #[test]
+    fn test_smart_punctuation_skips_code() {
+        let options = ParseOptions {
+            smart_punctuation: true,
+        };
+        let doc = parse_markdown_with_options("\"quote\" -- dash... `\"code\" --`", options);
+        let Element::Paragraph { content } = &doc.elements[0] else {
+            panic!("Expected paragraph");
+        };
+        let text: String = content
+            .iter()
+            .filter_map(|inline| match inline {
+                InlineElement::Text(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "\u{201c}quote\u{201d} \u{2013} dash\u{2026} ");
+        assert!(
+            content
+                .iter()
+                .any(|inline| matches!(inline, InlineElement::Code(c) if c == "\"code\" --"))
+        );
+    }
+
+    fn paragraph_links(input: &str) -> Vec<(String, String)> {
+        let doc = parse_markdown(input);
+        let Element::Paragraph { content } = &doc.elements[0] else {
+            panic!("Expected paragraph");
+        };
+        content
+            .iter()
+            .filter_map(|inline| match inline {
+                InlineElement::Link { url, content, .. } => match content.as_slice() {
+                    [InlineElement::Text(text)] => Some((url.clone(), text.clone())),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bare_urls_become_links() {
+        let links = paragraph_links(
+            "See https://example.com/a_b?x=1&y=2. Or www.example.org, or mail me@example.com!",
+        );
+        assert_eq!(
+            links,
+            vec![
+                (
+                    "https://example.com/a_b?x=1&y=2".to_string(),
+                    "https://example.com/a_b?x=1&y=2".to_string()
+                ),
+                (
+                    "http://www.example.org".to_string(),
+                    "www.example.org".to_string()
+                ),
+                (
+                    "mailto:me@example.com".to_string(),
+                    "me@example.com".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_autolink_trims_unbalanced_paren() {
+        let links = paragraph_links("(see https://en.wikipedia.org/wiki/Rust_(language))");
+        assert_eq!(links[0].0, "https://en.wikipedia.org/wiki/Rust_(language)");
+    }
+
+    #[test]
+    fn test_autolink_skips_links_and_code() {
+        let links = paragraph_links("[site](https://a.example) and `https://b.example`");
+        assert_eq!(
+            links,
+            vec![("https://a.example".to_string(), "site".to_string())]
+        );
+
+        let doc = parse_markdown("```\nhttps://example.com\n```");
+        assert!(matches!(
+            &doc.elements[0],
+            Element::CodeBlock { content, .. } if content == "https://example.com\n"
+        ));
+    }
+
+    #[test]
+    fn test_document_title() {
+        assert_eq!(
+            parse_markdown("Intro\n\n## Sub\n\n# Main Title\n\n# Other").title(),
+            Some("Main Title")
+        );
+        assert_eq!(parse_markdown("## Only sub").title(), None);
+    }
+
+    #[test]
+    fn test_element_spans() {
+        let input = "---\ntitle: T\n---\n# Heading\n\nText [^1].\n\n[^1]: A\n\n[^1]: B\n";
+        let doc = parse_markdown(input);
+        let spans: Vec<&str> = (0..doc.elements.len())
+            .map(|i| &input[doc.span(i).unwrap()])
+            .collect();
+        assert_eq!(spans, ["# Heading\n", "Text [^1].\n", "[^1]: A\n\n"]);
+        assert_eq!(doc.elements.len(), doc.spans.len());
+        assert_eq!(Document::default().span(0), None);
+    }
+
+    #[test]
+    fn test_toc_duplicate_heading_anchors() {
+        let doc = parse_markdown("# Usage\n\n## Install\n\n# Usage");
+        let anchors: Vec<String> = generate_toc(&doc).into_iter().map(|e| e.anchor).collect();
+        assert_eq!(anchors, vec!["usage", "install", "usage-1"]);
+    }
+
     #[test]
     fn test_nested_strong_emphasis() {
         let input = "This is **bold with _italic_ inside** text.";
@@ -883,6 +2200,158 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_frontmatter_tags() {
+        let doc = parse_markdown("---\ntitle: Post\ntags: [rust, cli]\n---\n# Hello\n");
+        assert_eq!(doc.tags, ["rust", "cli"]);
+        assert_eq!(doc.frontmatter.as_ref().unwrap().fields["title"], "Post");
+        // The block is stripped from the body
+        assert_eq!(doc.elements.len(), 1);
+        assert_eq!(doc.title(), Some("Hello"));
+        assert_eq!(doc.display_title(), Some("Post"));
+        assert_eq!(parse_markdown("# Hello\n").display_title(), Some("Hello"));
+
+        let doc = parse_markdown("---\nauthor: [Ann, Bo]\ndate: 2024-01-02\n---\n");
+        assert_eq!(
+            doc.frontmatter.unwrap().byline().as_deref(),
+            Some("Ann, Bo · 2024-01-02")
+        );
+
+        let doc = parse_markdown("---\ntags: docs, guide\n---\ntext\n");
+        assert_eq!(doc.tags, ["docs", "guide"]);
+
+        let doc = parse_markdown("---\ndraft: false\nauthors: [a, b]\nnote:\n---\n");
+        let frontmatter = doc.frontmatter.as_ref().unwrap();
+        assert_eq!(frontmatter.format, FrontMatterFormat::Yaml);
+        assert_eq!(
+            frontmatter.display_fields(),
+            [
+                ("draft".to_string(), "false".to_string()),
+                ("authors".to_string(), "a, b".to_string()),
+                ("note".to_string(), String::new()),
+            ]
+        );
+
+        // A leading horizontal rule is not front matter
+        let doc = parse_markdown("---\nJust text\n---\n");
+        assert!(doc.frontmatter.is_none());
+        assert!(doc.tags.is_empty());
+        assert!(matches!(doc.elements[0], Element::HorizontalRule));
+    }
+
+    #[test]
+    fn test_toml_frontmatter() {
+        let doc = parse_markdown(
+            "+++\ntitle = \"x\"\ntags = [\"a\", \"b\"]\ndate = 2024-01-02\n+++\nBody\n",
+        );
+        let frontmatter = doc.frontmatter.as_ref().unwrap();
+        assert_eq!(frontmatter.title(), Some("x"));
+        assert_eq!(frontmatter.fields["date"], "2024-01-02");
+        assert_eq!(frontmatter.format, FrontMatterFormat::Toml);
+        assert_eq!(doc.tags, ["a", "b"]);
+        assert_eq!(doc.elements.len(), 1);
+        assert!(matches!(
+            &doc.elements[0],
+            Element::Paragraph { content } if matches!(&content[..], [InlineElement::Text(t)] if t == "Body")
+        ));
+
+        // Delimiters do not mix, and a block that is not TOML stays in the body
+        assert!(
+            parse_markdown("+++\ntitle = \"x\"\n---\n")
+                .frontmatter
+                .is_none()
+        );
+        assert!(parse_markdown("+++\nnot toml\n+++\n").frontmatter.is_none());
+    }
+
+    #[test]
+    fn test_crlf_and_bom_input() {
+        let doc = parse_markdown(
+            "\u{feff}# Title\r\n\r\nline one\r\nline two\r\n\r\n```\r\ncode\r\n```\r\n",
+        );
+        assert_eq!(doc.title(), Some("Title"));
+        assert!(matches!(
+            &doc.elements[1],
+            Element::Paragraph { content } if matches!(
+                content.as_slice(),
+                [InlineElement::Text(a), InlineElement::SoftBreak, InlineElement::Text(b)]
+                    if a == "line one" && b == "line two"
+            )
+        ));
+        assert!(matches!(
+            &doc.elements[2],
+            Element::CodeBlock { content, .. } if content == "code\n"
+        ));
+
+        let doc = parse_markdown("\u{feff}---\r\ntags: [a]\r\n---\r\nbody\r\n");
+        assert_eq!(doc.tags, ["a"]);
+    }
+
+    #[test]
+    fn test_incremental_parse_matches_full_parse() {
+        let base =
+            "# Title\n\nFirst paragraph.\n\n- one\n- two\n\nSecond paragraph.\n\n## End\n\nLast.\n";
+        let mut parser = IncrementalParser::new(base, ParseOptions::default());
+
+        let edits = [
+            // Local edits re-parse only nearby blocks
+            (base.replace("First", "1st"), true),
+            (base.replace("- two\n", "- two\n- three\n"), true),
+            (base.replace("Second paragraph.\n\n", ""), true),
+            (base.replace("Last.", "Last.\n\nMore"), true),
+            (base.replace("\n\n- one", "\n- one"), true),
+            (base.replace("# Title", "Title\n==="), true),
+            // Open-ended blocks and references force a full parse
+            (base.replace("Second paragraph.", "```\ncode"), false),
+            (base.replace("Last.", "Last[^1].\n\n[^1]: Note"), false),
+            (format!("---\ntags: [a]\n---\n{}", base), false),
+        ];
+        for (text, incremental) in edits {
+            parser.update(base);
+            assert_eq!(parser.update(&text), incremental, "{:?}", text);
+            assert_eq!(
+                format!("{:?}", parser.document()),
+                format!("{:?}", parse_markdown(&text)),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_incremental_parse_updates_warnings() {
+        let base = "# Title\n\n![](a.png)\n\nText.\n\n![](b.png)\n";
+        let mut parser = IncrementalParser::new(base, ParseOptions::default());
+        assert_eq!(parser.document().warnings.len(), 2);
+
+        let fixed = base.replace("![](a.png)", "![alt](a.png)");
+        assert!(parser.update(&fixed));
+        assert_eq!(
+            parser.document().warnings,
+            ["image without alt text: b.png"]
+        );
+
+        let broken = fixed.replace("Text.", "Text ![](c.png).");
+        assert!(parser.update(&broken));
+        assert_eq!(parser.document().warnings, parse_markdown(&broken).warnings);
+        assert_eq!(parser.document().warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_toggle_task() {
+        let input = "Not a task: [ ]\n\n- [ ] first\n- [X] second\n\n```\n- [ ] in code\n```\n\n1. [ ] third\n";
+        assert_eq!(
+            toggle_task(input, 0).unwrap(),
+            input.replacen("- [ ] first", "- [x] first", 1)
+        );
+        assert_eq!(toggle_task(input, 1).unwrap(), input.replace("[X]", "[ ]"));
+        assert_eq!(
+            toggle_task(input, 2).unwrap(),
+            input.replace("1. [ ]", "1. [x]")
+        );
+        assert!(toggle_task(input, 3).is_none());
+    }
+
     #[test]
     fn test_inline_image() {
         let input = "Here is ![alt text](https://example.com/img.png \"title\") inline.";
@@ -920,6 +2389,238 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_image_without_alt_warns() {
+        let doc = parse_markdown("![](a.png) ![ ](b.png) ![logo](c.png) ![`x`](d.png)");
+        assert_eq!(
+            doc.warnings,
+            [
+                "image without alt text: a.png",
+                "image without alt text: b.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table_cell_formatting() {
+        let doc = parse_markdown(
+            "| **Name** | Notes |\n|---|---|\n| `cfg` | see [docs](d.md), *soon* |\n",
+        );
+        let Element::Table { headers, rows, .. } = &doc.elements[0] else {
+            panic!("Expected a table");
+        };
+        assert!(matches!(&headers[0][..], [InlineElement::Strong(_)]));
+        assert!(matches!(&rows[0][0][..], [InlineElement::Code(code)] if code == "cfg"));
+        assert!(matches!(&rows[0][1][1], InlineElement::Link { url, .. } if url == "d.md"));
+        assert!(matches!(&rows[0][1][3], InlineElement::Emphasis(_)));
+        assert_eq!(inline_text(&rows[0][1]), "see docs, soon");
+    }
+
+    #[test]
+    fn test_table_cell_line_breaks() {
+        let doc = parse_markdown("| A |\n|---|\n| one<br>two<BR />three<b>x</b> |\n");
+        let Element::Table { rows, .. } = &doc.elements[0] else {
+            panic!("Expected a table");
+        };
+        let cell = &rows[0][0];
+        assert_eq!(
+            cell.iter()
+                .filter(|inline| matches!(inline, InlineElement::HardBreak))
+                .count(),
+            2
+        );
+        assert_eq!(inline_text(cell), "one two threex");
+        assert!(!is_line_break_tag("<b>"));
+        assert!(!is_line_break_tag("<bra>"));
+    }
+
+    #[test]
+    fn test_kbd() {
+        let doc = parse_markdown("Press <kbd>Ctrl</kbd>+<KBD>C</KBD>, not <kbd>**x**</kbd>");
+
+        let Element::Paragraph { content } = &doc.elements[0] else {
+            panic!("First element should be a paragraph");
+        };
+        assert!(matches!(&content[1], InlineElement::Kbd(key) if key == "Ctrl"));
+        assert!(matches!(&content[3], InlineElement::Kbd(key) if key == "C"));
+        // Markup inside is left as inline HTML
+        assert!(matches!(&content[5], InlineElement::InlineHtml(html) if html == "<kbd>"));
+    }
+
+    #[test]
+    fn test_code_info_title() {
+        let info = |s| parse_code_info(s);
+        assert_eq!(
+            info(r#"rust title="main.rs""#),
+            (Some("rust".to_string()), Some("main.rs".to_string()))
+        );
+        assert_eq!(
+            info("sh title='my script.sh'"),
+            (Some("sh".to_string()), Some("my script.sh".to_string()))
+        );
+        assert_eq!(
+            info("toml  title=Cargo.toml"),
+            (Some("toml".to_string()), Some("Cargo.toml".to_string()))
+        );
+        assert_eq!(
+            info(r#"title="notes.txt""#),
+            (None, Some("notes.txt".to_string()))
+        );
+        assert_eq!(info("rust,ignore"), (Some("rust,ignore".to_string()), None));
+        assert_eq!(
+            info("rust subtitle=x title=\"\""),
+            (Some("rust".to_string()), None)
+        );
+
+        let doc = parse_markdown("```rust title=\"main.rs\"\nfn main() {}\n```\n");
+        assert!(matches!(
+            &doc.elements[0],
+            Element::CodeBlock { language: Some(lang), title: Some(title), .. }
+                if lang == "rust" && title == "main.rs"
+        ));
+    }
+
+    #[test]
+    fn test_math() {
+        let doc = parse_markdown("Area $\\pi r^2$ for $5 and $6, *$x$*\n\n$$\na^2 +\nb^2\n$$\n");
+
+        let Element::Paragraph { content } = &doc.elements[0] else {
+            panic!("First element should be a paragraph");
+        };
+        assert!(matches!(&content[0], InlineElement::Text(text) if text == "Area "));
+        assert!(matches!(
+            &content[1],
+            InlineElement::Math { tex, display: false } if tex == "\\pi r^2"
+        ));
+        assert!(matches!(&content[2], InlineElement::Text(text) if text == " for $5 and $6, "));
+        assert!(matches!(
+            &content[3],
+            InlineElement::Emphasis(inner) if matches!(&inner[..], [InlineElement::Math { tex, .. }] if tex == "x")
+        ));
+
+        // Display math on its own is a block, keeping its lines
+        assert!(matches!(
+            &doc.elements[1],
+            Element::MathBlock { content } if content == "a^2 +\nb^2"
+        ));
+    }
+
+    #[test]
+    fn test_math_is_not_html() {
+        // Markup that looks like rendered math stays HTML, and TeX is never
+        // autolinked or read as a footnote reference
+        let doc = parse_markdown(
+            "<span class=\"math-inline\">x</span> and $a[^1] www.example.com$ in a list:\n\n- $$y$$\n",
+        );
+        assert!(doc.warnings.is_empty());
+
+        let Element::Paragraph { content } = &doc.elements[0] else {
+            panic!("First element should be a paragraph");
+        };
+        assert!(
+            matches!(&content[0], InlineElement::InlineHtml(html) if html == "<span class=\"math-inline\">")
+        );
+        assert!(
+            !content[..2]
+                .iter()
+                .any(|el| matches!(el, InlineElement::Math { .. }))
+        );
+        assert!(content.iter().any(|el| matches!(
+            el,
+            InlineElement::Math { tex, display: false } if tex == "a[^1] www.example.com"
+        )));
+
+        let Element::List { items, .. } = &doc.elements[1] else {
+            panic!("Second element should be a list");
+        };
+        assert!(matches!(
+            &items[0].content[..],
+            [Element::Paragraph { content }]
+                if matches!(&content[..], [InlineElement::Math { tex, display: true }] if tex == "y")
+        ));
+    }
+
+    #[test]
+    fn test_unclosed_math_block() {
+        let doc = parse_markdown("$$\nx^2\n\nText $$y$$ here\n");
+        assert_eq!(doc.elements.len(), 2);
+        assert!(matches!(
+            &doc.elements[0],
+            Element::Paragraph { content } if inline_text(content) == "$$ x^2"
+        ));
+        let Element::Paragraph { content } = &doc.elements[1] else {
+            panic!("Second element should be a paragraph");
+        };
+        assert!(matches!(&content[1], InlineElement::Math { tex, display: true } if tex == "y"));
+    }
+
+    #[test]
+    fn test_math_escaped_dollar() {
+        let doc = parse_markdown("Costs \\$x$ and &#36;y$ only\n\nPay \\$5 for $z$\n");
+
+        let Element::Paragraph { content } = &doc.elements[0] else {
+            panic!("First element should be a paragraph");
+        };
+        assert!(
+            !content
+                .iter()
+                .any(|e| matches!(e, InlineElement::Math { .. }))
+        );
+        assert_eq!(inline_text(content), "Costs $x$ and $y$ only");
+
+        let Element::Paragraph { content } = &doc.elements[1] else {
+            panic!("Second element should be a paragraph");
+        };
+        assert_eq!(inline_text(content), "Pay $5 for z");
+        assert!(matches!(
+            content.last(),
+            Some(InlineElement::Math { tex, display: false }) if tex == "z"
+        ));
+
+        // Escapes inside math keep their backslash
+        let doc = parse_markdown("Set $\\{x \\$ y\\}$\n");
+        let Element::Paragraph { content } = &doc.elements[0] else {
+            panic!("First element should be a paragraph");
+        };
+        assert!(matches!(&content[1], InlineElement::Math { tex, .. } if tex == "\\{x \\$ y\\}"));
+    }
+
+    #[test]
+    fn test_list_item_starting_with_image() {
+        let doc = parse_markdown("- ![Chart](chart.png) caption\n- <b>bold</b>\n");
+
+        let Element::List { items, .. } = &doc.elements[0] else {
+            panic!("First element should be a list");
+        };
+        assert!(matches!(
+            &items[0].content[..],
+            [Element::Paragraph { content }] if matches!(&content[0], InlineElement::Image { url, .. } if url == "chart.png")
+        ));
+        assert!(matches!(
+            &items[1].content[..],
+            [Element::Paragraph { content }] if matches!(&content[0], InlineElement::InlineHtml(html) if html == "<b>")
+        ));
+    }
+
+    #[test]
+    fn test_alerts() {
+        let doc = parse_markdown(
+            "> [!NOTE]\n> Read *this*.\n\n> [!warning]\n\n> [!TIP] not alone\n\n> [!OTHER]\n> x\n",
+        );
+        assert!(matches!(
+            &doc.elements[0],
+            Element::Alert { kind: AlertKind::Note, content }
+                if matches!(&content[..], [Element::Paragraph { content }] if inline_text(content) == "Read this.")
+        ));
+        assert!(matches!(
+            &doc.elements[1],
+            Element::Alert { kind: AlertKind::Warning, content } if content.is_empty()
+        ));
+        // The marker must be alone on the first line, and a known kind
+        assert!(matches!(&doc.elements[2], Element::BlockQuote { .. }));
+        assert!(matches!(&doc.elements[3], Element::BlockQuote { .. }));
+    }
+
     #[test]
     fn test_html_block() {
         let input = "<div>\n  <p>HTML block</p>\n</div>";
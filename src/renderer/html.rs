@@ -1,18 +1,159 @@
-use crate::files::FileTree;
-use crate::parser::AnchorGenerator;
-use pulldown_cmark::{CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd, html};
+use crate::files::{FileTree, format_date};
+use crate::parser::{
+    AlertKind, AnchorGenerator, Document, Element, FootnoteMode, FrontMatter, FrontMatterMode,
+    InlineElement, ParseOptions, TocEntry, autolink_events, check_footnotes, inline_text,
+    math_events, normalize_input, parse_code_info, parse_markdown_with_options, push_heading_text,
+    split_frontmatter, top_level_blocks,
+};
+use crate::renderer::DiagramKind;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, HeadingLevel, Parser, Tag, TagEnd, html};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
 
 const TEMPLATE: &str = include_str!("../../assets/template.html");
 const TEMPLATE_SIDEBAR: &str = include_str!("../../assets/template_sidebar.html");
 const CSS: &str = include_str!("../../assets/github.css");
 
+/// Code block languages that get a "Copy command" button
+const SHELL_LANGUAGES: &[&str] = &[
+    "sh",
+    "bash",
+    "zsh",
+    "shell",
+    "console",
+    "shell-session",
+    "shellsession",
+];
+
+/// Start of a rendered task list checkbox
+const TASK_CHECKBOX_PREFIX: &str = r#"<input type="checkbox" class="task-list-item-checkbox""#;
+
+/// Stylesheet link for `--css`, served by the server at `/assets/custom.css`
+const CUSTOM_CSS_LINK: &str = "\n    <link rel=\"stylesheet\" href=\"/assets/custom.css\">";
+
+/// Longest `og:description` taken from the first paragraph, in characters
+const DESCRIPTION_MAX_CHARS: usize = 200;
+
+/// Browser renderer for Graphviz diagrams, loaded only with `--diagrams`
+const GRAPHVIZ_SCRIPT: &str = r#"
+    <script src="https://cdn.jsdelivr.net/npm/@viz-js/viz@3/lib/viz-standalone.js"></script>"#;
+
+/// Language attribute right after an inline code span, as in `` `let x`{.rust} ``
+static INLINE_CODE_LANG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\{\.([A-Za-z0-9_+#-]+)\}").expect("valid inline code language regex")
+});
+
+/// Badge after the name of a sidebar file changed within `--since`
+const RECENT_BADGE: &str = r#"<span class="sidebar-item-badge">updated</span>"#;
+
 // SVG icons for the sidebar
 const ICON_FILE: &str = r#"<svg class="sidebar-item-icon" viewBox="0 0 16 16"><path d="M2 1.75C2 .784 2.784 0 3.75 0h6.586c.464 0 .909.184 1.237.513l2.914 2.914c.329.328.513.773.513 1.237v9.586A1.75 1.75 0 0 1 13.25 16h-9.5A1.75 1.75 0 0 1 2 14.25Zm1.75-.25a.25.25 0 0 0-.25.25v12.5c0 .138.112.25.25.25h9.5a.25.25 0 0 0 .25-.25V6h-2.75A1.75 1.75 0 0 1 9 4.25V1.5Zm6.75.062V4.25c0 .138.112.25.25.25h2.688l-.011-.013-2.914-2.914-.013-.011Z"/></svg>"#;
 const ICON_CHEVRON: &str = r#"<svg class="sidebar-folder-icon" viewBox="0 0 16 16"><path d="M12.78 5.22a.749.749 0 0 1 0 1.06l-4.25 4.25a.749.749 0 0 1-1.06 0L3.22 6.28a.749.749 0 1 1 1.06-1.06L8 8.939l3.72-3.719a.749.749 0 0 1 1.06 0Z"/></svg>"#;
 
+/// Anchor and link rewriting for one file of a combined document
+struct CombineContext<'a> {
+    /// Section id of this file, used to prefix its heading ids
+    section: &'a str,
+    /// Directory of this file, relative to the combined root
+    dir: &'a Path,
+    /// Section ids of all combined files, keyed by relative path (`/` separated)
+    sections: &'a HashMap<String, String>,
+}
+
+impl CombineContext<'_> {
+    /// Point in-page anchors at this file's prefixed ids and `.md` links at their sections
+    fn rewrite_link<'u>(&self, url: &'u str) -> Cow<'u, str> {
+        if let Some(fragment) = url.strip_prefix('#') {
+            return Cow::Owned(format!("#{}-{}", self.section, fragment));
+        }
+        if url.contains("://") || url.starts_with("mailto:") {
+            return Cow::Borrowed(url);
+        }
+
+        let (path, fragment) = match url.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (url, None),
+        };
+        if !(path.ends_with(".md") || path.ends_with(".markdown")) {
+            return Cow::Borrowed(url);
+        }
+
+        let target = normalize_relative_path(&self.dir.join(path));
+        match (self.sections.get(&target), fragment) {
+            (Some(section), Some(fragment)) => Cow::Owned(format!("#{}-{}", section, fragment)),
+            (Some(section), None) => Cow::Owned(format!("#{}", section)),
+            (None, _) => Cow::Borrowed(url),
+        }
+    }
+}
+
+/// Resolve `.` and `..` lexically and join with `/`
+fn normalize_relative_path(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// Percent-encode a query parameter value, keeping `/` readable
+pub(crate) fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[derive(Clone)]
 pub struct HtmlRenderer {
     title: String,
     show_toc: bool,
+    parse_options: ParseOptions,
+    /// Add `data-source-line` to top-level blocks for scroll sync
+    source_lines: bool,
+    /// Prefix of "Edit this page" links, joined with the file's relative path
+    edit_url_base: Option<String>,
+    /// Show a "Last updated" date above the content
+    show_modified: bool,
+    /// Highlight authoring problems, such as images without alt text
+    authoring: bool,
+    /// Browser theme from `--theme`: `dark`, `light`, or `auto` to follow the OS.
+    /// `None` keeps the reader's last toggled theme.
+    theme: Option<String>,
+    /// How the document's front matter is shown
+    frontmatter: FrontMatterMode,
+    /// Mark sidebar files modified within this window as updated
+    since: Option<Duration>,
+    /// Load external renderers for diagram kinds beyond mermaid
+    diagrams: bool,
+    /// Where footnote text goes
+    footnotes: FootnoteMode,
+    /// Language for highlighting inline code spans without a `{.lang}` attribute
+    inline_code_lang: Option<String>,
+    /// Prefix for root-relative link and image URLs, for sites served below the root
+    base_url: Option<String>,
+    /// Markup inserted verbatim at the end of the page's `<head>`
+    head_extra: String,
+    /// Stylesheet from `--css`, loaded after the built-in styles so it overrides them
+    custom_css: String,
+    /// Two content panes side by side on pages with the sidebar
+    split: bool,
 }
 
 impl HtmlRenderer {
@@ -20,6 +161,21 @@ impl HtmlRenderer {
         Self {
             title: title.to_string(),
             show_toc: false,
+            parse_options: ParseOptions::default(),
+            source_lines: false,
+            edit_url_base: None,
+            show_modified: false,
+            authoring: false,
+            theme: None,
+            frontmatter: FrontMatterMode::default(),
+            since: None,
+            diagrams: false,
+            footnotes: FootnoteMode::default(),
+            inline_code_lang: None,
+            base_url: None,
+            head_extra: String::new(),
+            custom_css: String::new(),
+            split: false,
         }
     }
 
@@ -28,13 +184,225 @@ impl HtmlRenderer {
         self
     }
 
-    /// Render markdown content to full HTML page (single file mode)
+    pub fn with_parse_options(mut self, parse_options: ParseOptions) -> Self {
+        self.parse_options = parse_options;
+        self
+    }
+
+    pub fn with_source_lines(mut self, source_lines: bool) -> Self {
+        self.source_lines = source_lines;
+        self
+    }
+
+    pub fn with_edit_url_base(mut self, edit_url_base: Option<String>) -> Self {
+        self.edit_url_base = edit_url_base;
+        self
+    }
+
+    pub fn with_modified(mut self, show_modified: bool) -> Self {
+        self.show_modified = show_modified;
+        self
+    }
+
+    pub fn with_authoring(mut self, authoring: bool) -> Self {
+        self.authoring = authoring;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Option<String>) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_frontmatter(mut self, frontmatter: FrontMatterMode) -> Self {
+        self.frontmatter = frontmatter;
+        self
+    }
+
+    pub fn with_since(mut self, since: Option<Duration>) -> Self {
+        self.since = since;
+        self
+    }
+
+    pub fn with_diagrams(mut self, diagrams: bool) -> Self {
+        self.diagrams = diagrams;
+        self
+    }
+
+    pub fn with_footnotes(mut self, footnotes: FootnoteMode) -> Self {
+        self.footnotes = footnotes;
+        self
+    }
+
+    pub fn with_inline_code_lang(mut self, inline_code_lang: Option<String>) -> Self {
+        self.inline_code_lang = inline_code_lang;
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: Option<String>) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    pub fn with_head_extra(mut self, head_extra: String) -> Self {
+        self.head_extra = head_extra;
+        self
+    }
+
+    pub fn with_custom_css(mut self, custom_css: String) -> Self {
+        self.custom_css = custom_css;
+        self
+    }
+
+    /// Page title used when a document has none of its own
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Markdown extensions documents are parsed with
+    pub fn parse_options(&self) -> ParseOptions {
+        self.parse_options
+    }
+
+    /// Window for marking recently modified files, if set
+    pub fn since(&self) -> Option<Duration> {
+        self.since
+    }
+
+    /// Stylesheet from `--css`, empty without one
+    pub fn custom_css(&self) -> &str {
+        &self.custom_css
+    }
+
+    /// Link to the `--css` stylesheet, if there is one
+    fn custom_css_link(&self) -> &'static str {
+        if self.custom_css.is_empty() {
+            ""
+        } else {
+            CUSTOM_CSS_LINK
+        }
+    }
+
+    /// Prefix a root-relative URL (`/foo`, not `//host`) with the base URL
+    fn rebase_url<'u>(&self, url: Cow<'u, str>) -> Cow<'u, str> {
+        match &self.base_url {
+            Some(base) if url.starts_with('/') && !url.starts_with("//") => {
+                Cow::Owned(format!("{}{}", base.trim_end_matches('/'), url))
+            }
+            _ => url,
+        }
+    }
+
+    pub fn with_split(mut self, split: bool) -> Self {
+        self.split = split;
+        self
+    }
+
+    /// Script tags for the diagram renderers enabled with `--diagrams`
+    fn diagram_scripts(&self) -> &'static str {
+        if self.diagrams { GRAPHVIZ_SCRIPT } else { "" }
+    }
+
+    /// Theme option read by the page script
+    fn theme_option(&self) -> &str {
+        match self.theme.as_deref() {
+            Some(theme @ ("dark" | "light" | "auto")) => theme,
+            _ => "default",
+        }
+    }
+
+    /// Attributes of the page's `<body>` tag
+    fn body_attributes(&self) -> String {
+        let classes: Vec<&str> = [(self.authoring, "authoring"), (self.split, "split")]
+            .into_iter()
+            .filter_map(|(enabled, class)| enabled.then_some(class))
+            .collect();
+        if classes.is_empty() {
+            String::new()
+        } else {
+            format!(r#" class="{}""#, classes.join(" "))
+        }
+    }
+
+    /// "Last updated" line for a file modified at `modified`, or an empty string
+    /// when disabled or the time is unknown
+    pub fn modified_line(&self, modified: Option<SystemTime>) -> String {
+        match modified {
+            Some(modified) if self.show_modified => format!(
+                "<div class=\"last-updated\">Last updated: {}</div>\n",
+                format_date(modified)
+            ),
+            _ => String::new(),
+        }
+    }
+
+    /// "Edit this page" link for a file, or an empty string without an edit URL base
+    pub fn edit_link(&self, relative_path: &str) -> String {
+        let Some(base) = &self.edit_url_base else {
+            return String::new();
+        };
+        let url = format!(
+            "{}{}",
+            base,
+            normalize_relative_path(Path::new(relative_path))
+        );
+        format!(
+            "<div class=\"edit-link\">{}Edit this page</a></div>\n",
+            Self::generate_link_open_tag(&url, None)
+        )
+    }
+
+    /// Render markdown content to full HTML page (single file mode), titled after the
+    /// front matter `title` when there is one
     pub fn render(&self, markdown: &str) -> String {
-        let html_content = self.markdown_to_html(markdown);
+        let document = parse_markdown_with_options(markdown, self.parse_options);
+        let title = document.frontmatter.as_ref().and_then(FrontMatter::title);
+        self.page_with_meta(
+            title.unwrap_or(&self.title),
+            &self.markdown_to_html(markdown),
+            &self.meta_tags(&document),
+            false,
+        )
+    }
+
+    /// Render markdown content to a full page with the stylesheet embedded, for use
+    /// without the server
+    pub fn render_standalone(&self, markdown: &str) -> String {
+        self.inline_css(&self.render(markdown))
+    }
+
+    /// Wrap already rendered content HTML in the single file page served by mdp,
+    /// which reloads live and toggles tasks
+    pub fn page(&self, html_content: &str) -> String {
+        self.page_with_meta(&self.title, html_content, "", true)
+    }
 
+    /// Wrap already rendered content HTML in the served single file page titled `title`
+    pub fn page_titled(&self, title: &str, html_content: &str) -> String {
+        self.page_with_meta(title, html_content, "", true)
+    }
+
+    /// The single file page; `live_reload` for pages served by mdp, not exports
+    fn page_with_meta(
+        &self,
+        title: &str,
+        html_content: &str,
+        meta: &str,
+        live_reload: bool,
+    ) -> String {
         TEMPLATE
-            .replace("{{TITLE}}", &self.title)
-            .replace("{{CONTENT}}", &html_content)
+            .replace("{{TITLE}}", &html_escape::encode_text(title))
+            .replace("{{META}}", meta)
+            .replace(
+                "{{LIVE_RELOAD}}",
+                if live_reload { "true" } else { "false" },
+            )
+            .replace("{{DIAGRAM_SCRIPTS}}", self.diagram_scripts())
+            .replace("{{BODY_ATTRIBUTES}}", &self.body_attributes())
+            .replace("{{THEME}}", self.theme_option())
+            .replace("{{CUSTOM_CSS}}", self.custom_css_link())
+            .replace("{{HEAD_EXTRA}}", &self.head_extra)
+            .replace("{{CONTENT}}", html_content)
     }
 
     /// Render markdown content with sidebar (directory mode)
@@ -44,13 +412,152 @@ impl HtmlRenderer {
         file_tree: &FileTree,
         current_file: Option<&str>,
     ) -> String {
-        let html_content = self.markdown_to_html(markdown);
+        let mut html = String::new();
+        if let Some(file) = current_file {
+            html.push_str(&self.edit_link(file));
+            let modified = file_tree.find_file(file).and_then(|f| f.modified);
+            html.push_str(&self.modified_line(modified));
+        }
+        html.push_str(&self.markdown_to_html(markdown));
+        self.page_with_sidebar(&html, file_tree, current_file)
+    }
+
+    /// Wrap already rendered content HTML in the page with sidebar
+    pub fn page_with_sidebar(
+        &self,
+        html_content: &str,
+        file_tree: &FileTree,
+        current_file: Option<&str>,
+    ) -> String {
         let sidebar_html = self.build_sidebar(file_tree, current_file);
 
         TEMPLATE_SIDEBAR
-            .replace("{{TITLE}}", &self.title)
+            .replace("{{TITLE}}", &html_escape::encode_text(&self.title))
+            .replace("{{DIAGRAM_SCRIPTS}}", self.diagram_scripts())
+            .replace("{{BODY_ATTRIBUTES}}", &self.body_attributes())
+            .replace("{{THEME}}", self.theme_option())
+            .replace("{{CUSTOM_CSS}}", self.custom_css_link())
+            .replace("{{HEAD_EXTRA}}", &self.head_extra)
             .replace("{{SIDEBAR}}", &sidebar_html)
-            .replace("{{CONTENT}}", &html_content)
+            .replace("{{CONTENT}}", html_content)
+    }
+
+    /// Render several files as one self-contained page, in the given order.
+    /// Each file becomes a section with unique heading ids, and links between
+    /// the files point to the sections instead of the `.md` files.
+    pub fn render_combined(&self, files: &[(PathBuf, String)]) -> String {
+        let mut section_gen = AnchorGenerator::new();
+        let sections: HashMap<String, String> = files
+            .iter()
+            .map(|(path, _)| {
+                let key = normalize_relative_path(path);
+                let section = section_gen.generate(&key);
+                (key, section)
+            })
+            .collect();
+
+        // Per-file TOCs would repeat; list the files instead
+        let renderer = HtmlRenderer {
+            show_toc: false,
+            ..self.clone()
+        };
+
+        let mut html_content = String::new();
+        if self.show_toc {
+            html_content.push_str("<nav class=\"toc\">\n");
+            html_content.push_str("<h2>📑 Table of Contents</h2>\n");
+            html_content.push_str("<ul>\n");
+            for (path, _) in files {
+                let key = normalize_relative_path(path);
+                html_content.push_str(&format!(
+                    "<li><a href=\"#{}\">{}</a></li>\n",
+                    html_escape::encode_text(&sections[&key]),
+                    html_escape::encode_text(&key)
+                ));
+            }
+            html_content.push_str("</ul>\n");
+            html_content.push_str("</nav>\n");
+            html_content.push_str("<hr />\n");
+        }
+
+        for (path, markdown) in files {
+            let key = normalize_relative_path(path);
+            let section = &sections[&key];
+            let context = CombineContext {
+                section,
+                dir: path.parent().unwrap_or(Path::new("")),
+                sections: &sections,
+            };
+            let (html, _) = renderer.markdown_to_html_with_context(markdown, Some(&context));
+
+            html_content.push_str(&format!(
+                "<section class=\"combined-file\" id=\"{}\">\n<div class=\"combined-file-title\">{}</div>\n",
+                html_escape::encode_double_quoted_attribute(section),
+                html_escape::encode_text(&key)
+            ));
+            html_content.push_str(&html);
+            html_content.push_str("</section>\n");
+        }
+
+        self.inline_css(&self.page_with_meta(&self.title, &html_content, "", false))
+    }
+
+    /// OpenGraph tags for the page head. Each value comes from the front matter
+    /// (`title`, `description`, `image`) or else the document: its H1, first paragraph,
+    /// and first image. Tags without a value are left out.
+    fn meta_tags(&self, document: &Document) -> String {
+        let field = |key: &str| {
+            document
+                .frontmatter
+                .as_ref()
+                .and_then(|frontmatter| frontmatter.fields.get(key))
+                .and_then(|value| value.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(String::from)
+        };
+
+        let title = field("title")
+            .or_else(|| document.title().map(String::from))
+            .unwrap_or_else(|| self.title.clone());
+        let description = field("description").or_else(|| first_paragraph_text(document));
+        let image = field("image").or_else(|| first_image(&document.elements));
+
+        let mut meta = String::new();
+        let mut push = |attribute: &str, name: &str, content: &str| {
+            meta.push_str(&format!(
+                "\n    <meta {}=\"{}\" content=\"{}\">",
+                attribute,
+                name,
+                html_escape::encode_double_quoted_attribute(content)
+            ));
+        };
+        push("property", "og:title", &title);
+        if let Some(description) = &description {
+            push("name", "description", description);
+            push("property", "og:description", description);
+        }
+        if let Some(image) = &image {
+            push("property", "og:image", image);
+        }
+        meta
+    }
+
+    /// Embed the stylesheets so the page works without the server
+    fn inline_css(&self, page: &str) -> String {
+        let page = page.replacen(
+            r#"<link rel="stylesheet" href="/assets/github.css">"#,
+            &format!("<style>\n{}\n</style>", CSS),
+            1,
+        );
+        if self.custom_css.is_empty() {
+            return page;
+        }
+        page.replacen(
+            CUSTOM_CSS_LINK,
+            &format!("\n    <style>\n{}\n</style>", self.custom_css),
+            1,
+        )
     }
 
     /// Render only the content HTML (for AJAX loading)
@@ -58,11 +565,31 @@ impl HtmlRenderer {
         self.markdown_to_html(markdown)
     }
 
-    /// Build sidebar HTML from file tree
-    fn build_sidebar(&self, file_tree: &FileTree, current_file: Option<&str>) -> String {
-        let mut html = String::new();
+    /// Render the top-level element at `index` of `document`, parsed from `markdown`,
+    /// to an HTML fragment. The element is rendered from its own source, so reference
+    /// links and footnotes defined elsewhere stay unresolved. `None` without a span.
+    pub fn render_element(
+        &self,
+        markdown: &str,
+        document: &Document,
+        index: usize,
+    ) -> Option<String> {
+        let span = document.span(index)?;
+        let source = normalize_input(markdown).get(span)?.to_string();
+        Some(self.markdown_to_html(&source))
+    }
+
+    /// Render the content HTML fragment together with the table of contents entries
+    /// generated with it. The entries' anchors are the heading ids in the fragment,
+    /// so callers don't need to derive them again from the markdown.
+    pub fn render_to_html_string(&self, markdown: &str) -> (String, Vec<TocEntry>) {
+        self.markdown_to_html_with_context(markdown, None)
+    }
 
-        // Group files by directory
+    /// Files grouped by their directory, root level files under ""
+    fn group_by_directory(
+        file_tree: &FileTree,
+    ) -> std::collections::BTreeMap<String, Vec<&crate::files::MarkdownFile>> {
         let mut dirs: std::collections::BTreeMap<String, Vec<&crate::files::MarkdownFile>> =
             std::collections::BTreeMap::new();
 
@@ -74,9 +601,48 @@ impl HtmlRenderer {
                 .unwrap_or_default();
             dirs.entry(parent).or_default().push(file);
         }
+        dirs
+    }
+
+    /// Generated landing page content listing every file of the tree, grouped by folder
+    pub fn landing_page(&self, file_tree: &FileTree) -> String {
+        let mut html = format!(
+            "<div class=\"landing-page\">\n<h1>{}</h1>\n",
+            html_escape::encode_text(&self.title)
+        );
+        for (dir, files) in &Self::group_by_directory(file_tree) {
+            if !dir.is_empty() {
+                html.push_str(&format!("<h2>{}/</h2>\n", html_escape::encode_text(dir)));
+            }
+            html.push_str("<ul>\n");
+            for file in files {
+                let path = normalize_relative_path(&file.relative_path);
+                let href = format!("/view?file={}", encode_query_value(&path));
+                html.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a>",
+                    html_escape::encode_double_quoted_attribute(&href),
+                    html_escape::encode_text(file.title.as_deref().unwrap_or(&file.name))
+                ));
+                if file.title.is_some() {
+                    html.push_str(&format!(
+                        " <code>{}</code>",
+                        html_escape::encode_text(&path)
+                    ));
+                }
+                html.push_str("</li>\n");
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</div>\n");
+        html
+    }
+
+    /// Build sidebar HTML from file tree
+    fn build_sidebar(&self, file_tree: &FileTree, current_file: Option<&str>) -> String {
+        let mut html = String::new();
 
-        // Render file tree
-        for (dir, files) in &dirs {
+        // Render file tree, grouped by directory
+        for (dir, files) in &Self::group_by_directory(file_tree) {
             if dir.is_empty() {
                 // Root level files
                 for file in files {
@@ -126,34 +692,103 @@ impl HtmlRenderer {
         if is_root {
             classes.push("root-item");
         }
+        let recent = self.since.is_some_and(|since| file.modified_within(since));
+        if recent {
+            classes.push("recent");
+        }
+
+        // Front matter metadata for the sidebar filter
+        let mut metadata = String::new();
+        if !file.tags.is_empty() {
+            metadata.push_str(&format!(
+                r#" data-tags="{}""#,
+                html_escape::encode_double_quoted_attribute(&file.tags.join(","))
+            ));
+        }
+        if let Some(title) = &file.title {
+            metadata.push_str(&format!(
+                r#" data-title="{}""#,
+                html_escape::encode_double_quoted_attribute(title)
+            ));
+        }
 
         format!(
-            r#"<a href="javascript:void(0)" class="{}" data-path="{}" onclick="loadFile('{}')">
+            r#"<a href="javascript:void(0)" class="{}" data-path="{}"{} onclick="loadFile('{}')">
                 {}
-                <span class="sidebar-item-name">{}</span>
+                <span class="sidebar-item-name">{}</span>{}
             </a>"#,
             classes.join(" "),
             html_escape::encode_text(&path),
+            metadata,
             html_escape::encode_text(&path),
             ICON_FILE,
-            html_escape::encode_text(&file.name)
+            html_escape::encode_text(&file.name),
+            if recent { RECENT_BADGE } else { "" }
         )
     }
 
     /// Convert markdown to HTML fragment
     fn markdown_to_html(&self, markdown: &str) -> String {
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_TABLES);
-        options.insert(Options::ENABLE_STRIKETHROUGH);
-        options.insert(Options::ENABLE_TASKLISTS);
-        options.insert(Options::ENABLE_FOOTNOTES);
+        self.render_to_html_string(markdown).0
+    }
+
+    /// Convert markdown to HTML fragment; `context` rewrites anchors and links for combined output
+    fn markdown_to_html_with_context(
+        &self,
+        markdown: &str,
+        context: Option<&CombineContext>,
+    ) -> (String, Vec<TocEntry>) {
+        let input = normalize_input(markdown);
+        let (frontmatter, markdown) = split_frontmatter(&input);
+        let (raw, ranges): (Vec<_>, Vec<_>) =
+            Parser::new_ext(markdown, self.parse_options.to_pulldown())
+                .into_offset_iter()
+                .unzip();
+        let mut blocks = top_level_blocks(&raw, &ranges).into_iter();
+        let raw = math_events(raw, &ranges, markdown);
+        let (parser, _) = check_footnotes(autolink_events(raw));
+        let footnote_texts = match self.footnotes {
+            FootnoteMode::Section => HashMap::new(),
+            FootnoteMode::Inline | FootnoteMode::Hover => footnote_texts(&parser),
+        };
+        let mut parser = inline_code_languages(parser, self.inline_code_lang.as_deref());
+        for event in &mut parser {
+            if let Event::Start(Tag::Image { dest_url, .. }) = event
+                && let Cow::Owned(url) = self.rebase_url(Cow::Borrowed(dest_url.as_ref()))
+            {
+                *dest_url = url.into();
+            }
+        }
+        let mut footnote_numbers: HashMap<String, usize> = HashMap::new();
+        let mut footnote_start = 0;
 
-        let parser = Parser::new_ext(markdown, options);
+        // 1-based source line of the next block, counting front matter lines
+        let mut line = input[..input.len() - markdown.len()].matches('\n').count() + 1;
+        let mut line_pos = 0;
+        let mut depth = 0usize;
+
+        // Tag badges go below a leading H1 title, otherwise at the top
+        let mut tags_html = frontmatter
+            .as_ref()
+            .map(FrontMatter::tags)
+            .filter(|tags| !tags.is_empty())
+            .map(|tags| Self::tags_html(&tags));
+        let starts_with_title = matches!(
+            parser.first(),
+            Some(Event::Start(Tag::Heading {
+                level: HeadingLevel::H1,
+                ..
+            }))
+        );
 
         // Collect TOC entries and add IDs to headings
-        let mut toc_entries: Vec<(u8, String, String)> = Vec::new(); // (level, text, anchor)
+        let mut toc_entries: Vec<TocEntry> = Vec::new();
         let mut anchor_gen = AnchorGenerator::new();
+        // Events of the current top-level block, and of the finished ones with the
+        // source line each starts on when tracked
         let mut main_events: Vec<Event> = Vec::new();
+        let mut main_blocks: Vec<(Option<usize>, Vec<Event>)> = Vec::new();
+        let mut block_line = None;
         let mut footnote_events: Vec<Event> = Vec::new();
         let mut in_footnote = false;
         let mut in_heading = false;
@@ -162,16 +797,82 @@ impl HtmlRenderer {
         let mut current_heading_events: Vec<Event> = Vec::new(); // Events for HTML structure
         let mut current_heading_classes: Vec<CowStr> = Vec::new();
         let mut current_heading_attrs: Vec<(CowStr, Option<CowStr>)> = Vec::new();
+        let mut task_index = 0;
+
+        if let Some(html) = frontmatter
+            .as_ref()
+            .and_then(|fm| self.frontmatter_html(fm))
+        {
+            main_events.push(Event::Html(CowStr::Boxed(html.into_boxed_str())));
+        }
+        if !starts_with_title && let Some(html) = tags_html.take() {
+            main_events.push(Event::Html(CowStr::Boxed(html.into_boxed_str())));
+        }
 
         for event in parser {
+            // Mark where each top-level block starts in the source for scroll sync
+            if self.source_lines
+                && depth == 0
+                && !matches!(
+                    &event,
+                    Event::Start(Tag::FootnoteDefinition(_) | Tag::HtmlBlock) | Event::End(_)
+                )
+                && let Some((_, range)) = blocks.find(|(block, _)| *block == event)
+            {
+                line += markdown[line_pos..range.start].matches('\n').count();
+                line_pos = range.start;
+                main_blocks.push((block_line.replace(line), std::mem::take(&mut main_events)));
+            }
+            match &event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                _ => {}
+            }
+
             match &event {
                 Event::Start(Tag::FootnoteDefinition(_)) => {
                     in_footnote = true;
+                    footnote_start = footnote_events.len();
                     footnote_events.push(event);
                 }
                 Event::End(TagEnd::FootnoteDefinition) => {
                     footnote_events.push(event);
                     in_footnote = false;
+                    // Inlined definitions don't need a section entry
+                    if self.footnotes == FootnoteMode::Inline
+                        && let Event::Start(Tag::FootnoteDefinition(label)) =
+                            &footnote_events[footnote_start]
+                        && footnote_texts.contains_key(label.as_ref())
+                    {
+                        footnote_events.truncate(footnote_start);
+                    }
+                }
+                // Footnote text in place of the reference, or as its tooltip
+                Event::FootnoteReference(label)
+                    if !in_footnote && footnote_texts.contains_key(label.as_ref()) =>
+                {
+                    let text = &footnote_texts[label.as_ref()];
+                    let html = if self.footnotes == FootnoteMode::Inline {
+                        format!(
+                            r#" <span class="footnote-inline">({})</span>"#,
+                            html_escape::encode_text(text)
+                        )
+                    } else {
+                        let next = footnote_numbers.len() + 1;
+                        let number = *footnote_numbers.entry(label.to_string()).or_insert(next);
+                        format!(
+                            r##"<sup class="footnote-reference"><a href="#{}" title="{}">{}</a></sup>"##,
+                            html_escape::encode_double_quoted_attribute(label),
+                            html_escape::encode_double_quoted_attribute(text),
+                            number
+                        )
+                    };
+                    let html_event = Event::Html(CowStr::Boxed(html.into_boxed_str()));
+                    if in_heading {
+                        current_heading_events.push(html_event);
+                    } else {
+                        main_events.push(html_event);
+                    }
                 }
                 Event::Start(Tag::Heading {
                     level,
@@ -191,14 +892,20 @@ impl HtmlRenderer {
                     in_heading = false;
 
                     // Generate anchor using shared utility (from plain text)
-                    let anchor = anchor_gen.generate(&current_heading_text);
+                    let mut anchor = anchor_gen.generate(&current_heading_text);
+                    if let Some(context) = context {
+                        anchor = format!("{}-{}", context.section, anchor);
+                    }
 
-                    // Store TOC entry (plain text for display)
-                    toc_entries.push((
-                        current_heading_level,
-                        current_heading_text.clone(),
-                        anchor.clone(),
-                    ));
+                    // Store TOC entry (plain text for display); headings inside
+                    // footnotes stay out of the TOC
+                    if !in_footnote {
+                        toc_entries.push(TocEntry {
+                            level: current_heading_level,
+                            text: current_heading_text.clone(),
+                            anchor: anchor.clone(),
+                        });
+                    }
 
                     // Create heading with id attribute
                     let level = match current_heading_level {
@@ -209,23 +916,24 @@ impl HtmlRenderer {
                         5 => HeadingLevel::H5,
                         _ => HeadingLevel::H6,
                     };
-                    main_events.push(Event::Start(Tag::Heading {
+                    // A heading inside a footnote stays with the footnote, in source order
+                    let target = if in_footnote {
+                        &mut footnote_events
+                    } else {
+                        &mut main_events
+                    };
+                    target.push(Event::Start(Tag::Heading {
                         level,
                         id: Some(CowStr::Boxed(anchor.into_boxed_str())),
                         classes: current_heading_classes.clone(),
                         attrs: current_heading_attrs.clone(),
                     }));
                     // Push collected heading content (preserves links and other inline elements)
-                    main_events.append(&mut current_heading_events);
-                    main_events.push(event);
-                }
-                Event::Text(text) if in_heading => {
-                    current_heading_text.push_str(text);
-                    current_heading_events.push(event);
-                }
-                Event::Code(code) if in_heading => {
-                    current_heading_text.push_str(code);
-                    current_heading_events.push(event);
+                    target.append(&mut current_heading_events);
+                    target.push(event);
+                    if !in_footnote && let Some(html) = tags_html.take() {
+                        main_events.push(Event::Html(CowStr::Boxed(html.into_boxed_str())));
+                    }
                 }
                 // Transform Link events to Html events with custom attributes
                 Event::Start(Tag::Link {
@@ -239,7 +947,11 @@ impl HtmlRenderer {
                     } else {
                         Some(title.as_ref())
                     };
-                    let html = Self::generate_link_open_tag(dest_url.as_ref(), title_opt);
+                    let url = self.rebase_url(match context {
+                        Some(context) => context.rewrite_link(dest_url),
+                        None => Cow::Borrowed(dest_url.as_ref()),
+                    });
+                    let html = Self::generate_link_open_tag(&url, title_opt);
                     let html_event = Event::Html(CowStr::Boxed(html.into_boxed_str()));
                     if in_heading {
                         current_heading_events.push(html_event);
@@ -249,6 +961,22 @@ impl HtmlRenderer {
                         main_events.push(html_event);
                     }
                 }
+                // Number task checkboxes in source order so the browser can toggle them
+                Event::TaskListMarker(checked) => {
+                    let html = format!(
+                        r#"{} data-task-index="{}" disabled=""{} />"#,
+                        TASK_CHECKBOX_PREFIX,
+                        task_index,
+                        if *checked { r#" checked="""# } else { "" }
+                    );
+                    task_index += 1;
+                    let html_event = Event::Html(CowStr::Boxed(html.into_boxed_str()));
+                    if in_footnote {
+                        footnote_events.push(html_event);
+                    } else {
+                        main_events.push(html_event);
+                    }
+                }
                 Event::End(TagEnd::Link) => {
                     let html_event = Event::Html(CowStr::Borrowed("</a>"));
                     if in_heading {
@@ -260,9 +988,13 @@ impl HtmlRenderer {
                     }
                 }
                 _ => {
-                    if in_footnote {
+                    // Inline styling in headings is kept; only text feeds the anchor
+                    if in_heading {
+                        push_heading_text(&mut current_heading_text, &event);
+                        current_heading_events.push(event);
+                    } else if in_footnote {
                         footnote_events.push(event);
-                    } else if !in_heading {
+                    } else {
                         main_events.push(event);
                     }
                 }
@@ -274,37 +1006,120 @@ impl HtmlRenderer {
         if self.show_toc && !toc_entries.is_empty() {
             html_output.push_str("<nav class=\"toc\">\n");
             html_output.push_str("<h2>📑 Table of Contents</h2>\n");
-            html_output.push_str("<ul>\n");
-
-            let min_level = toc_entries.iter().map(|(l, _, _)| *l).min().unwrap_or(1);
-            for (level, text, anchor) in &toc_entries {
-                let indent = "  ".repeat((*level - min_level) as usize);
-                html_output.push_str(&format!(
-                    "{}<li><a href=\"#{}\">{}</a></li>\n",
-                    indent,
-                    html_escape::encode_text(anchor),
-                    html_escape::encode_text(text)
-                ));
-            }
-
-            html_output.push_str("</ul>\n");
+            html_output.push_str(&Self::toc_list(&toc_entries));
             html_output.push_str("</nav>\n");
             html_output.push_str("<hr />\n");
         }
 
-        // Render main content
-        html::push_html(&mut html_output, main_events.into_iter());
+        // Render main content, with source lines on the blocks' opening tags
+        main_blocks.push((block_line, main_events));
+        for (line, events) in main_blocks {
+            push_block(&mut html_output, rewrite_blocks(events), line);
+        }
 
         // Render footnotes at the end with separator
         if !footnote_events.is_empty() {
             html_output.push_str("<hr class=\"footnotes-separator\" />\n");
             html_output.push_str("<section class=\"footnotes\">\n");
-            html::push_html(&mut html_output, footnote_events.into_iter());
+            html::push_html(
+                &mut html_output,
+                rewrite_blocks(footnote_events).into_iter(),
+            );
             html_output.push_str("</section>\n");
         }
 
-        // Process mermaid code blocks
-        self.process_mermaid(&html_output)
+        (html_output, toc_entries)
+    }
+
+    /// TOC entries as nested `<ul>` lists following the heading hierarchy. A heading
+    /// that skips levels nests one list deeper; one between two open levels joins
+    /// the deeper list.
+    fn toc_list(entries: &[TocEntry]) -> String {
+        let mut html = String::new();
+        // Heading level of each open list, outermost first
+        let mut open: Vec<u8> = Vec::new();
+
+        for entry in entries {
+            match open.last() {
+                None => {
+                    html.push_str("<ul>\n");
+                    open.push(entry.level);
+                }
+                Some(&level) if entry.level > level => {
+                    // Nest inside the previous item, which is still open
+                    html.push_str("\n<ul>\n");
+                    open.push(entry.level);
+                }
+                Some(_) => {
+                    html.push_str("</li>\n");
+                    while open.len() > 1 && entry.level < open[open.len() - 1] {
+                        if entry.level > open[open.len() - 2] {
+                            *open.last_mut().unwrap() = entry.level;
+                            break;
+                        }
+                        html.push_str("</ul>\n</li>\n");
+                        open.pop();
+                    }
+                }
+            }
+            html.push_str(&format!(
+                "<li><a href=\"#{}\">{}</a>",
+                html_escape::encode_text(&entry.anchor),
+                html_escape::encode_text(&entry.text)
+            ));
+        }
+
+        // Close each open item along with its list
+        for _ in &open {
+            html.push_str("</li>\n</ul>\n");
+        }
+        html
+    }
+
+    /// Front matter as a key/value table or the raw block, depending on the mode
+    fn frontmatter_html(&self, frontmatter: &FrontMatter) -> Option<String> {
+        match self.frontmatter {
+            FrontMatterMode::Hide => None,
+            FrontMatterMode::Show => {
+                let fields = frontmatter.display_fields();
+                if fields.is_empty() {
+                    return None;
+                }
+                let rows: String = fields
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "<tr><th>{}</th><td>{}</td></tr>\n",
+                            html_escape::encode_text(key),
+                            html_escape::encode_text(value)
+                        )
+                    })
+                    .collect();
+                Some(format!(
+                    "<table class=\"frontmatter\">\n<tbody>\n{}</tbody>\n</table>\n",
+                    rows
+                ))
+            }
+            FrontMatterMode::Raw => Some(format!(
+                "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                frontmatter.format.language(),
+                html_escape::encode_text(&frontmatter.raw)
+            )),
+        }
+    }
+
+    /// Badge pills for front matter tags
+    fn tags_html(tags: &[String]) -> String {
+        let badges: String = tags
+            .iter()
+            .map(|tag| {
+                format!(
+                    r#"<span class="tag">{}</span>"#,
+                    html_escape::encode_text(tag)
+                )
+            })
+            .collect();
+        format!("<div class=\"frontmatter-tags\">{}</div>\n", badges)
     }
 
     /// Generate opening <a> tag with appropriate attributes based on URL type
@@ -337,36 +1152,6 @@ impl HtmlRenderer {
         }
     }
 
-    /// Process mermaid code blocks into styled containers
-    fn process_mermaid(&self, html: &str) -> String {
-        let mermaid_pattern =
-            regex::Regex::new(r#"<pre><code class="language-mermaid">([^<]*)</code></pre>"#).ok();
-
-        if let Some(re) = mermaid_pattern {
-            re.replace_all(html, |caps: &regex::Captures| {
-                // Decode HTML entities first to get raw mermaid code,
-                // then re-encode to ensure safe HTML output
-                let code = html_escape::decode_html_entities(&caps[1]);
-                let safe_code = html_escape::encode_text(code.trim());
-                format!(
-                    r#"<div class="mermaid-container">
-    <div class="mermaid-header">
-        <svg viewBox="0 0 24 24"><path d="M12 2L2 7l10 5 10-5-10-5zM2 17l10 5 10-5M2 12l10 5 10-5"/></svg>
-        <span>Mermaid Diagram</span>
-    </div>
-    <div class="mermaid-body">
-        <pre class="mermaid">{}</pre>
-    </div>
-</div>"#,
-                    safe_code
-                )
-            })
-            .to_string()
-        } else {
-            html.to_string()
-        }
-    }
-
     fn heading_level_to_u8(level: HeadingLevel) -> u8 {
         match level {
             HeadingLevel::H1 => 1,
@@ -384,6 +1169,459 @@ impl HtmlRenderer {
     }
 }
 
+/// Give items with a task checkbox the `task-list-item` class, and lists holding
+/// them `contains-task-list`, like GitHub, so the stylesheet can drop their bullets
+/// at every nesting level. Expects checkboxes already rendered as HTML.
+fn mark_task_lists(mut events: Vec<Event>) -> Vec<Event> {
+    let mut lists = Vec::new();
+    let mut items = Vec::new();
+    let mut task_lists: Vec<usize> = Vec::new();
+    let mut task_items: Vec<usize> = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::List(_)) => lists.push(i),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => items.push(i),
+            Event::End(TagEnd::Item) => {
+                items.pop();
+            }
+            Event::Html(html) if html.starts_with(TASK_CHECKBOX_PREFIX) => {
+                task_items.extend(items.last());
+                task_lists.extend(lists.last());
+            }
+            _ => {}
+        }
+    }
+
+    for i in task_items {
+        events[i] = Event::Html(CowStr::Borrowed("<li class=\"task-list-item\">"));
+    }
+    for i in task_lists {
+        let tag = match &events[i] {
+            Event::Start(Tag::List(Some(1))) => "<ol class=\"contains-task-list\">".to_string(),
+            Event::Start(Tag::List(Some(start))) => {
+                format!("<ol class=\"contains-task-list\" start=\"{}\">", start)
+            }
+            // Already replaced for an earlier item
+            Event::Html(_) => continue,
+            _ => "<ul class=\"contains-task-list\">".to_string(),
+        };
+        // Start on a new line as the HTML writer would, e.g. after an item's text
+        let ends_line = ends_line(i.checked_sub(1).map(|prev| &events[prev]));
+        let html = format!("{}{}\n", if ends_line { "" } else { "\n" }, tag);
+        events[i] = Event::Html(CowStr::Boxed(html.into_boxed_str()));
+    }
+    events
+}
+
+/// Markup the HTML writer has no support for: task lists, alerts, math, code block
+/// headers, diagrams, and images without alt text
+fn rewrite_blocks(events: Vec<Event>) -> Vec<Event> {
+    diagrams(code_block_headers(math(alerts(mark_task_lists(
+        missing_alt(events),
+    )))))
+}
+
+/// Mark images without alt text, kept as alt="" since that is valid for decoration
+fn missing_alt(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    for event in events {
+        if let Event::End(TagEnd::Image) = event
+            && let Some(Event::Start(Tag::Image { .. })) = result.last()
+        {
+            let mut html = String::new();
+            html::push_html(&mut html, [result.pop().unwrap(), event].into_iter());
+            let html = with_attribute(&html, "data-missing-alt");
+            result.push(Event::Html(CowStr::Boxed(html.into_boxed_str())));
+            continue;
+        }
+        result.push(event);
+    }
+    result
+}
+
+/// Whether the HTML written for `prev` ends a line, so a block tag after it needs no
+/// newline of its own
+fn ends_line(prev: Option<&Event>) -> bool {
+    match prev {
+        None => true,
+        Some(Event::Html(html)) => html.ends_with('\n'),
+        Some(event) => matches!(
+            event,
+            Event::Rule
+                | Event::Start(Tag::BlockQuote(_))
+                | Event::End(
+                    TagEnd::Paragraph
+                        | TagEnd::Heading(_)
+                        | TagEnd::BlockQuote(_)
+                        | TagEnd::CodeBlock
+                        | TagEnd::List(_)
+                        | TagEnd::Item
+                        | TagEnd::Table
+                        | TagEnd::HtmlBlock
+                        | TagEnd::FootnoteDefinition
+                )
+        ),
+    }
+}
+
+/// Write a top-level block to `output` on a line of its own, with its source `line`
+/// as `data-source-line` on its opening tag for scroll sync
+fn push_block(output: &mut String, events: Vec<Event>, line: Option<usize>) {
+    let mut html = String::new();
+    html::push_html(&mut html, events.into_iter());
+    if html.is_empty() {
+        return;
+    }
+    // The writer starts blocks on a new line, but only within one call
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    match line {
+        Some(line) => output.push_str(&with_attribute(
+            html.trim_start_matches('\n'),
+            &format!(r#"data-source-line="{}""#, line),
+        )),
+        None => output.push_str(&html),
+    }
+}
+
+/// `html` with `attribute` added to its first tag
+fn with_attribute(html: &str, attribute: &str) -> String {
+    let Some(tag_start) = html.find('<') else {
+        return html.to_string();
+    };
+    // End of the tag, skipping `>` inside quoted attribute values
+    let mut quote = None;
+    let mut end = html.len();
+    for (i, c) in html[tag_start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => {
+                end = tag_start + i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    let tag = html[..end].trim_end_matches('/').trim_end();
+    format!("{} {}{}", tag, attribute, &html[tag.len()..])
+}
+
+/// Keep only the language of fenced code block info strings, and wrap blocks with
+/// a `title="..."` or a shell language in a container whose header shows the file
+/// name and, for shell, a "Copy command" button. Diagrams are left alone.
+fn code_block_headers(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut wrapped = false;
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let (language, title) = parse_code_info(&info);
+                let language = language.unwrap_or_default();
+                let shell = SHELL_LANGUAGES.contains(&language.as_str());
+                if (title.is_some() || shell) && DiagramKind::from_language(&language).is_none() {
+                    let mut header =
+                        String::from("<div class=\"code-block\"><div class=\"code-header\">");
+                    if let Some(title) = title {
+                        header.push_str(&format!(
+                            "<span class=\"code-title\">{}</span>",
+                            html_escape::encode_text(&title)
+                        ));
+                    }
+                    if shell {
+                        header.push_str(
+                            "<button class=\"code-copy\" type=\"button\">Copy command</button>",
+                        );
+                    }
+                    header.push_str("</div>\n");
+                    result.push(Event::Html(header.into()));
+                    wrapped = true;
+                }
+                result.push(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(
+                    language.into(),
+                ))));
+            }
+            Event::End(TagEnd::CodeBlock) if wrapped => {
+                result.push(event);
+                result.push(Event::Html("</div>\n".into()));
+                wrapped = false;
+            }
+            event => result.push(event),
+        }
+    }
+    result
+}
+
+/// Turn diagram code blocks into labeled containers for the page script to draw
+fn diagrams(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut diagram: Option<(DiagramKind, String)> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref language))) => {
+                match DiagramKind::from_language(language) {
+                    Some(kind) => diagram = Some((kind, String::new())),
+                    None => result.push(event),
+                }
+            }
+            Event::Text(ref text) if diagram.is_some() => {
+                if let Some((_, code)) = &mut diagram {
+                    code.push_str(text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) if diagram.is_some() => {
+                let Some((kind, code)) = diagram.take() else {
+                    continue;
+                };
+                let html = format!(
+                    r#"<div class="diagram-container {name}-container">
+    <div class="diagram-header">
+        <svg viewBox="0 0 24 24"><path d="M12 2L2 7l10 5 10-5-10-5zM2 17l10 5 10-5M2 12l10 5 10-5"/></svg>
+        <span>{title}</span>
+    </div>
+    <div class="diagram-body">
+        <pre class="{name}">{code}</pre>
+    </div>
+</div>
+"#,
+                    name = kind.name(),
+                    title = kind.title(),
+                    code = html_escape::encode_text(code.trim()),
+                );
+                result.push(Event::Html(CowStr::Boxed(html.into_boxed_str())));
+            }
+            event => result.push(event),
+        }
+    }
+    result
+}
+
+/// Inline code spans marked with a language for the browser's highlighter, from a
+/// `{.lang}` attribute right after the span or else `default_lang`. Code in headings
+/// stays plain so it still feeds the anchor; only the attribute is removed there.
+fn inline_code_languages<'a>(events: Vec<Event<'a>>, default_lang: Option<&str>) -> Vec<Event<'a>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut events = events.into_iter().peekable();
+    let mut in_heading = false;
+    while let Some(event) = events.next() {
+        match &event {
+            Event::Start(Tag::Heading { .. }) => in_heading = true,
+            Event::End(TagEnd::Heading(_)) => in_heading = false,
+            _ => {}
+        }
+        let Event::Code(code) = event else {
+            result.push(event);
+            continue;
+        };
+
+        // Take the attribute off the text that follows
+        let mut lang = default_lang.map(str::to_string);
+        let mut rest = None;
+        if let Some(Event::Text(text)) = events.peek()
+            && let Some(caps) = INLINE_CODE_LANG_RE.captures(text)
+        {
+            lang = Some(caps[1].to_string());
+            rest = Some(text[caps[0].len()..].to_string());
+            events.next();
+        }
+
+        match lang {
+            Some(lang) if !in_heading => result.push(Event::Html(CowStr::Boxed(
+                format!(
+                    r#"<code class="language-{}">{}</code>"#,
+                    html_escape::encode_double_quoted_attribute(&lang),
+                    html_escape::encode_text(&code)
+                )
+                .into_boxed_str(),
+            ))),
+            _ => result.push(Event::Code(code)),
+        }
+        if let Some(rest) = rest.filter(|rest| !rest.is_empty()) {
+            result.push(Event::Text(CowStr::Boxed(rest.into_boxed_str())));
+        }
+    }
+    result
+}
+
+/// Blockquotes whose first line is only a `[!NOTE]`-style marker as `alert` divs,
+/// titled after the kind in place of the marker
+fn alerts(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    // Whether each open blockquote became an alert
+    let mut quotes = Vec::new();
+    let mut events = events.into_iter().peekable();
+    while let Some(event) = events.next() {
+        match event {
+            Event::Start(Tag::BlockQuote(_)) => {
+                quotes.push(false);
+                result.push(event);
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                if quotes.pop() == Some(true) {
+                    result.push(Event::Html("</div>\n".into()));
+                } else {
+                    result.push(event);
+                }
+            }
+            Event::Text(ref text) => {
+                let first_line = matches!(
+                    result[..],
+                    [
+                        ..,
+                        Event::Start(Tag::BlockQuote(_)),
+                        Event::Start(Tag::Paragraph)
+                    ]
+                ) && matches!(
+                    events.peek(),
+                    Some(Event::SoftBreak | Event::HardBreak | Event::End(TagEnd::Paragraph))
+                );
+                let Some(kind) = AlertKind::from_marker(text).filter(|_| first_line) else {
+                    result.push(event);
+                    continue;
+                };
+                result.truncate(result.len() - 2);
+                if let Some(quote) = quotes.last_mut() {
+                    *quote = true;
+                }
+                result.push(Event::Html(
+                    format!(
+                        "<div class=\"alert alert-{}\">\n<p class=\"alert-title\">{}</p>\n",
+                        kind.label().to_lowercase(),
+                        kind.label()
+                    )
+                    .into(),
+                ));
+                // The rest of the first paragraph, if any, keeps its own
+                if !matches!(events.next(), Some(Event::End(TagEnd::Paragraph))) {
+                    result.push(Event::Start(Tag::Paragraph));
+                }
+            }
+            event => result.push(event),
+        }
+    }
+    result
+}
+
+/// Math as spans KaTeX renders, and display math alone in a paragraph as a
+/// `math-block` div, which KaTeX renders in display mode
+fn math(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut events = events.into_iter().peekable();
+    while let Some(event) = events.next() {
+        let (tex, display) = match &event {
+            Event::InlineMath(tex) => (tex, false),
+            Event::DisplayMath(tex) => (tex, true),
+            _ => {
+                result.push(event);
+                continue;
+            }
+        };
+        let tex = html_escape::encode_text(tex).into_owned();
+        if display
+            && let Some(Event::Start(Tag::Paragraph)) = result.last()
+            && let Some(Event::End(TagEnd::Paragraph)) = events.peek()
+        {
+            result.pop();
+            events.next();
+            result.push(Event::Html(
+                format!("<div class=\"math-block\">{}</div>\n", tex).into(),
+            ));
+            continue;
+        }
+        let class = if display {
+            "math-display"
+        } else {
+            "math-inline"
+        };
+        result.push(Event::InlineHtml(
+            format!("<span class=\"{}\">{}</span>", class, tex).into(),
+        ));
+    }
+    result
+}
+
+/// Plain text of each footnote definition by label, with whitespace collapsed
+fn footnote_texts(events: &[Event]) -> HashMap<String, String> {
+    let mut texts = HashMap::new();
+    let mut current: Option<(&str, String)> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                current = Some((label.as_ref(), String::new()));
+            }
+            Event::End(TagEnd::FootnoteDefinition) => {
+                if let Some((label, text)) = current.take() {
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if !text.is_empty() {
+                        texts.entry(label.to_string()).or_insert(text);
+                    }
+                }
+            }
+            Event::Text(text)
+            | Event::Code(text)
+            | Event::InlineMath(text)
+            | Event::DisplayMath(text) => {
+                if let Some((_, current)) = current.as_mut() {
+                    current.push_str(text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak | Event::End(TagEnd::Paragraph) => {
+                if let Some((_, current)) = current.as_mut() {
+                    current.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    texts
+}
+
+/// Plain text of the first top-level paragraph with any text, shortened for a
+/// page description
+fn first_paragraph_text(document: &Document) -> Option<String> {
+    document.elements.iter().find_map(|element| {
+        let Element::Paragraph { content } = element else {
+            return None;
+        };
+        let text = inline_text(content);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return None;
+        }
+        if text.chars().count() <= DESCRIPTION_MAX_CHARS {
+            return Some(text);
+        }
+        let cut: String = text.chars().take(DESCRIPTION_MAX_CHARS - 1).collect();
+        Some(format!("{}…", cut.trim_end()))
+    })
+}
+
+/// URL of the first image in the document, including images in lists and quotes
+fn first_image(elements: &[Element]) -> Option<String> {
+    fn inline_image(inlines: &[InlineElement]) -> Option<String> {
+        inlines.iter().find_map(|inline| match inline {
+            InlineElement::Image { url, .. } => Some(url.clone()),
+            InlineElement::Strong(content)
+            | InlineElement::Emphasis(content)
+            | InlineElement::Strikethrough(content)
+            | InlineElement::Link { content, .. } => inline_image(content),
+            _ => None,
+        })
+    }
+
+    elements.iter().find_map(|element| match element {
+        Element::Image { url, .. } => Some(url.clone()),
+        Element::Paragraph { content } => inline_image(content),
+        Element::List { items, .. } => items.iter().find_map(|item| first_image(&item.content)),
+        Element::BlockQuote { content } | Element::Alert { content, .. } => first_image(content),
+        _ => None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -396,6 +1634,377 @@ mod tests {
         assert!(result.contains("<p>World</p>"));
     }
 
+    #[test]
+    fn test_frontmatter_tags() {
+        let renderer = HtmlRenderer::new("Test");
+        let html = renderer.render_content("---\ntags: [rust, <cli>]\n---\n# Title\n\nBody");
+        assert!(!html.contains("<hr"));
+        assert!(html.contains(
+            "</h1>\n<div class=\"frontmatter-tags\"><span class=\"tag\">rust</span><span class=\"tag\">&lt;cli&gt;</span></div>"
+        ));
+
+        let html = renderer.render_content("---\ntags: rust\n---\nBody");
+        assert!(html.starts_with("<div class=\"frontmatter-tags\">"));
+
+        let html = renderer.render_content("---\ntitle: x\n---\n# Title");
+        assert!(!html.contains("frontmatter-tags"));
+
+        let html = renderer.render_content("+++\ntitle = \"x\"\ntags = [\"toml\"]\n+++\nBody");
+        assert!(!html.contains("title ="));
+        assert!(html.contains("<span class=\"tag\">toml</span>"));
+    }
+
+    #[test]
+    fn test_frontmatter_modes() {
+        let input = "---\ntitle: A & B\ntags: [x, y]\n---\n# Title";
+        let renderer = HtmlRenderer::new("Test");
+        let hidden = renderer.render_content(input);
+        assert!(!hidden.contains("class=\"frontmatter\""));
+        assert!(!hidden.contains("title:"));
+
+        let shown = renderer
+            .clone()
+            .with_frontmatter(FrontMatterMode::Show)
+            .render_content(input);
+        assert!(shown.starts_with(
+            "<table class=\"frontmatter\">\n<tbody>\n<tr><th>title</th><td>A &amp; B</td></tr>\n<tr><th>tags</th><td>x, y</td></tr>\n"
+        ));
+
+        let raw = renderer
+            .with_frontmatter(FrontMatterMode::Raw)
+            .render_content("+++\ntitle = \"<x>\"\n+++\nBody");
+        assert!(raw.starts_with(
+            "<pre><code class=\"language-toml\">title = \"&lt;x&gt;\"\n</code></pre>\n"
+        ));
+    }
+
+    #[test]
+    fn test_task_list_indices() {
+        let renderer = HtmlRenderer::new("Test");
+        let html = renderer.render_content("- [ ] a\n- [x] b");
+        assert!(html.contains(r#"data-task-index="0" disabled="" />"#));
+        assert!(html.contains(r#"data-task-index="1" disabled="" checked="" />"#));
+    }
+
+    #[test]
+    fn test_nested_task_lists() {
+        let renderer = HtmlRenderer::new("Test").with_source_lines(true);
+        let html = renderer.render_content(
+            "- [ ] parent\n  - [x] child\n  - plain\n- item\n  1. [ ] ordered\n\n> - [ ] quoted\n",
+        );
+        assert_eq!(html.matches(r#"<input type="checkbox""#).count(), 4);
+        assert!(html.contains(
+            "<ul class=\"contains-task-list\" data-source-line=\"1\">\n<li class=\"task-list-item\"><input type=\"checkbox\" class=\"task-list-item-checkbox\" data-task-index=\"0\" disabled=\"\" />parent\n<ul class=\"contains-task-list\">\n<li class=\"task-list-item\"><input type=\"checkbox\" class=\"task-list-item-checkbox\" data-task-index=\"1\" disabled=\"\" checked=\"\" />child</li>\n<li>plain</li>"
+        ));
+        assert!(html.contains(
+            "<li>item\n<ol class=\"contains-task-list\">\n<li class=\"task-list-item\">"
+        ));
+        assert!(
+            html.contains("<blockquote data-source-line=\"7\">\n<ul class=\"contains-task-list\">")
+        );
+    }
+
+    #[test]
+    fn test_math_spans() {
+        let renderer = HtmlRenderer::new("Test");
+        let html = renderer.render_content(
+            "# Area $a<b$\n\n$E = mc^2$ costs \\$3, or $5 and $10\n\n$$\n\\sum x\n$$\n\n`$x$`\n",
+        );
+        assert!(
+            html.contains(
+                "<h1 id=\"area-a-b\">Area <span class=\"math-inline\">a&lt;b</span></h1>"
+            )
+        );
+        assert!(html.contains(
+            "<p><span class=\"math-inline\">E = mc^2</span> costs $3, or $5 and $10</p>"
+        ));
+        assert!(html.contains("<div class=\"math-block\">\\sum x</div>"));
+        assert!(html.contains("<code>$x$</code>"));
+
+        // Math in a footnote and TeX that looks like a URL
+        let html = renderer.render_content("Note[^1]\n\n[^1]: $$www.example.com$$\n");
+        assert!(html.contains("<div class=\"math-block\">www.example.com</div>"));
+        assert!(!html.contains("http://www.example.com"));
+    }
+
+    #[test]
+    fn test_alerts() {
+        let renderer = HtmlRenderer::new("Test").with_source_lines(true);
+        let html =
+            renderer.render_content("> [!IMPORTANT]\n> Back up *first*.\n\n> [!NOTE] text\n");
+        assert!(html.starts_with(
+            "<div class=\"alert alert-important\" data-source-line=\"1\">\n<p class=\"alert-title\">Important</p>\n<p>Back up <em>first</em>.</p>\n</div>\n"
+        ));
+        assert!(html.contains("<blockquote data-source-line=\"4\">\n<p>[!NOTE] text</p>"));
+    }
+
+    #[test]
+    fn test_math_block() {
+        let renderer = HtmlRenderer::new("Test").with_source_lines(true);
+        let html = renderer.render_content("Intro\n\n$$\na &= b \\\\\nc\n$$\n\nSee $$x$$ too\n");
+        assert!(
+            html.contains(
+                "<div class=\"math-block\" data-source-line=\"3\">a &amp;= b \\\\\nc</div>"
+            )
+        );
+        assert!(html.contains("See <span class=\"math-display\">x</span> too</p>"));
+    }
+
+    #[test]
+    fn test_code_block_headers() {
+        let renderer = HtmlRenderer::new("Test").with_source_lines(true);
+        let html = renderer.render_content(
+            "```rust title=\"main.rs\"\nfn main() {}\n```\n\n```console\n$ cargo run\nHello\n```\n\n```rust\nlet x = 1;\n```\n\n```mermaid title=\"flow\"\ngraph TD\n```\n",
+        );
+        assert!(html.contains(
+            "<div class=\"code-block\" data-source-line=\"1\"><div class=\"code-header\"><span class=\"code-title\">main.rs</span></div>\n<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n</div>"
+        ));
+        assert!(html.contains(
+            "<div class=\"code-header\"><button class=\"code-copy\" type=\"button\">Copy command</button></div>\n<pre><code class=\"language-console\">$ cargo run"
+        ));
+        // Plain blocks and diagrams are unchanged
+        assert!(html.contains("<pre data-source-line=\"10\"><code class=\"language-rust\">let x"));
+        assert_eq!(html.matches("class=\"code-block\"").count(), 2);
+        assert!(html.contains("mermaid-container"));
+    }
+
+    #[test]
+    fn test_render_to_html_string() {
+        let renderer = HtmlRenderer::new("Test");
+        let (html, toc) = renderer.render_to_html_string("# Intro\n\n## Usage `cli`\n\n## Usage");
+        assert!(html.contains("<h2 id=\"usage-cli\">"));
+        // TOC is returned separately, not inlined, when show_toc is off
+        assert!(!html.contains("class=\"toc\""));
+        // Every anchor is the id of its heading in the fragment
+        for entry in &toc {
+            assert!(html.contains(&format!(" id=\"{}\"", entry.anchor)));
+        }
+        let toc: Vec<(u8, &str, &str)> = toc
+            .iter()
+            .map(|e| (e.level, e.text.as_str(), e.anchor.as_str()))
+            .collect();
+        assert_eq!(
+            toc,
+            vec![
+                (1, "Intro", "intro"),
+                (2, "Usage cli", "usage-cli"),
+                (2, "Usage", "usage")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_anchors_match_terminal_toc() {
+        let markdown = concat!(
+            "# Using `mdp --toc` in [docs](x.md)\n\n",
+            "> ## Usage\n\n",
+            "- ## Usage\n\n",
+            "## Usage\n\n",
+            "Multi-line\nheading\n---\n\n",
+            "## *Emphasis* and <kbd>Ctrl</kbd> and a note[^1]\n\n",
+            "[^1]: ## Usage\n\n",
+            "## Usage\n",
+        );
+        let (_, html_toc) = HtmlRenderer::new("Test").render_to_html_string(markdown);
+        let terminal_toc = crate::parser::generate_toc(&crate::parser::parse_markdown(markdown));
+        let entries = |toc: &[TocEntry]| -> Vec<(String, String)> {
+            toc.iter()
+                .map(|e| (e.text.clone(), e.anchor.clone()))
+                .collect()
+        };
+        assert_eq!(entries(&terminal_toc), entries(&html_toc));
+    }
+
+    #[test]
+    fn test_render_element() {
+        let markdown = "---\ntitle: T\n---\n# Title\r\n\r\nSome *changed* text.\r\n";
+        let document = crate::parser::parse_markdown(markdown);
+        let renderer = HtmlRenderer::new("Test");
+        assert_eq!(
+            renderer.render_element(markdown, &document, 1).unwrap(),
+            "<p>Some <em>changed</em> text.</p>\n"
+        );
+        assert!(renderer.render_element(markdown, &document, 2).is_none());
+    }
+
+    #[test]
+    fn test_meta_tags() {
+        let renderer = HtmlRenderer::new("file.md");
+        let page = renderer.render(
+            "# Guide\n\n![logo](logo.png)\n\nRead **this** \"first\"\nplease.\n\n![later](b.png)",
+        );
+        assert!(page.contains(r#"<meta property="og:title" content="Guide">"#));
+        assert!(page.contains(
+            r#"<meta property="og:description" content="Read this &quot;first&quot; please.">"#
+        ));
+        assert!(page.contains(r#"<meta name="description" content="Read this"#));
+        assert!(page.contains(r#"<meta property="og:image" content="logo.png">"#));
+        assert!(!page.contains("{{META}}"));
+
+        // Front matter wins, and missing values leave their tags out
+        let page = renderer.render_standalone(
+            "---\ntitle: Post\ndescription: Summary\nimage: /cover.jpg\n---\n# Heading\n\nBody",
+        );
+        assert!(page.contains(r#"<meta property="og:title" content="Post">"#));
+        assert!(page.contains(r#"<meta property="og:description" content="Summary">"#));
+        assert!(page.contains(r#"<meta property="og:image" content="/cover.jpg">"#));
+
+        let page = renderer.render("```\ncode\n```");
+        assert!(page.contains(r#"<meta property="og:title" content="file.md">"#));
+        assert!(!page.contains("og:description"));
+        assert!(!page.contains("og:image"));
+
+        // Pages for the server carry no meta tags
+        assert!(!renderer.page("<p>x</p>").contains("og:title"));
+    }
+
+    #[test]
+    fn test_first_paragraph_text_is_shortened() {
+        let long = "word ".repeat(100);
+        let document = parse_markdown_with_options(&long, ParseOptions::default());
+        let text = first_paragraph_text(&document).unwrap();
+        assert_eq!(text.chars().count(), DESCRIPTION_MAX_CHARS);
+        assert!(text.ends_with("word…"));
+    }
+
+    #[test]
+    fn test_theme_option() {
+        let renderer = HtmlRenderer::new("Test");
+        assert!(
+            renderer
+                .render("x")
+                .contains("const THEME_OPTION = 'default';")
+        );
+        let page = renderer.with_theme(Some("auto".to_string())).render("x");
+        assert!(page.contains("const THEME_OPTION = 'auto';"));
+        assert!(!page.contains("{{THEME}}"));
+    }
+
+    #[test]
+    fn test_missing_alt() {
+        let renderer = HtmlRenderer::new("Test");
+        let html = renderer.render_content(
+            "![](a.png) ![logo](b.png \"Logo\") ![](c.png \"C\") <img src=\"d.png\" alt=\"\">",
+        );
+        assert!(html.contains(r#"<img src="a.png" alt="" data-missing-alt />"#));
+        assert!(html.contains(r#"<img src="b.png" alt="logo" title="Logo" />"#));
+        assert!(html.contains(r#"<img src="c.png" alt="" title="C" data-missing-alt />"#));
+        // Raw HTML is written as is
+        assert!(html.contains(r#"<img src="d.png" alt="">"#));
+
+        assert!(!renderer.render("x").contains(r#"class="authoring""#));
+        let page = renderer.with_authoring(true).render("x");
+        assert!(page.contains(r#"<body class="authoring">"#));
+    }
+
+    #[test]
+    fn test_split_body_class() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        let tree = FileTree::from_directory(dir.path()).unwrap();
+
+        let renderer = HtmlRenderer::new("Test").with_split(true);
+        let page = renderer.render_with_sidebar("# A", &tree, Some("a.md"));
+        assert!(page.contains(r#"<body class="split">"#));
+        let page = renderer.with_authoring(true).render("x");
+        assert!(page.contains(r#"<body class="authoring split">"#));
+    }
+
+    #[test]
+    fn test_toc_is_nested() {
+        let renderer = HtmlRenderer::new("Test").with_toc(true);
+        let html = renderer.render_content("# A\n## B\n### C\n## D\n# E\n### F\n## G");
+        let toc = &html[html.find("<ul>").unwrap()..html.find("</nav>").unwrap()];
+        let toc: String = toc.lines().collect();
+        assert_eq!(
+            toc,
+            concat!(
+                r##"<ul><li><a href="#a">A</a>"##,
+                r##"<ul><li><a href="#b">B</a>"##,
+                r##"<ul><li><a href="#c">C</a></li></ul></li>"##,
+                r##"<li><a href="#d">D</a></li></ul></li>"##,
+                r##"<li><a href="#e">E</a>"##,
+                r##"<ul><li><a href="#f">F</a></li>"##,
+                r##"<li><a href="#g">G</a></li></ul></li></ul>"##,
+            )
+        );
+    }
+
+    #[test]
+    fn test_smart_punctuation() {
+        let renderer = HtmlRenderer::new("Test").with_parse_options(ParseOptions {
+            smart_punctuation: true,
+        });
+        let html = renderer.render_content("\"quote\" -- dash... `\"raw\" --`");
+        assert!(html.contains("\u{201c}quote\u{201d} \u{2013} dash\u{2026}"));
+        assert!(html.contains("<code>\"raw\" --</code>"));
+
+        // Off by default
+        let html = HtmlRenderer::new("Test").render_content("a -- b...");
+        assert!(html.contains("a -- b..."));
+    }
+
+    #[test]
+    fn test_bare_url_opens_in_new_tab() {
+        let renderer = HtmlRenderer::new("Test");
+        let html = renderer.render_content("Visit https://example.com today");
+        assert!(html.contains(
+            r#"<a href="https://example.com" target="_blank" rel="noopener noreferrer">https://example.com</a>"#
+        ));
+    }
+
+    #[test]
+    fn test_render_standalone() {
+        let html = HtmlRenderer::new("Doc").render_standalone("# Hello");
+        assert!(html.contains("<title>Doc</title>"));
+        // The front matter title wins over the file name
+        let html = HtmlRenderer::new("doc.md").render_standalone("---\ntitle: A & B\n---\n# Hello");
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains(r#"<h1 id="hello">Hello</h1>"#));
+        assert!(html.contains("<style>"));
+        assert!(!html.contains("/assets/github.css"));
+        // Exported pages have no server to reconnect to
+        assert!(html.contains("const LIVE_RELOAD = false;"));
+        assert!(
+            HtmlRenderer::new("Doc")
+                .page("<p>x</p>")
+                .contains("const LIVE_RELOAD = true;")
+        );
+    }
+
+    #[test]
+    fn test_render_combined() {
+        let renderer = HtmlRenderer::new("Docs");
+        let files = vec![
+            (
+                PathBuf::from("README.md"),
+                "# Usage\n\nSee [the API](docs/api.md#usage) and [below](#usage).".to_string(),
+            ),
+            (
+                PathBuf::from("docs/api.md"),
+                "# Usage\n\nBack to [readme](../README.md).".to_string(),
+            ),
+        ];
+        let html = renderer.render_combined(&files);
+
+        // Sections in order, with unique heading ids per file
+        let readme = html.find(r#"<section class="combined-file" id="readme-md">"#);
+        let api = html.find(r#"<section class="combined-file" id="docs-api-md">"#);
+        assert!(readme.unwrap() < api.unwrap());
+        assert!(html.contains(r#"<h1 id="readme-md-usage">"#));
+        assert!(html.contains(r#"<h1 id="docs-api-md-usage">"#));
+
+        // Cross-file and in-page links point to anchors in the page
+        assert!(html.contains(r##"<a href="#docs-api-md-usage">the API</a>"##));
+        assert!(html.contains(r##"<a href="#readme-md-usage">below</a>"##));
+        assert!(html.contains(r##"<a href="#readme-md">readme</a>"##));
+        assert!(!html.contains("loadFile"));
+        assert!(html.contains("const LIVE_RELOAD = false;"));
+
+        // Self-contained: stylesheet is inlined
+        assert!(!html.contains("/assets/github.css"));
+    }
+
     #[test]
     fn test_external_links() {
         let renderer = HtmlRenderer::new("Test");
@@ -404,6 +2013,32 @@ mod tests {
         assert!(result.contains(r#"rel="noopener noreferrer""#));
     }
 
+    #[test]
+    fn test_edit_link() {
+        let renderer = HtmlRenderer::new("Test");
+        assert_eq!(renderer.edit_link("a.md"), "");
+
+        let renderer = renderer.with_edit_url_base(Some("https://example.com/edit/".to_string()));
+        let link = renderer.edit_link("guide.md");
+        assert!(link.contains(r#"href="https://example.com/edit/guide.md""#));
+        assert!(link.contains(r#"target="_blank""#));
+    }
+
+    #[test]
+    fn test_modified_line() {
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_717_243_200);
+        let renderer = HtmlRenderer::new("Test");
+        assert_eq!(renderer.modified_line(Some(modified)), "");
+
+        let renderer = renderer.with_modified(true);
+        assert!(
+            renderer
+                .modified_line(Some(modified))
+                .contains("Last updated: 2024-06-01")
+        );
+        assert_eq!(renderer.modified_line(None), "");
+    }
+
     #[test]
     fn test_heading_with_link() {
         let renderer = HtmlRenderer::new("Test");
@@ -418,6 +2053,225 @@ mod tests {
         assert!(result.contains("</h1>"));
     }
 
+    #[test]
+    fn test_heading_inline_styling() {
+        let renderer = HtmlRenderer::new("Test");
+        let (html, toc) =
+            renderer.render_to_html_string("## A **bold** ~~old~~ *new* `code` [link](x.md)");
+        assert!(html.contains(
+            "<h2 id=\"a-bold-old-new-code-link\">A <strong>bold</strong> <del>old</del> <em>new</em> <code>code</code> <a "
+        ));
+        assert!(html.contains(">link</a></h2>"));
+        assert_eq!(toc[0].text, "A bold old new code link");
+    }
+
+    #[test]
+    fn test_sidebar_item_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("post.md"),
+            "---\ntitle: \"Q&A\"\ntags: [rust, cli]\n---\n# Post",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("plain.md"), "# Plain").unwrap();
+        let tree = FileTree::from_directory(dir.path()).unwrap();
+
+        let html = HtmlRenderer::new("Test").render_with_sidebar("# Post", &tree, None);
+        assert!(html.contains(r#"data-path="post.md" data-tags="rust,cli" data-title="Q&amp;A""#));
+        assert!(html.contains(r#"data-path="plain.md" onclick"#));
+    }
+
+    #[test]
+    fn test_sidebar_recent_badge() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("new.md"), "# New").unwrap();
+        std::fs::write(dir.path().join("old.md"), "# Old").unwrap();
+        let mut tree = FileTree::from_directory(dir.path()).unwrap();
+        for file in &mut tree.files {
+            if file.name == "old" {
+                file.modified = Some(SystemTime::now() - Duration::from_secs(10 * 86_400));
+            }
+        }
+
+        // Off by default
+        let html = HtmlRenderer::new("Test").render_with_sidebar("", &tree, None);
+        assert!(!html.contains("root-item recent"));
+        assert!(!html.contains(&format!("new</span>{}", RECENT_BADGE)));
+
+        let html = HtmlRenderer::new("Test")
+            .with_since(Some(Duration::from_secs(7 * 86_400)))
+            .render_with_sidebar("", &tree, None);
+        assert!(html.contains(&format!("new</span>{}", RECENT_BADGE)));
+        assert!(!html.contains(&format!("old</span>{}", RECENT_BADGE)));
+        assert!(html.contains(r#"class="sidebar-item root-item recent" data-path="new.md""#));
+        assert!(html.contains(r#"class="sidebar-item root-item" data-path="old.md""#));
+    }
+
+    #[test]
+    fn test_ordered_list_start_near_footnotes_and_headings() {
+        let markdown = "# Title\n\n3. three[^1]\n4. four\n\n   [^1]: nested note\n\n      7. seven\n5. five\n\n## Heading\n\n10. ten\n11. eleven[^2]\n\n[^2]: ## Note heading\n\n    5. five\n    6. six\n\n12. twelve\n";
+        let (html, toc) = HtmlRenderer::new("Test").render_to_html_string(markdown);
+        let (main, footnotes) = html.split_once(r#"<section class="footnotes">"#).unwrap();
+
+        // Lists keep their start and stay in source order around headings
+        let starts: Vec<_> = main.match_indices("<ol start=").map(|(i, _)| i).collect();
+        assert_eq!(starts.len(), 4);
+        let order = [
+            r#"<h1 id="title">"#,
+            r#"<ol start="3">"#,
+            r#"<ol start="7">"#,
+            "<p>five</p>",
+            r#"<h2 id="heading">"#,
+            r#"<ol start="10">"#,
+            r#"<ol start="12">"#,
+        ];
+        let positions: Vec<_> = order.iter().map(|s| main.find(s).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", main);
+
+        // A heading inside a footnote stays with its list, outside the TOC
+        assert!(!main.contains("Note heading"));
+        assert!(
+            footnotes.contains(
+                "Note heading</h2>\n<ol start=\"5\">\n<li>five</li>\n<li>six</li>\n</ol>"
+            )
+        );
+        assert_eq!(
+            toc.iter().map(|e| e.text.as_str()).collect::<Vec<_>>(),
+            ["Title", "Heading"]
+        );
+    }
+
+    #[test]
+    fn test_inline_code_languages() {
+        let markdown = "# Use `x`{.rust}\n\nCall `a < b`{.rust}, then `plain` and `f()`{.c++}.\n";
+
+        let html = HtmlRenderer::new("Test").render_content(markdown);
+        assert!(html.contains(r#"<h1 id="use-x">Use <code>x</code></h1>"#));
+        assert!(html.contains(
+            r#"Call <code class="language-rust">a &lt; b</code>, then <code>plain</code> and <code class="language-c++">f()</code>."#
+        ));
+
+        // A default language applies to spans without an attribute
+        let html = HtmlRenderer::new("Test")
+            .with_inline_code_lang(Some("python".to_string()))
+            .render_content(markdown);
+        assert!(html.contains(r#"then <code class="language-python">plain</code>"#));
+        assert!(html.contains(r#"<code class="language-rust">a &lt; b</code>"#));
+
+        // Without either, inline code stays plain
+        let html = HtmlRenderer::new("Test").render_content("Some `code` {.rust}");
+        assert!(html.contains("<code>code</code> {.rust}"));
+    }
+
+    #[test]
+    fn test_head_extra() {
+        let marker = r#"<meta name="head-extra-marker">"#;
+        let renderer = HtmlRenderer::new("Test").with_head_extra(marker.to_string());
+
+        let html = renderer.render_standalone("# Title");
+        let head = &html[..html.find("</head>").unwrap()];
+        assert!(head.contains(marker));
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        std::fs::write(dir.path().join("b.md"), "# B").unwrap();
+        let tree = FileTree::from_directory(dir.path()).unwrap();
+        let html = renderer.render_with_sidebar("# A", &tree, Some("a.md"));
+        let head = &html[..html.find("</head>").unwrap()];
+        assert!(head.contains(marker));
+
+        // Nothing is left of the placeholder by default
+        let html = HtmlRenderer::new("Test").render("# Title");
+        assert!(!html.contains("{{HEAD_EXTRA}}"));
+    }
+
+    #[test]
+    fn test_custom_css() {
+        let renderer =
+            HtmlRenderer::new("Test").with_custom_css(".markdown-body { color: red; }".to_string());
+
+        // Linked after the built-in stylesheet so it overrides it
+        let html = renderer.render("# Title");
+        let base = html.find("/assets/github.css").unwrap();
+        let custom = html.find(r#"<link rel="stylesheet" href="/assets/custom.css">"#);
+        assert!(base < custom.unwrap());
+
+        let html = renderer.render_standalone("# Title");
+        assert!(!html.contains("/assets/custom.css"));
+        assert!(html.contains("<style>\n.markdown-body { color: red; }\n</style>"));
+
+        let html = HtmlRenderer::new("Test").render("# Title");
+        assert!(!html.contains("/assets/custom.css"));
+    }
+
+    #[test]
+    fn test_base_url() {
+        let markdown = "[x](/a) [y](//cdn.example.com/y) [z](https://example.com/z) [w](#w) [v](b.md)\n\n![img](/img/logo.png)\n";
+
+        let html = HtmlRenderer::new("Test")
+            .with_base_url(Some("/docs/".to_string()))
+            .render_content(markdown);
+        assert!(html.contains(r#"<a href="/docs/a">x</a>"#));
+        assert!(html.contains(r#"href="//cdn.example.com/y""#));
+        assert!(html.contains(r#"href="https://example.com/z""#));
+        assert!(html.contains(r##"href="#w""##));
+        assert!(html.contains(r#"src="/docs/img/logo.png""#));
+
+        // Without a base URL links are unchanged
+        let html = HtmlRenderer::new("Test").render_content(markdown);
+        assert!(html.contains(r#"<a href="/a">x</a>"#));
+    }
+
+    #[test]
+    fn test_footnote_modes() {
+        let markdown = "Claim[^1] and again[^1].\n\n[^1]: A <b>`source`</b>\n    \"quoted\".\n";
+
+        let section = HtmlRenderer::new("Test").render_content(markdown);
+        assert!(section.contains(r##"<sup class="footnote-reference"><a href="#1">1</a></sup>"##));
+        assert!(section.contains(r#"<section class="footnotes">"#));
+
+        let inline = HtmlRenderer::new("Test")
+            .with_footnotes(FootnoteMode::Inline)
+            .render_content(markdown);
+        assert!(inline.contains(
+            r#"Claim <span class="footnote-inline">(A source "quoted".)</span> and again <span class="footnote-inline">"#
+        ));
+        assert!(!inline.contains("footnote-reference"));
+        assert!(!inline.contains(r#"<section class="footnotes">"#));
+
+        let hover = HtmlRenderer::new("Test")
+            .with_footnotes(FootnoteMode::Hover)
+            .render_content(markdown);
+        assert_eq!(
+            hover
+                .matches(r##"<sup class="footnote-reference"><a href="#1" title="A source &quot;quoted&quot;.">1</a></sup>"##)
+                .count(),
+            2
+        );
+        assert!(hover.contains(r#"<section class="footnotes">"#));
+    }
+
+    #[test]
+    fn test_landing_page() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("my notes")).unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        std::fs::write(
+            dir.path().join("my notes/b&c.md"),
+            "---\ntitle: Bees\n---\n# B",
+        )
+        .unwrap();
+        let tree = FileTree::from_directory(dir.path()).unwrap();
+
+        let html = HtmlRenderer::new("Docs").landing_page(&tree);
+        assert!(html.starts_with("<div class=\"landing-page\">\n<h1>Docs</h1>\n<ul>\n"));
+        assert!(html.contains(r#"<li><a href="/view?file=a.md">a</a></li>"#));
+        assert!(html.contains("<h2>my notes/</h2>"));
+        assert!(html.contains(
+            r#"<li><a href="/view?file=my%20notes/b%26c.md">Bees</a> <code>my notes/b&amp;c.md</code></li>"#
+        ));
+    }
+
     #[test]
     fn test_md_links() {
         let renderer = HtmlRenderer::new("Test");
@@ -433,6 +2287,57 @@ mod tests {
         assert!(result.contains(r#"target="_blank""#));
     }
 
+    #[test]
+    fn test_crlf_and_bom_input() {
+        let renderer = HtmlRenderer::new("Test");
+        let result = renderer.render_content("\u{feff}# Title\r\n\r\n```\r\ncode\r\n```\r\n");
+        assert!(result.contains(r#"<h1 id="title">"#));
+        assert!(!result.contains('\r'));
+        assert!(!result.contains('\u{feff}'));
+    }
+
+    #[test]
+    fn test_duplicate_footnote_keeps_first() {
+        let renderer = HtmlRenderer::new("Test");
+        let result = renderer.render_content("Text[^a].\n\n[^a]: First.\n\n[^a]: Second.\n");
+        assert!(result.contains("First."));
+        assert!(!result.contains("Second."));
+    }
+
+    #[test]
+    fn test_source_lines() {
+        let markdown = "---\ntitle: T\n---\n# Title\n\nText\nmore\n\n- a\n- b\n\n```mermaid\ngraph TD\n```\n\n***\n";
+        let result = HtmlRenderer::new("Test")
+            .with_source_lines(true)
+            .render_content(markdown);
+        assert!(result.contains(r#"<h1 id="title" data-source-line="4">Title</h1>"#));
+        assert!(result.contains(r#"<p data-source-line="6">Text"#));
+        assert!(result.contains(r#"<ul data-source-line="9">"#));
+        assert!(result.contains(
+            r#"<div class="diagram-container mermaid-container" data-source-line="12">"#
+        ));
+        assert!(result.contains(r#"<hr data-source-line="16" />"#));
+        assert!(!result.contains("source-line:"));
+
+        let result = HtmlRenderer::new("Test").render_content(markdown);
+        assert!(!result.contains("data-source-line"));
+    }
+
+    #[test]
+    fn test_source_lines_ignore_raw_html() {
+        // Comments in the markdown are left alone, and tables keep their alignment
+        let markdown = "<!--source-line:99-->\n\n| a | b |\n|---|--:|\n| 1 | 2 |\n\nText <!--source-line:98--> <b>x</b>\n";
+        let result = HtmlRenderer::new("Test")
+            .with_source_lines(true)
+            .render_content(markdown);
+        assert!(result.starts_with("<!--source-line:99-->\n<table data-source-line=\"3\">"));
+        assert!(result.contains(r#"<td style="text-align: right">2</td>"#));
+        assert!(
+            result.contains(r#"<p data-source-line="7">Text <!--source-line:98--> <b>x</b></p>"#)
+        );
+        assert_eq!(result.matches("data-source-line").count(), 2);
+    }
+
     #[test]
     fn test_anchor_links() {
         let renderer = HtmlRenderer::new("Test");
@@ -443,6 +2348,31 @@ mod tests {
         assert!(!result.contains("onclick"));
     }
 
+    #[test]
+    fn test_graphviz_diagram() {
+        let renderer = HtmlRenderer::new("Test");
+        for language in ["dot", "graphviz"] {
+            let html = renderer
+                .render_content(&format!("```{}\ndigraph {{ a -> \"b&c\" }}\n```", language));
+            assert!(html.contains(r#"<div class="diagram-container graphviz-container">"#));
+            assert!(html.contains("<span>Graphviz Diagram</span>"));
+            assert!(html.contains(r#"<pre class="graphviz">digraph { a -&gt; "b&amp;c" }</pre>"#));
+        }
+
+        // Raw HTML that looks like a rendered diagram block is left alone
+        let raw = "<pre><code class=\"language-dot\">digraph {}</code></pre>\n";
+        assert_eq!(renderer.render_content(raw), raw);
+
+        // viz.js is only loaded with --diagrams
+        let page = renderer.render("```dot\ndigraph {}\n```");
+        assert!(!page.contains("viz-standalone.js"));
+        assert!(!page.contains("{{DIAGRAM_SCRIPTS}}"));
+        let page = renderer
+            .with_diagrams(true)
+            .render("```dot\ndigraph {}\n```");
+        assert!(page.contains("viz-standalone.js"));
+    }
+
     #[test]
     fn test_mermaid_special_characters() {
         let renderer = HtmlRenderer::new("Test");
@@ -492,4 +2422,14 @@ graph TD
             }
         }
     }
+
+    #[test]
+    fn test_encode_query_value() {
+        assert_eq!(encode_query_value("docs/guide.md"), "docs/guide.md");
+        assert_eq!(
+            encode_query_value("my notes/a&b #1.md"),
+            "my%20notes/a%26b%20%231.md"
+        );
+        assert_eq!(encode_query_value("日.md"), "%E6%97%A5.md");
+    }
 }
@@ -0,0 +1,209 @@
+//! Best-effort conversion of simple TeX math to Unicode for the terminal
+
+/// Greek letters and common symbols by TeX command name
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("infty", "∞"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("times", "×"),
+    ("div", "÷"),
+    ("cdot", "·"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("iff", "⇔"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("ldots", "…"),
+    ("cdots", "⋯"),
+];
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('=', '⁼'),
+    ('(', '⁽'),
+    (')', '⁾'),
+    ('i', 'ⁱ'),
+    ('n', 'ⁿ'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+    ('=', '₌'),
+    ('(', '₍'),
+    (')', '₎'),
+    ('a', 'ₐ'),
+    ('e', 'ₑ'),
+    ('i', 'ᵢ'),
+    ('j', 'ⱼ'),
+    ('k', 'ₖ'),
+    ('n', 'ₙ'),
+    ('o', 'ₒ'),
+    ('x', 'ₓ'),
+];
+
+/// Translate simple TeX such as `x^2`, `a_{n+1}` or `\alpha \leq \pi` to Unicode.
+/// `None` when the expression uses anything without a Unicode equivalent here
+/// (fractions, roots, unknown commands, scripts of other characters).
+pub fn tex_to_unicode(tex: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = tex.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                    name.push(c);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    // Escaped punctuation and spacing commands
+                    match chars.next()? {
+                        ',' | ';' | ' ' => result.push(' '),
+                        c @ ('{' | '}' | '%' | '$' | '#' | '&' | '_') => result.push(c),
+                        _ => return None,
+                    }
+                } else {
+                    let (_, symbol) = SYMBOLS.iter().find(|(command, _)| *command == name)?;
+                    result.push_str(symbol);
+                }
+            }
+            '^' | '_' => {
+                let table = if c == '^' { SUPERSCRIPTS } else { SUBSCRIPTS };
+                let script = match chars.next()? {
+                    '{' => {
+                        let mut group = String::new();
+                        loop {
+                            match chars.next()? {
+                                '}' => break,
+                                c => group.push(c),
+                            }
+                        }
+                        group
+                    }
+                    c => c.to_string(),
+                };
+                for c in script.chars() {
+                    let (_, mapped) = table.iter().find(|(from, _)| *from == c)?;
+                    result.push(*mapped);
+                }
+            }
+            // Braces only group
+            '{' | '}' => {}
+            c if c.is_alphanumeric() || c.is_whitespace() || "+-=<>()[],.;:/|'!*".contains(c) => {
+                result.push(c)
+            }
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tex_to_unicode() {
+        assert_eq!(tex_to_unicode("x^2").as_deref(), Some("x²"));
+        assert_eq!(tex_to_unicode("x_1").as_deref(), Some("x₁"));
+        assert_eq!(
+            tex_to_unicode("a_{n+1} = a_n^{2}").as_deref(),
+            Some("aₙ₊₁ = aₙ²")
+        );
+        assert_eq!(
+            tex_to_unicode(r"\alpha + \beta \leq \pi").as_deref(),
+            Some("α + β ≤ π")
+        );
+        assert_eq!(
+            tex_to_unicode(r"\sum_{i=0}^n i").as_deref(),
+            Some("∑ᵢ₌₀ⁿ i")
+        );
+    }
+
+    #[test]
+    fn test_tex_to_unicode_unsupported() {
+        assert_eq!(tex_to_unicode(r"\frac{a}{b}"), None);
+        assert_eq!(tex_to_unicode(r"\sqrt{x}"), None);
+        // No Unicode superscript for `y`
+        assert_eq!(tex_to_unicode("x^y"), None);
+        assert_eq!(tex_to_unicode("x^{2"), None);
+        assert_eq!(tex_to_unicode("a & b"), None);
+    }
+}
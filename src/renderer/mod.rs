@@ -1,2 +1,40 @@
 pub mod html;
+pub mod math;
 pub mod terminal;
+
+/// Diagram languages drawn from fenced code blocks instead of shown as code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramKind {
+    /// ```` ```mermaid ````, rendered in the browser by mermaid.js
+    Mermaid,
+    /// ```` ```dot ```` or ```` ```graphviz ````, rendered in the browser by viz.js
+    /// when enabled with `--diagrams`
+    Graphviz,
+}
+
+impl DiagramKind {
+    /// The diagram kind of a code block's language, if it is one
+    pub fn from_language(language: &str) -> Option<Self> {
+        match language {
+            "mermaid" => Some(DiagramKind::Mermaid),
+            "dot" | "graphviz" => Some(DiagramKind::Graphviz),
+            _ => None,
+        }
+    }
+
+    /// Short lowercase name, used as the HTML class the page script looks for
+    pub fn name(self) -> &'static str {
+        match self {
+            DiagramKind::Mermaid => "mermaid",
+            DiagramKind::Graphviz => "graphviz",
+        }
+    }
+
+    /// Label shown above the diagram
+    pub fn title(self) -> &'static str {
+        match self {
+            DiagramKind::Mermaid => "Mermaid Diagram",
+            DiagramKind::Graphviz => "Graphviz Diagram",
+        }
+    }
+}
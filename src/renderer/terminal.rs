@@ -1,15 +1,22 @@
 use crossterm::execute;
 use crossterm::style::{Attribute, Color, ResetColor, SetAttribute, SetForegroundColor};
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::time::SystemTime;
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{Style, ThemeSet};
+use syntect::highlighting::{Style, Theme, ThemeSet};
 use syntect::parsing::SyntaxSet;
 use syntect::util::as_24_bit_terminal_escaped;
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+use crate::error::{Error, Result};
+use crate::files::format_date;
 use crate::parser::{
-    Alignment, Document, Element, InlineElement, ListItem, TocEntry, generate_toc,
+    AlertKind, Alignment, Document, Element, FootnoteMode, FrontMatter, FrontMatterMode,
+    InlineElement, ListItem, TocEntry, generate_toc, inline_text,
 };
+use crate::renderer::DiagramKind;
+use crate::renderer::math::tex_to_unicode;
 
 /// Tracks the current text style state for proper nesting
 #[derive(Clone, Default, PartialEq)]
@@ -27,7 +34,7 @@ impl StyleState {
     fn apply_fresh<W: Write>(&self, out: &mut W) -> io::Result<()> {
         // First, explicitly clear all style attributes to ensure a clean slate
         // This prevents any previously set terminal styles from leaking through
-        execute!(out, SetAttribute(Attribute::NoBold))?;
+        execute!(out, SetAttribute(Attribute::NormalIntensity))?;
         execute!(out, SetAttribute(Attribute::NoItalic))?;
         execute!(out, SetAttribute(Attribute::NotCrossedOut))?;
         execute!(out, SetAttribute(Attribute::NoUnderline))?;
@@ -59,7 +66,8 @@ impl StyleState {
             if self.bold {
                 execute!(out, SetAttribute(Attribute::Bold))?;
             } else {
-                execute!(out, SetAttribute(Attribute::NoBold))?;
+                // SGR 22; SGR 21 (`NoBold`) means double underline on many terminals
+                execute!(out, SetAttribute(Attribute::NormalIntensity))?;
             }
         }
 
@@ -90,52 +98,737 @@ impl StyleState {
             }
         }
 
-        // Handle color
+        // Handle color; `ResetColor` would also clear the attributes set above
         if self.color != from.color {
-            if let Some(color) = self.color {
-                execute!(out, SetForegroundColor(color))?;
-            } else {
-                execute!(out, ResetColor)?;
-            }
+            execute!(out, SetForegroundColor(self.color.unwrap_or(Color::Reset)))?;
         }
 
         Ok(())
     }
 }
 
+/// Horizontal placement of the content column within the terminal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContentAlign {
+    /// Content starts at the left edge
+    #[default]
+    Left,
+    /// Content column is centered in the terminal
+    Center,
+}
+
+/// Decoration around code blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CodeStyle {
+    /// Full box with the language in a header row
+    #[default]
+    Boxed,
+    /// Bar down the left side only
+    Bordered,
+    /// Language label above undecorated code
+    Minimal,
+    /// Only the code
+    Plain,
+}
+
+impl CodeStyle {
+    /// Style used without `--code-style`: boxed on a terminal, plain when the output
+    /// is piped so the code can be copied as is
+    pub fn default_for(interactive: bool) -> Self {
+        if interactive {
+            CodeStyle::Boxed
+        } else {
+            CodeStyle::Plain
+        }
+    }
+}
+
+/// One heading of a document outline
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineEntry {
+    pub level: u8,
+    pub text: String,
+    /// 1-based line of the heading in the rendered document (as shown in the pager)
+    pub line: usize,
+    /// Text of the paragraph directly below the heading
+    pub excerpt: Option<String>,
+}
+
+/// Prefix for one heading level; its color comes from the [`TerminalTheme`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadingStyle {
+    pub prefix: String,
+}
+
+/// How horizontal rules are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RuleStyle {
+    /// A heavy line across the content width
+    #[default]
+    Heavy,
+    /// A light line across the content width
+    Light,
+    /// A centered `* * *`
+    Dots,
+}
+
+/// Decorations used by the terminal renderer, configurable via the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalStyle {
+    /// Styles for heading levels 1 through 6
+    pub headings: [HeadingStyle; 6],
+    /// Unordered list bullets by nesting depth, cycling for deeper levels
+    pub bullets: Vec<String>,
+    /// Horizontal rule style
+    pub rule: RuleStyle,
+}
+
+impl Default for TerminalStyle {
+    fn default() -> Self {
+        let heading = |prefix: &str| HeadingStyle {
+            prefix: prefix.to_string(),
+        };
+        Self {
+            headings: [
+                heading("█ "),
+                heading("▓ "),
+                heading("▒ "),
+                heading("░ "),
+                heading("• "),
+                heading("· "),
+            ],
+            bullets: vec!["•".to_string(), "◦".to_string(), "▪".to_string()],
+            rule: RuleStyle::default(),
+        }
+    }
+}
+
+impl TerminalStyle {
+    fn heading(&self, level: u8) -> &HeadingStyle {
+        &self.headings[(level.clamp(1, 6) - 1) as usize]
+    }
+
+    fn bullet(&self, depth: usize) -> &str {
+        if self.bullets.is_empty() {
+            return "•";
+        }
+        &self.bullets[depth % self.bullets.len()]
+    }
+}
+
+/// Colors used by the terminal renderer, from a built-in palette with overrides
+/// from the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalTheme {
+    /// Heading colors for levels 1 through 6
+    pub headings: [Color; 6],
+    /// Document title in the header line and blockquote text
+    pub text: Color,
+    /// Secondary text: link URLs, raw HTML, dates and hints
+    pub muted: Color,
+    /// Box, table and quote borders and horizontal rules
+    pub border: Color,
+    /// List bullets, table headers, code labels and footnote markers
+    pub accent: Color,
+    pub link: Color,
+    /// Inline code
+    pub code: Color,
+    pub math: Color,
+    /// Tags, images and diagram titles
+    pub label: Color,
+    /// Checked task list markers
+    pub checked: Color,
+    /// Alert bars and titles: note, tip, important, warning, caution
+    pub alerts: [Color; 5],
+}
+
+impl TerminalTheme {
+    /// Bright colors for dark backgrounds
+    pub fn dark() -> Self {
+        Self {
+            headings: [
+                Color::Magenta,
+                Color::Cyan,
+                Color::Blue,
+                Color::Green,
+                Color::Yellow,
+                Color::White,
+            ],
+            text: Color::White,
+            muted: Color::DarkGrey,
+            border: Color::DarkGrey,
+            accent: Color::Cyan,
+            link: Color::Blue,
+            code: Color::Yellow,
+            math: Color::Green,
+            label: Color::Magenta,
+            checked: Color::Green,
+            alerts: [
+                Color::Blue,
+                Color::Green,
+                Color::Magenta,
+                Color::Yellow,
+                Color::Red,
+            ],
+        }
+    }
+
+    /// Darker colors that stay readable on light backgrounds
+    pub fn light() -> Self {
+        Self {
+            headings: [
+                Color::DarkMagenta,
+                Color::DarkCyan,
+                Color::DarkBlue,
+                Color::DarkGreen,
+                Color::DarkYellow,
+                Color::Black,
+            ],
+            text: Color::Black,
+            muted: Color::DarkGrey,
+            border: Color::DarkGrey,
+            accent: Color::DarkCyan,
+            link: Color::DarkBlue,
+            code: Color::DarkRed,
+            math: Color::DarkGreen,
+            label: Color::DarkMagenta,
+            checked: Color::DarkGreen,
+            alerts: [
+                Color::DarkBlue,
+                Color::DarkGreen,
+                Color::DarkMagenta,
+                Color::DarkYellow,
+                Color::DarkRed,
+            ],
+        }
+    }
+
+    /// Palette for a `--theme` value: light for `light`, dark otherwise
+    pub fn named(theme: &str) -> Self {
+        if theme == "light" {
+            Self::light()
+        } else {
+            Self::dark()
+        }
+    }
+
+    fn heading(&self, level: u8) -> Color {
+        self.headings[(level.clamp(1, 6) - 1) as usize]
+    }
+
+    fn alert(&self, kind: AlertKind) -> Color {
+        self.alerts[kind as usize]
+    }
+}
+
+/// Code blocks with a line longer than this (in bytes) are not syntax highlighted;
+/// highlighting a minified file's single huge line takes far too long
+const MAX_HIGHLIGHT_LINE_LEN: usize = 10_000;
+
+/// Decorative characters drawn by the renderer
+struct Glyphs {
+    horizontal: &'static str,
+    heavy: &'static str,
+    vertical: &'static str,
+    top_left: &'static str,
+    top_right: &'static str,
+    bottom_left: &'static str,
+    bottom_right: &'static str,
+    tee_right: &'static str,
+    tee_left: &'static str,
+    tee_down: &'static str,
+    tee_up: &'static str,
+    cross: &'static str,
+    /// Marks alignment in a table header separator, like the colons in `:--:`
+    align_marker: char,
+    quote_bar: &'static str,
+    toc_title: &'static str,
+    toc_bullets: [&'static str; 4],
+    task_done: &'static str,
+    task_open: &'static str,
+    image: &'static str,
+    /// Icon before a diagram placeholder's title
+    mermaid_icon: &'static str,
+    graphviz_icon: &'static str,
+    /// Icon before an alert's title, by kind
+    alert_icons: [&'static str; 5],
+    /// Marks text cut short to fit
+    ellipsis: &'static str,
+}
+
+const UNICODE_GLYPHS: Glyphs = Glyphs {
+    horizontal: "─",
+    heavy: "━",
+    vertical: "│",
+    top_left: "┌",
+    top_right: "┐",
+    bottom_left: "└",
+    bottom_right: "┘",
+    tee_right: "├",
+    tee_left: "┤",
+    tee_down: "┬",
+    tee_up: "┴",
+    cross: "┼",
+    align_marker: '━',
+    quote_bar: "  ▌ ",
+    toc_title: "📑 Table of Contents",
+    toc_bullets: ["●", "○", "◆", "◇"],
+    task_done: "☑",
+    task_open: "☐",
+    image: "🖼",
+    mermaid_icon: "🧜",
+    graphviz_icon: "◇",
+    alert_icons: ["ℹ️", "💡", "❗", "⚠️", "🛑"],
+    ellipsis: "…",
+};
+
+const ASCII_GLYPHS: Glyphs = Glyphs {
+    horizontal: "-",
+    heavy: "=",
+    vertical: "|",
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    tee_right: "+",
+    tee_left: "+",
+    tee_down: "+",
+    tee_up: "+",
+    cross: "+",
+    align_marker: ':',
+    quote_bar: "  > ",
+    toc_title: "[TOC] Table of Contents",
+    toc_bullets: ["*", "-", "+", "-"],
+    task_done: "[x]",
+    task_open: "[ ]",
+    image: "[image]",
+    mermaid_icon: "[mermaid]",
+    graphviz_icon: "[dot]",
+    alert_icons: ["(i)", "(*)", "(!)", "/!\\", "(x)"],
+    ellipsis: "...",
+};
+
+impl Glyphs {
+    /// Title of a diagram placeholder box
+    fn diagram_title(&self, kind: DiagramKind) -> String {
+        let icon = match kind {
+            DiagramKind::Mermaid => self.mermaid_icon,
+            DiagramKind::Graphviz => self.graphviz_icon,
+        };
+        format!("{} {}", icon, kind.title())
+    }
+
+    /// Icon and title of an alert
+    fn alert_title(&self, kind: AlertKind) -> String {
+        format!("{} {}", self.alert_icons[kind as usize], kind.label())
+    }
+}
+
+/// Terminal width from the detected size, then `COLUMNS`, then 80 columns,
+/// and whether it was detected
+fn screen_width(detected: Option<u16>, columns: Option<&str>) -> (usize, bool) {
+    match detected.filter(|&width| width > 0) {
+        Some(width) => (width as usize, true),
+        None => (
+            columns
+                .and_then(|columns| columns.trim().parse().ok())
+                .filter(|&width| width > 0)
+                .unwrap_or(80),
+            false,
+        ),
+    }
+}
+
 pub struct TerminalRenderer {
-    theme: String,
+    /// Name of the syntect theme for code blocks
+    syntax_theme: String,
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
+    /// Width available for content (capped by `with_width`)
     term_width: usize,
+    /// Width of the terminal itself
+    screen_width: usize,
+    /// `screen_width` was read from the terminal rather than `COLUMNS` or the default
+    screen_detected: bool,
+    /// Wrap paragraphs at `term_width` (enabled when a max width is set)
+    wrap: bool,
+    align: ContentAlign,
+    code_style: CodeStyle,
+    /// Emit ANSI colors and styles (disabled for plain output)
+    color: bool,
+    /// Highlighting language for code blocks without one (e.g. indented blocks)
+    default_lang: Option<String>,
+    style: TerminalStyle,
+    colors: TerminalTheme,
+    ascii: bool,
+    header: Option<String>,
+    /// Last-modified time shown near the title
+    modified: Option<SystemTime>,
+    frontmatter: FrontMatterMode,
+    footnotes: FootnoteMode,
 }
 
 impl TerminalRenderer {
     pub fn new(theme: &str) -> Self {
         let syntax_set = SyntaxSet::load_defaults_newlines();
         let theme_set = ThemeSet::load_defaults();
-        let term_width = crossterm::terminal::size()
-            .map(|(w, _)| w as usize)
-            .unwrap_or(80);
+        let (term_width, screen_detected) = screen_width(
+            crossterm::terminal::size().ok().map(|(w, _)| w),
+            std::env::var("COLUMNS").ok().as_deref(),
+        );
+
+        let syntax_theme = if theme == "light" {
+            "base16-ocean.light"
+        } else {
+            "base16-ocean.dark"
+        };
 
         Self {
-            theme: theme.to_string(),
+            syntax_theme: syntax_theme.to_string(),
             syntax_set,
             theme_set,
             term_width,
+            screen_width: term_width,
+            screen_detected,
+            wrap: false,
+            align: ContentAlign::Left,
+            code_style: CodeStyle::Boxed,
+            color: true,
+            default_lang: None,
+            style: TerminalStyle::default(),
+            colors: TerminalTheme::named(theme),
+            ascii: false,
+            header: None,
+            modified: None,
+            frontmatter: FrontMatterMode::Hide,
+            footnotes: FootnoteMode::Section,
+        }
+    }
+
+    /// Cap the content width (clamped to the terminal width) and wrap paragraphs to it.
+    /// Without a detected terminal the width is used as given.
+    pub fn with_width(mut self, width: Option<usize>) -> Self {
+        if let Some(width) = width {
+            if !self.screen_detected {
+                self.screen_width = width.max(1);
+            }
+            self.term_width = width.clamp(1, self.screen_width.max(1));
+            self.wrap = true;
+        }
+        self
+    }
+
+    /// Set how the content column is placed when it is narrower than the terminal
+    pub fn with_align(mut self, align: ContentAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Set the decoration drawn around code blocks
+    pub fn with_code_style(mut self, code_style: CodeStyle) -> Self {
+        self.code_style = code_style;
+        self
+    }
+
+    /// Set how the document's front matter is shown
+    pub fn with_frontmatter(mut self, frontmatter: FrontMatterMode) -> Self {
+        self.frontmatter = frontmatter;
+        self
+    }
+
+    /// Set where footnote text goes; `Hover` is shown as a section in the terminal
+    pub fn with_footnotes(mut self, footnotes: FootnoteMode) -> Self {
+        self.footnotes = footnotes;
+        self
+    }
+
+    /// Enable or disable ANSI colors and styles in the output
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Highlight unlabeled and indented code blocks as `lang` (plain text when `None`)
+    pub fn with_default_lang(mut self, lang: Option<String>) -> Self {
+        self.default_lang = lang;
+        self
+    }
+
+    /// Use custom heading prefixes, colors, and list bullets
+    pub fn with_style(mut self, style: TerminalStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Highlight code blocks with the named syntect theme instead of the one
+    /// picked from the theme name; an unknown name keeps it, with a warning
+    pub fn with_syntax_theme(mut self, name: Option<String>) -> Self {
+        if let Some(name) = name {
+            if self.theme_set.themes.contains_key(&name) {
+                self.syntax_theme = name;
+            } else {
+                crate::warning!(
+                    "Warning: unknown syntax theme '{}', using {} (see --list-syntax-themes)",
+                    name,
+                    self.syntax_theme
+                );
+            }
+        }
+        self
+    }
+
+    /// Names of the syntax highlighting themes `with_syntax_theme` accepts, sorted
+    pub fn syntax_themes() -> Vec<String> {
+        ThemeSet::load_defaults().themes.into_keys().collect()
+    }
+
+    /// Replace the color palette picked from the theme name
+    pub fn with_colors(mut self, colors: TerminalTheme) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Draw only ASCII decorations, for terminals without box-drawing or emoji glyphs
+    pub fn with_ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Show a header line with `label` (usually the file path) and the document title
+    pub fn with_header(mut self, label: Option<String>) -> Self {
+        self.header = label;
+        self
+    }
+
+    /// Show a dim "Last updated" line with the date of `modified`
+    pub fn with_modified(mut self, modified: Option<SystemTime>) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    fn glyphs(&self) -> &'static Glyphs {
+        if self.ascii {
+            &ASCII_GLYPHS
+        } else {
+            &UNICODE_GLYPHS
+        }
+    }
+
+    /// Heading color and prefix; in ASCII mode non-ASCII prefixes fall back to `#` markers
+    fn heading_style(&self, level: u8) -> (Color, String) {
+        let heading = self.style.heading(level);
+        let color = self.colors.heading(level);
+        if self.ascii && !heading.prefix.is_ascii() {
+            let level = level.clamp(1, 6) as usize;
+            (color, format!("{} ", "#".repeat(level)))
+        } else {
+            (color, heading.prefix.clone())
         }
     }
 
-    pub fn render(&self, document: &Document, show_toc: bool) -> io::Result<()> {
+    /// Unordered list bullet; in ASCII mode non-ASCII bullets fall back to `*` and `-`
+    fn list_bullet(&self, depth: usize) -> &str {
+        let bullet = self.style.bullet(depth);
+        if self.ascii && !bullet.is_ascii() {
+            if depth % 2 == 0 { "*" } else { "-" }
+        } else {
+            bullet
+        }
+    }
+
+    /// Left margin applied to every output line
+    fn margin(&self) -> usize {
+        match self.align {
+            ContentAlign::Left => 0,
+            ContentAlign::Center => self.screen_width.saturating_sub(self.term_width) / 2,
+        }
+    }
+
+    pub fn render(&self, document: &Document, show_toc: bool) -> Result<()> {
         self.render_to_writer(&mut io::stdout(), document, show_toc)
     }
 
+    /// Render a document into a `String` (useful for tests and embedding)
+    pub fn render_to_string(&self, document: &Document, show_toc: bool) -> Result<String> {
+        let mut buffer = Vec::new();
+        self.render_to_writer(&mut buffer, document, show_toc)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+
+    /// Headings of the document with the line each starts on when rendered with the
+    /// same options. A heading that cannot be found keeps the previous heading's line.
+    pub fn outline(&self, document: &Document, show_toc: bool) -> Result<Vec<OutlineEntry>> {
+        let rendered = strip_ansi(&self.render_to_string(document, show_toc)?);
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        // Top-level headings in document order
+        let headings = document
+            .elements
+            .iter()
+            .enumerate()
+            .filter_map(|(index, element)| match element {
+                Element::Heading { level, content } => Some((index, *level, content)),
+                _ => None,
+            });
+
+        let mut next = 0;
+        let mut entries = Vec::new();
+        for (index, level, text) in headings {
+            let (_, prefix) = self.heading_style(level);
+            let heading = format!("{}{}", prefix, text);
+            if let Some(offset) = lines[next..]
+                .iter()
+                .position(|line| line.trim() == heading.trim())
+            {
+                next += offset + 1;
+            }
+
+            let excerpt = match document.elements.get(index + 1) {
+                Some(Element::Paragraph { content }) => {
+                    let text = inline_text(content);
+                    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+                        .filter(|text| !text.is_empty())
+                }
+                _ => None,
+            };
+
+            entries.push(OutlineEntry {
+                level,
+                text: text.clone(),
+                line: next.max(1),
+                excerpt,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Print the outline: line numbers, headings indented by level, and a one-line
+    /// excerpt under each heading
+    pub fn render_outline<W: Write>(
+        &self,
+        out: &mut W,
+        document: &Document,
+        show_toc: bool,
+    ) -> Result<()> {
+        let entries = self.outline(document, show_toc)?;
+        let mut buffer = Vec::new();
+        self.write_outline(&mut buffer, &entries)
+            .map_err(Error::Render)?;
+        self.write_output(out, &buffer)
+    }
+
+    fn write_outline<W: Write>(&self, out: &mut W, entries: &[OutlineEntry]) -> io::Result<()> {
+        let number_width = entries
+            .last()
+            .map_or(1, |entry| entry.line.to_string().len());
+        let top_level = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+
+        for entry in entries {
+            let indent = 2 * (entry.level - top_level) as usize;
+            execute!(out, SetForegroundColor(self.colors.muted))?;
+            write!(out, "{:>width$}  ", entry.line, width = number_width)?;
+            let (color, _) = self.heading_style(entry.level);
+            execute!(
+                out,
+                SetForegroundColor(color),
+                SetAttribute(Attribute::Bold)
+            )?;
+            write!(out, "{}{}", " ".repeat(indent), entry.text)?;
+            execute!(out, ResetColor, SetAttribute(Attribute::Reset))?;
+            writeln!(out)?;
+
+            if let Some(excerpt) = &entry.excerpt {
+                // Under the heading text, indented one more step
+                let offset = number_width + 2 + indent + 2;
+                let width = self.term_width.saturating_sub(offset);
+                let excerpt = if excerpt.width() > width {
+                    truncate_to_width(excerpt, width, self.glyphs().ellipsis)
+                } else {
+                    excerpt.clone()
+                };
+                execute!(out, SetForegroundColor(self.colors.muted))?;
+                write!(out, "{}{}", " ".repeat(offset), excerpt)?;
+                execute!(out, ResetColor)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn render_to_writer<W: Write>(
         &self,
         out: &mut W,
         document: &Document,
         show_toc: bool,
+    ) -> Result<()> {
+        // Fail early rather than partway through the first code block
+        self.highlight_theme()?;
+
+        // Render into a buffer so the output can be post-processed
+        let mut buffer = Vec::new();
+        self.render_document(&mut buffer, document, show_toc)
+            .map_err(Error::Render)?;
+        self.write_output(out, &buffer)
+    }
+
+    /// Render a single element into a `String`, e.g. to preview just the part of a
+    /// document that changed. Output is post-processed like a whole document.
+    pub fn render_element_to_string(&self, element: &Element) -> Result<String> {
+        self.highlight_theme()?;
+
+        let mut buffer = Vec::new();
+        self.render_element(&mut buffer, element, 0)
+            .map_err(Error::Render)?;
+        let mut output = Vec::new();
+        self.write_output(&mut output, &buffer)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// Write rendered output, collapsing blank lines, stripping colors when disabled
+    /// and shifting lines to center the column
+    fn write_output<W: Write>(&self, out: &mut W, buffer: &[u8]) -> Result<()> {
+        let margin = self.margin();
+        // Each element adds its own spacing; like markdown, show runs of blank lines as one
+        let mut output = collapse_blank_lines(&String::from_utf8_lossy(buffer));
+
+        if !self.color {
+            output = strip_ansi(&output);
+        }
+
+        // Shift every line right to center the column
+        let padding = " ".repeat(margin);
+        for line in output.split_inclusive('\n') {
+            if margin > 0 && line != "\n" {
+                out.write_all(padding.as_bytes()).map_err(Error::Render)?;
+            }
+            out.write_all(line.as_bytes()).map_err(Error::Render)?;
+        }
+        Ok(())
+    }
+
+    /// Highlighting theme for code blocks, falling back to the first available theme
+    fn highlight_theme(&self) -> Result<&Theme> {
+        self.theme_set
+            .themes
+            .get(&self.syntax_theme)
+            .or_else(|| self.theme_set.themes.values().next())
+            .ok_or_else(|| Error::Theme(self.syntax_theme.clone()))
+    }
+
+    fn render_document<W: Write>(
+        &self,
+        out: &mut W,
+        document: &Document,
+        show_toc: bool,
     ) -> io::Result<()> {
+        if let Some(label) = &self.header {
+            self.render_header(out, label, document.display_title())?;
+        }
+
+        if let Some(frontmatter) = &document.frontmatter {
+            self.render_frontmatter(out, frontmatter)?;
+        }
+
         // Render TOC if requested
         if show_toc {
             let toc = generate_toc(document);
@@ -144,21 +837,52 @@ impl TerminalRenderer {
             }
         }
 
+        // Without a leading H1 title, the front matter title takes its place
+        let tags_after_title = matches!(
+            document.elements.first(),
+            Some(Element::Heading { level: 1, .. })
+        );
+        if !tags_after_title
+            && let Some(title) = document.frontmatter.as_ref().and_then(FrontMatter::title)
+        {
+            self.render_heading(out, 1, title)?;
+        }
+
+        // Byline and tags go below the title, otherwise at the top
+        if !tags_after_title {
+            self.render_meta(out, document)?;
+        }
+
+        let inlined;
+        let elements = if self.footnotes == FootnoteMode::Inline {
+            inlined = inline_footnotes(&document.elements);
+            &inlined
+        } else {
+            &document.elements
+        };
+
         // Separate footnote definitions from other elements
         let mut footnotes = Vec::new();
 
-        for element in &document.elements {
+        for (index, element) in elements.iter().enumerate() {
             if let Element::FootnoteDefinition { .. } = element {
                 footnotes.push(element);
             } else {
                 self.render_element(out, element, 0)?;
             }
+            if index == 0 && tags_after_title {
+                self.render_meta(out, document)?;
+            }
         }
 
         // Render footnotes at the end with a separator
         if !footnotes.is_empty() {
-            execute!(out, SetForegroundColor(Color::DarkGrey))?;
-            writeln!(out, "{}", "─".repeat(self.term_width.min(40)))?;
+            execute!(out, SetForegroundColor(self.colors.border))?;
+            writeln!(
+                out,
+                "{}",
+                self.glyphs().horizontal.repeat(self.term_width.min(40))
+            )?;
             execute!(out, ResetColor)?;
 
             for footnote in footnotes {
@@ -169,18 +893,110 @@ impl TerminalRenderer {
         Ok(())
     }
 
+    fn render_header<W: Write>(
+        &self,
+        out: &mut W,
+        label: &str,
+        title: Option<&str>,
+    ) -> io::Result<()> {
+        execute!(out, SetForegroundColor(self.colors.muted))?;
+        write!(out, "{}", label)?;
+        if let Some(title) = title {
+            execute!(
+                out,
+                SetForegroundColor(self.colors.text),
+                SetAttribute(Attribute::Bold)
+            )?;
+            write!(out, "  {}", title)?;
+            execute!(out, SetAttribute(Attribute::Reset))?;
+        }
+        writeln!(out)?;
+        execute!(out, SetForegroundColor(self.colors.border))?;
+        writeln!(out, "{}", self.glyphs().heavy.repeat(self.term_width))?;
+        execute!(out, ResetColor)?;
+        Ok(())
+    }
+
+    /// Front matter as a key/value table or the raw block, depending on the mode
+    fn render_frontmatter<W: Write>(
+        &self,
+        out: &mut W,
+        frontmatter: &FrontMatter,
+    ) -> io::Result<()> {
+        match self.frontmatter {
+            FrontMatterMode::Hide => Ok(()),
+            FrontMatterMode::Show => {
+                let rows: Vec<Vec<String>> = frontmatter
+                    .display_fields()
+                    .into_iter()
+                    .map(|(key, value)| vec![key, value])
+                    .collect();
+                self.render_grid(out, &[], &[Alignment::Left; 2], &rows)
+            }
+            FrontMatterMode::Raw => self.render_code_block(
+                out,
+                Some(frontmatter.format.language()),
+                None,
+                &frontmatter.raw,
+                0,
+            ),
+        }
+    }
+
+    /// Front matter author and date, last-modified date, and front matter tags
+    fn render_meta<W: Write>(&self, out: &mut W, document: &Document) -> io::Result<()> {
+        let byline = document.frontmatter.as_ref().and_then(FrontMatter::byline);
+        let modified = self
+            .modified
+            .map(|modified| format!("Last updated: {}", format_date(modified)));
+        let lines: Vec<String> = byline.into_iter().chain(modified).collect();
+        for line in &lines {
+            execute!(out, SetForegroundColor(self.colors.muted))?;
+            write!(out, "{}", line)?;
+            execute!(out, ResetColor)?;
+            writeln!(out)?;
+        }
+        if !lines.is_empty() && document.tags.is_empty() {
+            writeln!(out)?;
+        }
+        self.render_tags(out, &document.tags)
+    }
+
+    /// Front matter tags as bracketed labels (`[rust] [cli]`)
+    fn render_tags<W: Write>(&self, out: &mut W, tags: &[String]) -> io::Result<()> {
+        if tags.is_empty() {
+            return Ok(());
+        }
+
+        for (i, tag) in tags.iter().enumerate() {
+            if i > 0 {
+                write!(out, " ")?;
+            }
+            execute!(out, SetForegroundColor(self.colors.label))?;
+            write!(out, "[{}]", tag)?;
+            execute!(out, ResetColor)?;
+        }
+        writeln!(out)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
     fn render_toc<W: Write>(&self, out: &mut W, toc: &[TocEntry]) -> io::Result<()> {
         // TOC header
         writeln!(out)?;
         execute!(
             out,
-            SetForegroundColor(Color::Cyan),
+            SetForegroundColor(self.colors.accent),
             SetAttribute(Attribute::Bold)
         )?;
-        writeln!(out, "📑 Table of Contents")?;
+        writeln!(out, "{}", self.glyphs().toc_title)?;
         execute!(out, ResetColor, SetAttribute(Attribute::Reset))?;
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        writeln!(out, "{}", "─".repeat(self.term_width.min(30)))?;
+        execute!(out, SetForegroundColor(self.colors.border))?;
+        writeln!(
+            out,
+            "{}",
+            self.glyphs().horizontal.repeat(self.term_width.min(30))
+        )?;
         execute!(out, ResetColor)?;
 
         // Find minimum level for proper indentation
@@ -188,22 +1004,21 @@ impl TerminalRenderer {
 
         for entry in toc {
             let indent = "  ".repeat((entry.level - min_level) as usize);
-            let bullet = match entry.level {
-                1 => "●",
-                2 => "○",
-                3 => "◆",
-                _ => "◇",
-            };
+            let bullet = self.glyphs().toc_bullets[(entry.level.clamp(1, 4) - 1) as usize];
 
-            execute!(out, SetForegroundColor(Color::Cyan))?;
+            execute!(out, SetForegroundColor(self.colors.accent))?;
             write!(out, "{}{} ", indent, bullet)?;
             execute!(out, ResetColor)?;
             writeln!(out, "{}", entry.text)?;
         }
 
         writeln!(out)?;
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        writeln!(out, "{}", "━".repeat(self.term_width.min(50)))?;
+        execute!(out, SetForegroundColor(self.colors.border))?;
+        writeln!(
+            out,
+            "{}",
+            self.glyphs().heavy.repeat(self.term_width.min(50))
+        )?;
         execute!(out, ResetColor)?;
         writeln!(out)?;
 
@@ -223,15 +1038,25 @@ impl TerminalRenderer {
             Element::Paragraph { content } => {
                 self.render_paragraph(out, content, indent)?;
             }
-            Element::CodeBlock { language, content } => {
-                self.render_code_block(out, language.as_deref(), content)?;
+            Element::CodeBlock {
+                language,
+                title,
+                content,
+            } => {
+                self.render_code_block(
+                    out,
+                    language.as_deref(),
+                    title.as_deref(),
+                    content,
+                    indent,
+                )?;
             }
             Element::List {
                 ordered,
                 start,
                 items,
             } => {
-                self.render_list(out, *ordered, *start, items, indent)?;
+                self.render_list(out, *ordered, *start, items, indent, 0)?;
             }
             Element::Table {
                 headers,
@@ -243,6 +1068,9 @@ impl TerminalRenderer {
             Element::BlockQuote { content } => {
                 self.render_blockquote(out, content)?;
             }
+            Element::Alert { kind, content } => {
+                self.render_alert(out, *kind, content)?;
+            }
             Element::HorizontalRule => {
                 self.render_horizontal_rule(out)?;
             }
@@ -254,24 +1082,20 @@ impl TerminalRenderer {
             }
             Element::Html(html) => {
                 // Display raw HTML in grey (terminal can't render HTML)
-                execute!(out, SetForegroundColor(Color::DarkGrey))?;
+                execute!(out, SetForegroundColor(self.colors.muted))?;
                 writeln!(out, "{}", html)?;
                 execute!(out, ResetColor)?;
                 writeln!(out)?; // Add blank line after HTML block for consistency
             }
+            Element::MathBlock { content } => {
+                self.render_math_block(out, content, indent)?;
+            }
         }
         Ok(())
     }
 
     fn render_heading<W: Write>(&self, out: &mut W, level: u8, content: &str) -> io::Result<()> {
-        let (color, prefix) = match level {
-            1 => (Color::Magenta, "█ "),
-            2 => (Color::Cyan, "▓ "),
-            3 => (Color::Blue, "▒ "),
-            4 => (Color::Green, "░ "),
-            5 => (Color::Yellow, "• "),
-            _ => (Color::White, "· "),
-        };
+        let (color, prefix) = self.heading_style(level);
 
         writeln!(out)?;
         execute!(
@@ -291,11 +1115,13 @@ impl TerminalRenderer {
 
         // Add decorative line for h1
         if level == 1 {
-            execute!(out, SetForegroundColor(Color::DarkGrey))?;
+            execute!(out, SetForegroundColor(self.colors.border))?;
             writeln!(
                 out,
                 "{}",
-                "─".repeat(self.term_width.min(content.width() + 4))
+                self.glyphs()
+                    .horizontal
+                    .repeat(self.term_width.min(content.width() + 4))
             )?;
             execute!(out, ResetColor)?;
         }
@@ -313,17 +1139,45 @@ impl TerminalRenderer {
         let indent_str = " ".repeat(indent);
         write!(out, "{}", indent_str)?;
 
+        let mut buffer = Vec::new();
         let style = StyleState::default();
         for inline in content {
-            self.render_inline(out, inline, &style)?;
+            self.render_inline(&mut buffer, inline, &style)?;
         }
+        self.write_wrapped(out, &buffer, indent, &indent_str)?;
 
         writeln!(out)?;
         writeln!(out)?;
         Ok(())
     }
 
-    #[allow(clippy::only_used_in_recursion)]
+    /// Write rendered inline content, wrapping it at the content width when enabled.
+    /// `offset` is the number of columns already used on the first line, and
+    /// `continuation` is written at the start of every wrapped line.
+    fn write_wrapped<W: Write>(
+        &self,
+        out: &mut W,
+        rendered: &[u8],
+        offset: usize,
+        continuation: &str,
+    ) -> io::Result<()> {
+        // Without wrapping, line breaks still need the continuation indent
+        let width = if self.wrap {
+            self.term_width
+        } else {
+            usize::MAX
+        };
+        let text = String::from_utf8_lossy(rendered);
+        let wrapped = wrap_ansi(
+            &text,
+            width,
+            offset,
+            continuation,
+            visible_width(continuation),
+        );
+        write!(out, "{}", wrapped)
+    }
+
     fn render_inline<W: Write>(
         &self,
         out: &mut W,
@@ -337,7 +1191,7 @@ impl TerminalRenderer {
             InlineElement::Code(code) => {
                 // Code has its own color, temporarily override
                 let code_style = StyleState {
-                    color: Some(Color::Yellow),
+                    color: Some(self.colors.code),
                     ..style.clone()
                 };
                 code_style.apply_diff(style, out)?;
@@ -345,6 +1199,23 @@ impl TerminalRenderer {
                 // Restore parent style (only color changed)
                 style.apply_diff(&code_style, out)?;
             }
+            InlineElement::Math { tex, display } => {
+                let math_style = StyleState {
+                    color: Some(self.colors.math),
+                    ..style.clone()
+                };
+                math_style.apply_diff(style, out)?;
+                let tex = &tex.replace('\n', " ");
+                // Simple expressions as Unicode, anything else as the raw TeX, which
+                // keeps its delimiters only when there is no color to set it apart
+                match tex_to_unicode(tex).filter(|text| !self.ascii || text.is_ascii()) {
+                    Some(text) => write!(out, "{}", text)?,
+                    None if self.color => write!(out, "{}", tex)?,
+                    None if *display => write!(out, "$${}$$", tex)?,
+                    None => write!(out, "${}$", tex)?,
+                }
+                style.apply_diff(&math_style, out)?;
+            }
             InlineElement::Strong(content) => {
                 let child_style = StyleState {
                     bold: true,
@@ -384,7 +1255,7 @@ impl TerminalRenderer {
             InlineElement::Link { url, content, .. } => {
                 let child_style = StyleState {
                     underline: true,
-                    color: Some(Color::Blue),
+                    color: Some(self.colors.link),
                     ..style.clone()
                 };
                 child_style.apply_diff(style, out)?;
@@ -393,7 +1264,7 @@ impl TerminalRenderer {
                 }
                 // URL suffix in grey (temporary style, no underline)
                 let url_style = StyleState {
-                    color: Some(Color::DarkGrey),
+                    color: Some(self.colors.muted),
                     ..StyleState::default()
                 };
                 url_style.apply_diff(&child_style, out)?;
@@ -403,7 +1274,7 @@ impl TerminalRenderer {
             }
             InlineElement::FootnoteReference(label) => {
                 let footnote_style = StyleState {
-                    color: Some(Color::Cyan),
+                    color: Some(self.colors.accent),
                     ..style.clone()
                 };
                 footnote_style.apply_diff(style, out)?;
@@ -414,37 +1285,56 @@ impl TerminalRenderer {
             InlineElement::TaskListMarker(checked) => {
                 let marker_style = StyleState {
                     color: Some(if *checked {
-                        Color::Green
+                        self.colors.checked
                     } else {
-                        Color::DarkGrey
+                        self.colors.muted
                     }),
                     ..style.clone()
                 };
                 marker_style.apply_diff(style, out)?;
-                write!(out, "{} ", if *checked { "☑" } else { "☐" })?;
+                let glyphs = self.glyphs();
+                write!(
+                    out,
+                    "{} ",
+                    if *checked {
+                        glyphs.task_done
+                    } else {
+                        glyphs.task_open
+                    }
+                )?;
                 // Restore parent style
                 style.apply_diff(&marker_style, out)?;
             }
             InlineElement::InlineHtml(html) => {
                 // Display inline HTML as-is in grey (terminal can't render HTML)
                 let html_style = StyleState {
-                    color: Some(Color::DarkGrey),
+                    color: Some(self.colors.muted),
                     ..style.clone()
                 };
                 html_style.apply_diff(style, out)?;
                 write!(out, "{}", html)?;
                 style.apply_diff(&html_style, out)?;
             }
+            InlineElement::Kbd(key) => {
+                // Bracketed and bold, so keys stand out even without color
+                let kbd_style = StyleState {
+                    bold: true,
+                    ..style.clone()
+                };
+                kbd_style.apply_diff(style, out)?;
+                write!(out, "[{}]", key)?;
+                style.apply_diff(&kbd_style, out)?;
+            }
             InlineElement::Image { url, alt, .. } => {
                 // Display image as [alt](url) with image icon
                 // Fallback to "Image" if alt is empty
                 let display_alt = if alt.is_empty() { "Image" } else { alt };
                 let img_style = StyleState {
-                    color: Some(Color::Magenta),
+                    color: Some(self.colors.label),
                     ..style.clone()
                 };
                 img_style.apply_diff(style, out)?;
-                write!(out, "🖼 [{}]({})", display_alt, url)?;
+                write!(out, "{} [{}]({})", self.glyphs().image, display_alt, url)?;
                 style.apply_diff(&img_style, out)?;
             }
             InlineElement::SoftBreak | InlineElement::HardBreak => {
@@ -458,67 +1348,98 @@ impl TerminalRenderer {
         &self,
         out: &mut W,
         language: Option<&str>,
+        title: Option<&str>,
         content: &str,
+        indent: usize,
     ) -> io::Result<()> {
-        // Special handling for mermaid diagrams
-        if language == Some("mermaid") {
-            return self.render_mermaid_placeholder(out, content);
+        // Diagrams get a labeled box; they are drawn only in the browser
+        if let Some(kind) = language.and_then(DiagramKind::from_language) {
+            return self.render_diagram_placeholder(out, kind, content, indent);
         }
 
-        let syntax_theme = if self.theme == "light" {
-            "base16-ocean.light"
-        } else {
-            "base16-ocean.dark"
-        };
-
-        // Get theme with fallback to first available theme
-        let theme = self
-            .theme_set
-            .themes
-            .get(syntax_theme)
-            .or_else(|| self.theme_set.themes.values().next())
-            .expect("No themes available in ThemeSet");
+        // Checked up front in render_to_writer
+        let theme = self.highlight_theme().map_err(io::Error::other)?;
 
-        // Find syntax for the language
+        // Find syntax for the language (unlabeled blocks use the default language)
         let syntax = language
+            .or(self.default_lang.as_deref())
             .and_then(|lang| self.syntax_set.find_syntax_by_token(lang))
             .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
 
         let mut highlighter = HighlightLines::new(syntax, theme);
 
+        let g = self.glyphs();
+        let indent_str = " ".repeat(indent);
+        let rule = g
+            .horizontal
+            .repeat(self.term_width.saturating_sub(indent + 2));
+
+        let boxed = self.code_style == CodeStyle::Boxed;
+        // Bar in front of each line
+        let gutter = match self.code_style {
+            CodeStyle::Boxed | CodeStyle::Bordered => format!("{} ", g.vertical),
+            CodeStyle::Minimal | CodeStyle::Plain => String::new(),
+        };
+
         // Draw top border
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        writeln!(out, "┌{}┐", "─".repeat(self.term_width.saturating_sub(2)))?;
+        if boxed {
+            execute!(out, SetForegroundColor(self.colors.border))?;
+            writeln!(out, "{}{}{}{}", indent_str, g.top_left, rule, g.top_right)?;
+        }
 
-        // Language label
-        if let Some(lang) = language {
-            execute!(out, SetForegroundColor(Color::Cyan))?;
-            writeln!(out, "│ {}", lang)?;
-            execute!(out, SetForegroundColor(Color::DarkGrey))?;
-            writeln!(out, "├{}┤", "─".repeat(self.term_width.saturating_sub(2)))?;
+        // Language label, after the file name if there is one
+        let label = match (title, language) {
+            (Some(title), Some(lang)) => Some(format!("{} ({})", title, lang)),
+            (Some(label), None) | (None, Some(label)) => Some(label.to_string()),
+            (None, None) => None,
+        };
+        if let Some(label) = label
+            && self.code_style != CodeStyle::Plain
+        {
+            execute!(out, SetForegroundColor(self.colors.accent))?;
+            writeln!(out, "{}{}{}", indent_str, gutter, label)?;
+            if boxed {
+                execute!(out, SetForegroundColor(self.colors.border))?;
+                writeln!(out, "{}{}{}{}", indent_str, g.tee_right, rule, g.tee_left)?;
+            }
         }
 
         execute!(out, ResetColor)?;
 
+        // Highlighter state carries across lines, so skip the whole block rather than a line
+        let highlight = content
+            .lines()
+            .all(|line| line.len() <= MAX_HIGHLIGHT_LINE_LEN);
+
         // Render code with syntax highlighting
         for line in content.lines() {
-            execute!(out, SetForegroundColor(Color::DarkGrey))?;
-            write!(out, "│ ")?;
+            execute!(out, SetForegroundColor(self.colors.border))?;
+            write!(out, "{}{}", indent_str, gutter)?;
             execute!(out, ResetColor)?;
 
-            let ranges: Vec<(Style, &str)> = highlighter
-                .highlight_line(line, &self.syntax_set)
-                .unwrap_or_default();
-            let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
-            write!(out, "{}", escaped)?;
-            write!(out, "\x1b[0m")?; // Reset
+            if highlight {
+                let ranges: Vec<(Style, &str)> = highlighter
+                    .highlight_line(line, &self.syntax_set)
+                    .unwrap_or_default();
+                let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
+                write!(out, "{}", escaped)?;
+                write!(out, "\x1b[0m")?; // Reset
+            } else {
+                write!(out, "{}", line)?;
+            }
             writeln!(out)?;
         }
 
         // Draw bottom border
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        writeln!(out, "└{}┘", "─".repeat(self.term_width.saturating_sub(2)))?;
-        execute!(out, ResetColor)?;
+        if boxed {
+            execute!(out, SetForegroundColor(self.colors.border))?;
+            writeln!(
+                out,
+                "{}{}{}{}",
+                indent_str, g.bottom_left, rule, g.bottom_right
+            )?;
+            execute!(out, ResetColor)?;
+        }
         writeln!(out)?;
 
         Ok(())
@@ -531,6 +1452,7 @@ impl TerminalRenderer {
         start: Option<u64>,
         items: &[ListItem],
         indent: usize,
+        depth: usize,
     ) -> io::Result<()> {
         let indent_str = " ".repeat(indent);
         let mut number = start.unwrap_or(1);
@@ -541,17 +1463,13 @@ impl TerminalRenderer {
                 number += 1;
                 b
             } else {
-                match indent / 2 {
-                    0 => "• ".to_string(),
-                    1 => "◦ ".to_string(),
-                    _ => "▪ ".to_string(),
-                }
+                format!("{} ", self.list_bullet(depth))
             };
 
             // Calculate content indent (indent + bullet width) for continuation lines
             // Use unicode_width for accurate display width calculation
-            let bullet_width = bullet.width();
-            let content_indent = " ".repeat(indent + bullet_width);
+            let content_width = indent + bullet.width();
+            let content_indent = " ".repeat(content_width);
 
             // Render bullet for the first content element
             let mut first_element = true;
@@ -561,18 +1479,21 @@ impl TerminalRenderer {
                     Element::Paragraph { content } => {
                         if first_element {
                             // First paragraph: print bullet then content on same line
-                            execute!(out, SetForegroundColor(Color::Cyan))?;
+                            execute!(out, SetForegroundColor(self.colors.accent))?;
                             write!(out, "{}{}", indent_str, bullet)?;
                             execute!(out, ResetColor)?;
                             first_element = false;
                         } else {
-                            // Subsequent paragraphs: indent to align with first paragraph
+                            // Subsequent paragraphs: separate them, then hang under the item text
+                            writeln!(out)?;
                             write!(out, "{}", content_indent)?;
                         }
+                        let mut buffer = Vec::new();
                         let style = StyleState::default();
                         for inline in content {
-                            self.render_inline(out, inline, &style)?;
+                            self.render_inline(&mut buffer, inline, &style)?;
                         }
+                        self.write_wrapped(out, &buffer, content_indent.len(), &content_indent)?;
                         writeln!(out)?;
                     }
                     Element::List {
@@ -583,7 +1504,7 @@ impl TerminalRenderer {
                         // Nested list: always needs newline before it
                         if first_element {
                             // Print bullet with newline, then nested list
-                            execute!(out, SetForegroundColor(Color::Cyan))?;
+                            execute!(out, SetForegroundColor(self.colors.accent))?;
                             write!(out, "{}{}", indent_str, bullet)?;
                             execute!(out, ResetColor)?;
                             writeln!(out)?;
@@ -594,27 +1515,42 @@ impl TerminalRenderer {
                             *nested_ordered,
                             *nested_start,
                             nested_items,
-                            indent + 2,
+                            content_width,
+                            depth + 1,
                         )?;
                     }
                     _ => {
                         // Other block elements (CodeBlock, BlockQuote, etc.)
                         if first_element {
-                            execute!(out, SetForegroundColor(Color::Cyan))?;
+                            execute!(out, SetForegroundColor(self.colors.accent))?;
                             write!(out, "{}{}", indent_str, bullet)?;
                             execute!(out, ResetColor)?;
                             writeln!(out)?;
                             first_element = false;
+                        } else {
+                            writeln!(out)?;
+                        }
+                        if let Element::CodeBlock { .. } = element {
+                            self.render_element(out, element, content_width)?;
+                        } else {
+                            // Shift blocks without indent support under the item text
+                            let mut buffer = Vec::new();
+                            self.render_element(&mut buffer, element, 0)?;
+                            for line in String::from_utf8_lossy(&buffer).lines() {
+                                if strip_ansi(line).trim().is_empty() {
+                                    writeln!(out, "{}", line)?;
+                                } else {
+                                    writeln!(out, "{}{}", content_indent, line)?;
+                                }
+                            }
                         }
-                        // Render with additional indent for visual nesting
-                        self.render_element(out, element, indent + 2)?;
                     }
                 }
             }
 
             // If item had no content, just print the bullet
             if first_element {
-                execute!(out, SetForegroundColor(Color::Cyan))?;
+                execute!(out, SetForegroundColor(self.colors.accent))?;
                 write!(out, "{}{}", indent_str, bullet)?;
                 execute!(out, ResetColor)?;
                 writeln!(out)?;
@@ -629,102 +1565,187 @@ impl TerminalRenderer {
     }
 
     fn render_table<W: Write>(
+        &self,
+        out: &mut W,
+        headers: &[Vec<InlineElement>],
+        alignments: &[Alignment],
+        rows: &[Vec<Vec<InlineElement>>],
+    ) -> io::Result<()> {
+        // Header cells are styled on top of the bold header color
+        let header_style = StyleState {
+            bold: true,
+            color: Some(self.colors.accent),
+            ..StyleState::default()
+        };
+        let headers = headers
+            .iter()
+            .map(|cell| self.render_cell(cell, &header_style))
+            .collect::<io::Result<Vec<_>>>()?;
+        let rows = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| self.render_cell(cell, &StyleState::default()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        self.render_grid(out, &headers, alignments, &rows)
+    }
+
+    /// A table cell as styled text, with a line per `<br>`
+    fn render_cell(&self, cell: &[InlineElement], style: &StyleState) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        for inline in cell {
+            match inline {
+                InlineElement::SoftBreak | InlineElement::HardBreak => writeln!(buffer)?,
+                _ => self.render_inline(&mut buffer, inline, style)?,
+            }
+        }
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+
+    /// Table of already styled cells; widths count only the visible text
+    fn render_grid<W: Write>(
         &self,
         out: &mut W,
         headers: &[String],
         alignments: &[Alignment],
         rows: &[Vec<String>],
     ) -> io::Result<()> {
-        // Determine number of columns
-        let num_cols = headers
-            .len()
-            .max(rows.first().map(|r| r.len()).unwrap_or(0));
+        // Determine number of columns (the header defines the grid when present)
+        let num_cols = if headers.is_empty() {
+            rows.iter().map(|r| r.len()).max().unwrap_or(0)
+        } else {
+            headers.len()
+        };
         if num_cols == 0 {
             return Ok(());
         }
 
+        // Pad short rows and fold extra cells into the last column so borders stay aligned
+        let rows: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                if row.len() > num_cols {
+                    crate::warning!(
+                        "Warning: table row has {} cells but the table has {} columns; merging extra cells into the last column",
+                        row.len(),
+                        num_cols
+                    );
+                }
+                normalize_table_row(row, num_cols)
+            })
+            .collect();
+
         // Calculate column widths
         let mut col_widths: Vec<usize> = vec![0; num_cols];
+        // Cells may span several lines (`<br>` in the source)
+        let cell_width = |cell: &str| cell.split('\n').map(visible_width).max().unwrap_or(0);
         for (i, header) in headers.iter().enumerate() {
             if i < col_widths.len() {
-                col_widths[i] = col_widths[i].max(header.width());
+                col_widths[i] = col_widths[i].max(cell_width(header));
             }
         }
-        for row in rows {
+        for row in &rows {
             for (i, cell) in row.iter().enumerate() {
                 if i < col_widths.len() {
-                    col_widths[i] = col_widths[i].max(cell.width());
+                    col_widths[i] = col_widths[i].max(cell_width(cell));
                 }
             }
         }
 
         // Add padding and ensure minimum width
-        let col_widths: Vec<usize> = col_widths.iter().map(|w| (*w).max(3) + 2).collect();
+        let mut col_widths: Vec<usize> = col_widths.iter().map(|w| (*w).max(3) + 2).collect();
+
+        // Shrink the widest columns until the table fits the content width
+        let available = self.term_width.saturating_sub(num_cols + 1);
+        while col_widths.iter().sum::<usize>() > available {
+            let Some(widest) = col_widths.iter_mut().max() else {
+                break;
+            };
+            if *widest <= 5 {
+                break;
+            }
+            *widest -= 1;
+        }
+
+        let g = self.glyphs();
 
         // Draw top border
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        write!(out, "┌")?;
+        execute!(out, SetForegroundColor(self.colors.border))?;
+        write!(out, "{}", g.top_left)?;
         for (i, width) in col_widths.iter().enumerate() {
-            write!(out, "{}", "─".repeat(*width))?;
+            write!(out, "{}", g.horizontal.repeat(*width))?;
             if i < col_widths.len() - 1 {
-                write!(out, "┬")?;
+                write!(out, "{}", g.tee_down)?;
             }
         }
-        writeln!(out, "┐")?;
+        writeln!(out, "{}", g.top_right)?;
 
         // Draw header only if headers exist
         if !headers.is_empty() {
-            execute!(out, SetForegroundColor(Color::DarkGrey))?;
-            write!(out, "│")?;
-            for (i, header) in headers.iter().enumerate() {
-                let width = col_widths.get(i).copied().unwrap_or(10);
-                let align = alignments.get(i).copied().unwrap_or(Alignment::Left);
-                execute!(
-                    out,
-                    SetForegroundColor(Color::Cyan),
-                    SetAttribute(Attribute::Bold)
-                )?;
-                write!(out, "{}", self.align_text(header, width, align))?;
-                execute!(out, ResetColor, SetAttribute(Attribute::Reset))?;
-                execute!(out, SetForegroundColor(Color::DarkGrey))?;
-                write!(out, "│")?;
+            for line in 0..row_height(headers) {
+                execute!(out, SetForegroundColor(self.colors.border))?;
+                write!(out, "{}", g.vertical)?;
+                for (i, header) in headers.iter().enumerate() {
+                    let width = col_widths.get(i).copied().unwrap_or(10);
+                    let align = alignments.get(i).copied().unwrap_or(Alignment::Left);
+                    let text = header.split('\n').nth(line).unwrap_or("");
+                    execute!(
+                        out,
+                        SetForegroundColor(self.colors.accent),
+                        SetAttribute(Attribute::Bold)
+                    )?;
+                    write!(out, "{}", self.align_text(text, width, align))?;
+                    execute!(out, ResetColor, SetAttribute(Attribute::Reset))?;
+                    execute!(out, SetForegroundColor(self.colors.border))?;
+                    write!(out, "{}", g.vertical)?;
+                }
+                writeln!(out)?;
             }
-            writeln!(out)?;
 
-            // Draw header separator
-            write!(out, "├")?;
+            // Draw header separator, marking column alignment like `:--`, `:-:` and `--:`
+            write!(out, "{}", g.tee_right)?;
             for (i, width) in col_widths.iter().enumerate() {
-                write!(out, "{}", "─".repeat(*width))?;
+                let align = alignments.get(i).copied().unwrap_or(Alignment::None);
+                write!(out, "{}", separator_segment(*width, align, g))?;
                 if i < col_widths.len() - 1 {
-                    write!(out, "┼")?;
+                    write!(out, "{}", g.cross)?;
                 }
             }
-            writeln!(out, "┤")?;
+            writeln!(out, "{}", g.tee_left)?;
         }
 
-        // Draw rows
-        for row in rows {
-            write!(out, "│")?;
-            for (i, cell) in row.iter().enumerate() {
-                let width = col_widths.get(i).copied().unwrap_or(10);
-                let align = alignments.get(i).copied().unwrap_or(Alignment::Left);
-                execute!(out, ResetColor)?;
-                write!(out, "{}", self.align_text(cell, width, align))?;
-                execute!(out, SetForegroundColor(Color::DarkGrey))?;
-                write!(out, "│")?;
+        // Draw rows, multi-line cells top-aligned
+        for row in &rows {
+            for line in 0..row_height(row) {
+                write!(out, "{}", g.vertical)?;
+                for (i, cell) in row.iter().enumerate() {
+                    let width = col_widths.get(i).copied().unwrap_or(10);
+                    let align = alignments.get(i).copied().unwrap_or(Alignment::Left);
+                    let text = cell.split('\n').nth(line).unwrap_or("");
+                    execute!(out, ResetColor)?;
+                    write!(out, "{}", self.align_text(text, width, align))?;
+                    execute!(
+                        out,
+                        SetAttribute(Attribute::Reset),
+                        SetForegroundColor(self.colors.border)
+                    )?;
+                    write!(out, "{}", g.vertical)?;
+                }
+                writeln!(out)?;
             }
-            writeln!(out)?;
         }
 
         // Draw bottom border
-        write!(out, "└")?;
+        write!(out, "{}", g.bottom_left)?;
         for (i, width) in col_widths.iter().enumerate() {
-            write!(out, "{}", "─".repeat(*width))?;
+            write!(out, "{}", g.horizontal.repeat(*width))?;
             if i < col_widths.len() - 1 {
-                write!(out, "┴")?;
+                write!(out, "{}", g.tee_up)?;
             }
         }
-        writeln!(out, "┘")?;
+        writeln!(out, "{}", g.bottom_right)?;
         execute!(out, ResetColor)?;
         writeln!(out)?;
 
@@ -732,7 +1753,14 @@ impl TerminalRenderer {
     }
 
     fn align_text(&self, text: &str, width: usize, alignment: Alignment) -> String {
-        let text_width = text.width();
+        // Keep one column of padding on each side; truncate cells that don't fit
+        let text = if visible_width(text) > width.saturating_sub(2) {
+            truncate_to_width(text, width.saturating_sub(2), self.glyphs().ellipsis)
+        } else {
+            text.to_string()
+        };
+        let text = text.as_str();
+        let text_width = visible_width(text);
         let padding = width.saturating_sub(text_width);
 
         match alignment {
@@ -754,41 +1782,87 @@ impl TerminalRenderer {
         // Blockquote base style: italic, white color
         let blockquote_style = StyleState {
             italic: true,
-            color: Some(Color::White),
+            color: Some(self.colors.text),
             ..StyleState::default()
         };
+        self.render_quoted(out, content, self.colors.border, &blockquote_style)
+    }
 
+    /// Alert: the kind's icon and title, then the content behind a bar in its color
+    fn render_alert<W: Write>(
+        &self,
+        out: &mut W,
+        kind: AlertKind,
+        content: &[Element],
+    ) -> io::Result<()> {
+        let color = self.colors.alert(kind);
+        execute!(out, SetForegroundColor(color))?;
+        write!(out, "{}", self.glyphs().quote_bar)?;
+        execute!(out, SetAttribute(Attribute::Bold))?;
+        write!(out, "{}", self.glyphs().alert_title(kind))?;
+        execute!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+        writeln!(out)?;
+        self.render_quoted(out, content, color, &StyleState::default())
+    }
+
+    /// Quoted content, each line behind a bar in `bar_color`
+    fn render_quoted<W: Write>(
+        &self,
+        out: &mut W,
+        content: &[Element],
+        bar_color: Color,
+        blockquote_style: &StyleState,
+    ) -> io::Result<()> {
         for element in content {
             match element {
                 Element::Paragraph { content } => {
-                    // First line - start fresh after prefix
-                    execute!(out, SetForegroundColor(Color::DarkGrey))?;
-                    write!(out, "  ▌ ")?;
-                    execute!(out, ResetColor)?;
-                    blockquote_style.apply_fresh(out)?;
-
+                    // Prefix bar, then start the blockquote style fresh
+                    let mut bar = Vec::new();
+                    execute!(bar, SetForegroundColor(bar_color))?;
+                    write!(bar, "{}", self.glyphs().quote_bar)?;
+                    execute!(bar, ResetColor)?;
+                    blockquote_style.apply_fresh(&mut bar)?;
+
+                    // Every following line resets, then repeats the bar
+                    let mut continuation = Vec::new();
+                    execute!(continuation, SetAttribute(Attribute::Reset), ResetColor)?;
+                    continuation.extend_from_slice(&bar);
+
+                    let mut buffer = Vec::new();
                     for inline in content {
                         match inline {
                             InlineElement::SoftBreak | InlineElement::HardBreak => {
-                                writeln!(out)?;
-                                // Reset for prefix, then apply blockquote style fresh
-                                execute!(out, SetAttribute(Attribute::Reset), ResetColor)?;
-                                execute!(out, SetForegroundColor(Color::DarkGrey))?;
-                                write!(out, "  ▌ ")?;
-                                execute!(out, ResetColor)?;
-                                blockquote_style.apply_fresh(out)?;
+                                writeln!(buffer)?;
                             }
                             _ => {
-                                self.render_inline(out, inline, &blockquote_style)?;
+                                self.render_inline(&mut buffer, inline, blockquote_style)?;
                             }
                         }
                     }
+
+                    let width = if self.wrap {
+                        self.term_width
+                    } else {
+                        usize::MAX
+                    };
+                    out.write_all(&bar)?;
+                    write!(
+                        out,
+                        "{}",
+                        wrap_ansi(
+                            &String::from_utf8_lossy(&buffer),
+                            width,
+                            4,
+                            &String::from_utf8_lossy(&continuation),
+                            4,
+                        )
+                    )?;
                     writeln!(out)?;
                     execute!(out, SetAttribute(Attribute::Reset), ResetColor)?;
                 }
                 _ => {
-                    execute!(out, SetForegroundColor(Color::DarkGrey))?;
-                    write!(out, "  ▌ ")?;
+                    execute!(out, SetForegroundColor(bar_color))?;
+                    write!(out, "{}", self.glyphs().quote_bar)?;
                     execute!(out, ResetColor)?;
                     self.render_element(out, element, 4)?;
                 }
@@ -799,9 +1873,21 @@ impl TerminalRenderer {
     }
 
     fn render_horizontal_rule<W: Write>(&self, out: &mut W) -> io::Result<()> {
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        writeln!(out)?;
-        writeln!(out, "{}", "━".repeat(self.term_width))?;
+        // Within the content width, which `--width` may cap below the terminal's
+        let rule = match self.style.rule {
+            RuleStyle::Heavy => self.glyphs().heavy.repeat(self.term_width),
+            RuleStyle::Light => self.glyphs().horizontal.repeat(self.term_width),
+            RuleStyle::Dots => {
+                let dots = "* * *";
+                format!(
+                    "{}{}",
+                    " ".repeat(self.term_width.saturating_sub(dots.len()) / 2),
+                    dots
+                )
+            }
+        };
+        execute!(out, SetForegroundColor(self.colors.border))?;
+        writeln!(out, "{}", rule)?;
         writeln!(out)?;
         execute!(out, ResetColor)?;
         Ok(())
@@ -810,16 +1896,16 @@ impl TerminalRenderer {
     fn render_image<W: Write>(&self, out: &mut W, url: &str, alt: &str) -> io::Result<()> {
         // For now, just display image info
         // TODO: Phase 5 - iTerm2/Kitty image protocol support
-        execute!(out, SetForegroundColor(Color::Magenta))?;
-        write!(out, "🖼  ")?;
+        execute!(out, SetForegroundColor(self.colors.label))?;
+        write!(out, "{}  ", self.glyphs().image)?;
         execute!(
             out,
-            SetForegroundColor(Color::Blue),
+            SetForegroundColor(self.colors.link),
             SetAttribute(Attribute::Underlined)
         )?;
         write!(out, "{}", if alt.is_empty() { "Image" } else { alt })?;
         execute!(out, ResetColor, SetAttribute(Attribute::Reset))?;
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
+        execute!(out, SetForegroundColor(self.colors.muted))?;
         writeln!(out, " ({})", url)?;
         execute!(out, ResetColor)?;
         writeln!(out)?;
@@ -833,7 +1919,7 @@ impl TerminalRenderer {
         content: &[Element],
     ) -> io::Result<()> {
         // Render footnote label
-        execute!(out, SetForegroundColor(Color::Cyan))?;
+        execute!(out, SetForegroundColor(self.colors.accent))?;
         write!(out, "[^{}]: ", label)?;
         execute!(out, ResetColor)?;
 
@@ -861,46 +1947,1229 @@ impl TerminalRenderer {
         Ok(())
     }
 
-    fn render_mermaid_placeholder<W: Write>(&self, out: &mut W, content: &str) -> io::Result<()> {
-        let box_width = self.term_width.saturating_sub(2);
+    /// Display math in a centered box, as Unicode when it converts and otherwise
+    /// as TeX, one row per source line
+    fn render_math_block<W: Write>(
+        &self,
+        out: &mut W,
+        content: &str,
+        indent: usize,
+    ) -> io::Result<()> {
+        let g = self.glyphs();
+        let text = tex_to_unicode(content)
+            .filter(|text| !self.ascii || text.is_ascii())
+            .unwrap_or_else(|| content.to_string());
+        let lines: Vec<&str> = text.lines().map(str::trim).collect();
+
+        // Rows are `│ text │`; the box is as wide as the longest line allows
+        let available = self.term_width.saturating_sub(indent);
+        let text_width = lines
+            .iter()
+            .map(|line| line.width())
+            .max()
+            .unwrap_or(0)
+            .min(available.saturating_sub(4));
+        let box_width = text_width + 2;
+        let indent_str = " ".repeat(indent + available.saturating_sub(box_width + 2) / 2);
+        let rule = g.horizontal.repeat(box_width);
+
+        execute!(out, SetForegroundColor(self.colors.border))?;
+        writeln!(out, "{}{}{}{}", indent_str, g.top_left, rule, g.top_right)?;
+        for line in lines {
+            let line = if line.width() > text_width {
+                truncate_to_width(line, text_width, g.ellipsis)
+            } else {
+                line.to_string()
+            };
+            write!(out, "{}{} ", indent_str, g.vertical)?;
+            execute!(out, SetForegroundColor(self.colors.math))?;
+            write!(out, "{}", pad_to_width(&line, text_width))?;
+            execute!(out, SetForegroundColor(self.colors.border))?;
+            writeln!(out, " {}", g.vertical)?;
+        }
+        writeln!(
+            out,
+            "{}{}{}{}",
+            indent_str, g.bottom_left, rule, g.bottom_right
+        )?;
+        execute!(out, ResetColor)?;
+        writeln!(out)?;
+
+        Ok(())
+    }
 
-        // Draw mermaid header
-        execute!(out, SetForegroundColor(Color::Magenta))?;
-        writeln!(out, "┌{}┐", "─".repeat(box_width))?;
+    fn render_diagram_placeholder<W: Write>(
+        &self,
+        out: &mut W,
+        kind: DiagramKind,
+        content: &str,
+        indent: usize,
+    ) -> io::Result<()> {
+        let box_width = self.term_width.saturating_sub(indent + 2);
+        let indent_str = " ".repeat(indent);
+        let g = self.glyphs();
+        let rule = g.horizontal.repeat(box_width);
+
+        // Rows are `│ text │`, so text gets the box width minus the two spaces
+        let text_width = box_width.saturating_sub(2);
+        let fit = |text: &str| {
+            if text.width() > text_width {
+                truncate_to_width(text, text_width, g.ellipsis)
+            } else {
+                text.to_string()
+            }
+        };
+
+        // Draw diagram header
+        execute!(out, SetForegroundColor(self.colors.label))?;
+        writeln!(out, "{}{}{}{}", indent_str, g.top_left, rule, g.top_right)?;
         writeln!(
             out,
-            "│ 🧜 Mermaid Diagram {:>width$}│",
-            "",
-            width = box_width - 21
+            "{}{} {} {}",
+            indent_str,
+            g.vertical,
+            pad_to_width(&fit(&g.diagram_title(kind)), text_width),
+            g.vertical
         )?;
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        writeln!(out, "├{}┤", "─".repeat(box_width))?;
+        execute!(out, SetForegroundColor(self.colors.border))?;
+        writeln!(out, "{}{}{}{}", indent_str, g.tee_right, rule, g.tee_left)?;
 
-        // Draw mermaid code
+        // Draw diagram source
         execute!(out, ResetColor)?;
         for line in content.lines() {
-            execute!(out, SetForegroundColor(Color::DarkGrey))?;
-            write!(out, "│ ")?;
-            execute!(out, SetForegroundColor(Color::Cyan))?;
-            let line_display = if line.width() > box_width - 3 {
-                format!("{}...", &line[..box_width.saturating_sub(6)])
-            } else {
-                line.to_string()
-            };
-            write!(out, "{:width$}", line_display, width = box_width - 2)?;
-            execute!(out, SetForegroundColor(Color::DarkGrey))?;
-            writeln!(out, "│")?;
+            execute!(out, SetForegroundColor(self.colors.border))?;
+            write!(out, "{}{} ", indent_str, g.vertical)?;
+            execute!(out, SetForegroundColor(self.colors.accent))?;
+            write!(out, "{}", pad_to_width(&fit(line), text_width))?;
+            execute!(out, SetForegroundColor(self.colors.border))?;
+            writeln!(out, " {}", g.vertical)?;
         }
 
         // Draw footer with hint
-        writeln!(out, "├{}┤", "─".repeat(box_width))?;
-        execute!(out, SetForegroundColor(Color::DarkGrey))?;
-        let hint = "(View rendered diagram: mdp -b)";
-        writeln!(out, "│{:^width$}│", hint, width = box_width)?;
-        writeln!(out, "└{}┘", "─".repeat(box_width))?;
+        writeln!(out, "{}{}{}{}", indent_str, g.tee_right, rule, g.tee_left)?;
+        execute!(out, SetForegroundColor(self.colors.muted))?;
+        let hint = fit(match kind {
+            DiagramKind::Mermaid => "(View rendered diagram: mdp -b)",
+            DiagramKind::Graphviz => "(View rendered diagram: mdp -b --diagrams)",
+        });
+        let left = box_width.saturating_sub(hint.width()) / 2;
+        writeln!(
+            out,
+            "{}{}{}{}{}",
+            indent_str,
+            g.vertical,
+            " ".repeat(left),
+            pad_to_width(&hint, box_width - left),
+            g.vertical
+        )?;
+        writeln!(
+            out,
+            "{}{}{}{}",
+            indent_str, g.bottom_left, rule, g.bottom_right
+        )?;
         execute!(out, ResetColor)?;
         writeln!(out)?;
 
         Ok(())
     }
 }
+
+/// Elements with each footnote reference replaced by its definition's text in
+/// parentheses, and the definitions that were used that way removed
+fn inline_footnotes(elements: &[Element]) -> Vec<Element> {
+    let texts: HashMap<&str, String> = elements
+        .iter()
+        .filter_map(|element| match element {
+            Element::FootnoteDefinition { label, content } => {
+                let text = content
+                    .iter()
+                    .filter_map(|element| match element {
+                        Element::Paragraph { content } => Some(inline_text(content)),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                (!text.is_empty()).then_some((label.as_str(), text))
+            }
+            _ => None,
+        })
+        .collect();
+
+    fn inlines(content: &[InlineElement], texts: &HashMap<&str, String>) -> Vec<InlineElement> {
+        let mut result = Vec::with_capacity(content.len());
+        for inline in content {
+            match inline {
+                InlineElement::FootnoteReference(label) if texts.contains_key(label.as_str()) => {
+                    result.push(InlineElement::Text(" ".to_string()));
+                    result.push(InlineElement::Emphasis(vec![InlineElement::Text(format!(
+                        "({})",
+                        texts[label.as_str()]
+                    ))]));
+                }
+                InlineElement::Strong(content) => {
+                    result.push(InlineElement::Strong(inlines(content, texts)))
+                }
+                InlineElement::Emphasis(content) => {
+                    result.push(InlineElement::Emphasis(inlines(content, texts)))
+                }
+                InlineElement::Strikethrough(content) => {
+                    result.push(InlineElement::Strikethrough(inlines(content, texts)))
+                }
+                InlineElement::Link {
+                    url,
+                    content,
+                    title,
+                } => result.push(InlineElement::Link {
+                    url: url.clone(),
+                    content: inlines(content, texts),
+                    title: title.clone(),
+                }),
+                other => result.push(other.clone()),
+            }
+        }
+        result
+    }
+
+    fn blocks(elements: &[Element], texts: &HashMap<&str, String>) -> Vec<Element> {
+        elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::FootnoteDefinition { label, .. } if texts.contains_key(label.as_str()) => {
+                    None
+                }
+                Element::Paragraph { content } => Some(Element::Paragraph {
+                    content: inlines(content, texts),
+                }),
+                Element::List {
+                    ordered,
+                    start,
+                    items,
+                } => Some(Element::List {
+                    ordered: *ordered,
+                    start: *start,
+                    items: items
+                        .iter()
+                        .map(|item| ListItem {
+                            content: blocks(&item.content, texts),
+                        })
+                        .collect(),
+                }),
+                Element::BlockQuote { content } => Some(Element::BlockQuote {
+                    content: blocks(content, texts),
+                }),
+                Element::Alert { kind, content } => Some(Element::Alert {
+                    kind: *kind,
+                    content: blocks(content, texts),
+                }),
+                other => Some(other.clone()),
+            })
+            .collect()
+    }
+
+    blocks(elements, &texts)
+}
+
+/// Remove ANSI escape sequences (colors, styles) from rendered text
+pub fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        if c == '\x1b' {
+            index += escape_len(&text[index..]);
+        } else {
+            result.push(c);
+            index += c.len_utf8();
+        }
+    }
+    result
+}
+
+/// Collapse runs of blank lines into a single blank line.
+/// Lines holding only escape sequences count as blank; their escapes are kept.
+fn collapse_blank_lines(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut previous_blank = false;
+    for line in text.split_inclusive('\n') {
+        let blank = strip_ansi(line).trim().is_empty();
+        if blank && previous_blank {
+            result.push_str(&escape_sequences(line));
+        } else {
+            result.push_str(line);
+        }
+        previous_blank = blank;
+    }
+    result
+}
+
+/// Only the ANSI escape sequences of `text`
+fn escape_sequences(text: &str) -> String {
+    let mut result = String::new();
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        if c == '\x1b' {
+            let len = escape_len(&text[index..]);
+            result.push_str(&text[index..index + len]);
+            index += len;
+        } else {
+            index += c.len_utf8();
+        }
+    }
+    result
+}
+
+/// Display width of a string, ignoring ANSI escape sequences
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        if c == '\x1b' {
+            index += escape_len(&text[index..]);
+        } else {
+            width += c.width().unwrap_or(0);
+            index += c.len_utf8();
+        }
+    }
+    width
+}
+
+/// Byte length of the ANSI escape sequence at the start of `text` (which begins with ESC)
+fn escape_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    if bytes.get(1) != Some(&b'[') {
+        return 1;
+    }
+    bytes[2..]
+        .iter()
+        .position(|b| (0x40..=0x7e).contains(b))
+        .map_or(bytes.len(), |end| end + 3)
+}
+
+/// Truncate text to fit within `max_width` display columns, ending with `ellipsis`.
+/// Cuts only between characters, so wide (e.g. CJK) characters are never split.
+/// ANSI escape sequences take no width and are kept up to the cut.
+fn truncate_to_width(text: &str, max_width: usize, ellipsis: &str) -> String {
+    // Too narrow for the ellipsis itself: show as much of it as fits
+    if ellipsis.width() > max_width {
+        return ellipsis.chars().take(max_width).collect();
+    }
+
+    let budget = max_width.saturating_sub(ellipsis.width());
+    let mut result = String::new();
+    let mut width = 0;
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        if c == '\x1b' {
+            let len = escape_len(&text[index..]);
+            result.push_str(&text[index..index + len]);
+            index += len;
+            continue;
+        }
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > budget {
+            break;
+        }
+        width += char_width;
+        result.push(c);
+        index += c.len_utf8();
+    }
+    result.push_str(ellipsis);
+    result
+}
+
+/// Pad text with spaces to `width` display columns (unlike `{:width$}`, which counts chars)
+fn pad_to_width(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+/// Greedily wrap text containing ANSI escapes at `width` columns.
+///
+/// The first line starts `offset` columns in; each wrapped line (and each existing
+/// line break) begins with `continuation`, which is `continuation_width` columns wide.
+/// Runs in linear time: word widths are measured once and accumulated incrementally.
+fn wrap_ansi(
+    text: &str,
+    width: usize,
+    offset: usize,
+    continuation: &str,
+    continuation_width: usize,
+) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for (i, line) in text.split('\n').enumerate() {
+        let mut line_width = offset;
+        if i > 0 {
+            result.push('\n');
+            result.push_str(continuation);
+            line_width = continuation_width;
+        }
+
+        for (j, word) in line.split(' ').enumerate() {
+            let word_width = visible_width(word);
+            if j > 0 {
+                if line_width + 1 + word_width <= width {
+                    result.push(' ');
+                    line_width += 1;
+                } else {
+                    result.push('\n');
+                    result.push_str(continuation);
+                    line_width = continuation_width;
+                }
+            }
+
+            if line_width + word_width <= width {
+                result.push_str(word);
+                line_width += word_width;
+                continue;
+            }
+
+            // Word longer than a whole line: break it at the column limit
+            let mut index = 0;
+            while let Some(c) = word[index..].chars().next() {
+                if c == '\x1b' {
+                    let len = escape_len(&word[index..]);
+                    result.push_str(&word[index..index + len]);
+                    index += len;
+                    continue;
+                }
+                let char_width = c.width().unwrap_or(0);
+                if line_width + char_width > width && line_width > continuation_width {
+                    result.push('\n');
+                    result.push_str(continuation);
+                    line_width = continuation_width;
+                }
+                result.push(c);
+                line_width += char_width;
+                index += c.len_utf8();
+            }
+        }
+    }
+
+    result
+}
+
+/// Number of terminal lines a table row takes, given cells with `\n` line breaks
+fn row_height(cells: &[String]) -> usize {
+    cells
+        .iter()
+        .map(|cell| cell.split('\n').count())
+        .max()
+        .unwrap_or(1)
+}
+
+/// Header separator segment for one column.
+/// Alignment markers stand in for the colons of the markdown delimiter row.
+fn separator_segment(width: usize, alignment: Alignment, glyphs: &Glyphs) -> String {
+    let (left, right) = match alignment {
+        Alignment::None => (false, false),
+        Alignment::Left => (true, false),
+        Alignment::Center => (true, true),
+        Alignment::Right => (false, true),
+    };
+    (0..width)
+        .map(|i| {
+            if (left && i == 0) || (right && i + 1 == width) {
+                glyphs.align_marker.to_string()
+            } else {
+                glyphs.horizontal.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Fit a table row to exactly `num_cols` cells.
+/// Missing cells become empty; extra cells are joined into the last column.
+fn normalize_table_row(row: &[String], num_cols: usize) -> Vec<String> {
+    let mut cells: Vec<String> = row.iter().take(num_cols).cloned().collect();
+    if row.len() > num_cols
+        && let Some(last) = cells.last_mut()
+    {
+        for extra in &row[num_cols..] {
+            last.push(' ');
+            last.push_str(extra);
+        }
+    }
+    cells.resize(num_cols, String::new());
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+
+    #[test]
+    fn test_render_to_string_heading_and_paragraph() {
+        let renderer = TerminalRenderer::new("dark");
+        let document = parse_markdown("# Hello\n\nSome paragraph text.");
+        let output = strip_ansi(&renderer.render_to_string(&document, false).unwrap());
+        assert!(output.contains("Hello"), "Heading should be rendered");
+        assert!(
+            output.contains("Some paragraph text."),
+            "Paragraph should be rendered"
+        );
+    }
+
+    #[test]
+    fn test_render_to_string_with_toc() {
+        let renderer = TerminalRenderer::new("dark");
+        let document = parse_markdown("# Intro\n\n## Details\n\nBody");
+        let output = strip_ansi(&renderer.render_to_string(&document, true).unwrap());
+        assert!(output.contains("Table of Contents"));
+        assert!(output.contains("Details"));
+    }
+
+    #[test]
+    fn test_header_shows_path_and_title() {
+        let renderer = TerminalRenderer::new("dark")
+            .with_color(false)
+            .with_header(Some("docs/guide.md".to_string()));
+        let document = parse_markdown("# Guide\n\nBody");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert_eq!(output.lines().next(), Some("docs/guide.md  Guide"));
+
+        let output = TerminalRenderer::new("dark")
+            .with_color(false)
+            .render_to_string(&document, false)
+            .unwrap();
+        assert!(!output.contains("docs/guide.md"));
+
+        // The front matter title wins over a later H1
+        let document = parse_markdown("---\ntitle: FM Title\n---\n# H1 Later\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert_eq!(output.lines().next(), Some("docs/guide.md  FM Title"));
+    }
+
+    #[test]
+    fn test_modified_line() {
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_717_243_200);
+        let renderer = TerminalRenderer::new("dark")
+            .with_color(false)
+            .with_modified(Some(modified));
+        let output = renderer
+            .render_to_string(&parse_markdown("# Guide\n\nBody"), false)
+            .unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[4..7], ["Last updated: 2024-06-01", "", "Body"]);
+    }
+
+    #[test]
+    fn test_no_consecutive_blank_lines() {
+        let document = parse_markdown(concat!(
+            "# Title\n\n## Section\n\n- a\n- b\n\n",
+            "```rust\nfn main() {}\n```\n\n> quote\n\n---\n\nText[^1]\n\n[^1]: Note\n",
+        ));
+        for color in [false, true] {
+            let output = TerminalRenderer::new("dark")
+                .with_color(color)
+                .render_to_string(&document, true)
+                .unwrap();
+            let output = strip_ansi(&output);
+            let lines: Vec<&str> = output.lines().map(str::trim_end).collect();
+            assert!(
+                !lines.windows(2).any(|w| w[0].is_empty() && w[1].is_empty()),
+                "Consecutive blank lines in:\n{}",
+                output
+            );
+            assert!(!output.contains("\n\n\n"));
+        }
+    }
+
+    #[test]
+    fn test_narrow_terminal_does_not_panic() {
+        let document = parse_markdown(concat!(
+            "# A heading that is longer than the terminal\n\n",
+            "```mermaid\ngraph TD\n    A[Start] --> B[A long label that needs truncating]\n```\n\n",
+            "```rust\nfn main() { println!(\"hello\"); }\n```\n\n",
+            "| Name | Description |\n|:-----|------------:|\n| a | a long cell value |\n\n",
+            "> quoted text\n\n- item\n  - nested\n\n---\n",
+        ));
+        for width in 1..=20 {
+            for ascii in [false, true] {
+                TerminalRenderer::new("dark")
+                    .with_width(Some(width))
+                    .with_ascii(ascii)
+                    .with_color(false)
+                    .render_to_string(&document, true)
+                    .unwrap();
+            }
+        }
+
+        let output = TerminalRenderer::new("dark")
+            .with_width(Some(10))
+            .with_color(false)
+            .render_to_string(&document, false)
+            .unwrap();
+        assert!(output.contains("│ graph… │"), "{}", output);
+    }
+
+    #[test]
+    fn test_mermaid_japanese_labels_in_narrow_terminal() {
+        let document = parse_markdown(
+            "```mermaid\ngraph TD\n    A[開始] --> B[データを読み込む]\n    B --> C[終了]\n```\n",
+        );
+        for width in [12, 17, 18, 25] {
+            let output = TerminalRenderer::new("dark")
+                .with_width(Some(width))
+                .with_color(false)
+                .render_to_string(&document, false)
+                .unwrap();
+            let lines: Vec<&str> = output.lines().filter(|l| !l.is_empty()).collect();
+            // Every row of the box, including those with wide characters, lines up
+            for line in &lines {
+                assert_eq!(line.width(), width, "{:?} at width {}", line, width);
+            }
+            assert!(lines.iter().any(|l| l.contains('…')));
+        }
+    }
+
+    #[test]
+    fn test_graphviz_placeholder() {
+        for language in ["dot", "graphviz"] {
+            let output = render_plain(&format!("```{}\ndigraph {{ a -> b }}\n```\n", language));
+            assert!(output.contains("│ ◇ Graphviz Diagram"), "{}", output);
+            assert!(output.contains("│ digraph { a -> b }"));
+            assert!(output.contains("mdp -b --diagrams"));
+        }
+
+        let output = TerminalRenderer::new("dark")
+            .with_color(false)
+            .with_ascii(true)
+            .render_to_string(&parse_markdown("```dot\ndigraph {}\n```\n"), false)
+            .unwrap();
+        assert!(output.contains("| [dot] Graphviz Diagram"));
+    }
+
+    #[test]
+    fn test_inline_footnotes() {
+        let markdown = "A claim[^src] and **bold[^b]**.\n\n- item[^src]\n\n[^src]: The *source*\n    text.\n\n[^b]: Bee.\n";
+        let document = parse_markdown(markdown);
+        let inline = TerminalRenderer::new("dark")
+            .with_color(false)
+            .with_footnotes(FootnoteMode::Inline)
+            .render_to_string(&document, false)
+            .unwrap();
+        assert_eq!(
+            inline,
+            "A claim (The source text.) and bold (Bee.).\n\n• item (The source text.)\n\n"
+        );
+
+        // The default and hover modes keep the section
+        for mode in [FootnoteMode::Section, FootnoteMode::Hover] {
+            let output = TerminalRenderer::new("dark")
+                .with_color(false)
+                .with_footnotes(mode)
+                .render_to_string(&document, false)
+                .unwrap();
+            assert!(output.contains("A claim[^src]"));
+            assert!(output.contains("Bee."));
+        }
+    }
+
+    #[test]
+    fn test_inline_footnote_styling() {
+        let document = parse_markdown("Text[^1]\n\n[^1]: Note.\n");
+        let output = TerminalRenderer::new("dark")
+            .with_footnotes(FootnoteMode::Inline)
+            .render_to_string(&document, false)
+            .unwrap();
+        assert!(output.contains("Text \x1b[3m(Note.)\x1b[23m"));
+    }
+
+    #[test]
+    fn test_math() {
+        let renderer = TerminalRenderer::new("dark").with_color(false);
+        let document = parse_markdown("Area $\\pi r^2$ and $x_1$.\n\n$$\n\\frac{a}{b}\n$$\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert!(output.contains("Area π r² and x₁."));
+        // No Unicode form: raw TeX in a box
+        assert!(output.contains("│ \\frac{a}{b} │"));
+
+        // Color sets the raw TeX apart, so the delimiters go
+        let output = TerminalRenderer::new("dark")
+            .render_to_string(&parse_markdown("See $x^y$ for \\$5"), false)
+            .unwrap();
+        assert!(output.contains(&format!("{}x^y", SetForegroundColor(Color::Green))));
+        assert!(!output.contains("$x^y$"));
+        assert!(output.contains("for $5"));
+    }
+
+    #[test]
+    fn test_alerts() {
+        let document = parse_markdown("> [!WARNING]\n> Mind the gap.\n");
+        let output = TerminalRenderer::new("dark")
+            .with_color(false)
+            .render_to_string(&document, false)
+            .unwrap();
+        assert!(output.contains("  ▌ ⚠️ Warning\n  ▌ Mind the gap."));
+
+        let output = TerminalRenderer::new("dark")
+            .render_to_string(&document, false)
+            .unwrap();
+        assert!(output.contains(&format!("{}  ▌ ", SetForegroundColor(Color::Yellow))));
+    }
+
+    #[test]
+    fn test_math_block() {
+        let document = parse_markdown("$$\n\\alpha +\n\\beta^2\n$$\n");
+        let output = TerminalRenderer::new("dark")
+            .with_width(Some(40))
+            .with_color(false)
+            .render_to_string(&document, false)
+            .unwrap();
+        let lines: Vec<&str> = output.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(
+            lines,
+            [
+                "                ┌─────┐",
+                "                │ α + │",
+                "                │ β²  │",
+                "                └─────┘"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_block_title() {
+        let renderer = TerminalRenderer::new("dark").with_color(false);
+        let document = parse_markdown("```rust title=\"main.rs\"\nfn main() {}\n```\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert!(output.contains("main.rs (rust)"));
+
+        let document = parse_markdown("```title=notes.txt\nhello\n```\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert!(output.contains("notes.txt"));
+        assert!(!output.contains("title="));
+    }
+
+    #[test]
+    fn test_frontmatter_tags() {
+        let renderer = TerminalRenderer::new("dark").with_color(false);
+        let document = parse_markdown("---\ntags: [rust, cli]\n---\n# Title\n\nBody\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        let title = output.find("Title").unwrap();
+        let tags = output.find("[rust] [cli]").unwrap();
+        assert!(title < tags && tags < output.find("Body").unwrap());
+        assert!(!output.contains("tags:"));
+
+        let document = parse_markdown("---\ntitle: x\n---\nBody\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert!(!output.contains('['));
+    }
+
+    #[test]
+    fn test_frontmatter_title_and_byline() {
+        let renderer = TerminalRenderer::new("dark").with_color(false);
+        let document =
+            parse_markdown("---\ntitle: Release Notes\nauthor: Ann\ndate: 2024-01-02\n---\nBody\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        let lines: Vec<&str> = output.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(
+            lines[..4],
+            [
+                "█ Release Notes",
+                "─────────────────",
+                "Ann · 2024-01-02",
+                "Body"
+            ]
+        );
+
+        // A leading H1 keeps its place, with the byline below it
+        let document = parse_markdown("---\ntitle: Notes\nauthor: Ann\n---\n# Heading\n\nBody\n");
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert!(!output.contains("Notes"));
+        let heading = output.find("Heading").unwrap();
+        let byline = output.find("Ann").unwrap();
+        assert!(heading < byline && byline < output.find("Body").unwrap());
+    }
+
+    #[test]
+    fn test_outline() {
+        let renderer = TerminalRenderer::new("dark").with_color(false);
+        let document = parse_markdown(
+            "# Guide\n\nIntro **text**\nhere.\n\n## Install\n\n```\ncargo install\n```\n\n## Usage\n\nRun it.\n",
+        );
+        let outline = renderer.outline(&document, false).unwrap();
+        let rendered = strip_ansi(&renderer.render_to_string(&document, false).unwrap());
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(outline.len(), 3);
+        for entry in &outline {
+            assert!(lines[entry.line - 1].ends_with(&entry.text), "{:?}", entry);
+        }
+        assert_eq!(outline[0].excerpt.as_deref(), Some("Intro text here."));
+        assert_eq!(outline[1].excerpt, None);
+        assert_eq!(outline[2].excerpt.as_deref(), Some("Run it."));
+
+        // With a TOC the headings move down
+        let with_toc = renderer.outline(&document, true).unwrap();
+        assert!(with_toc[0].line > outline[0].line);
+
+        let mut output = Vec::new();
+        renderer
+            .render_outline(&mut output, &document, false)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let printed: Vec<&str> = output.lines().collect();
+        assert_eq!(printed[0], format!("{:>2}  Guide", outline[0].line));
+        assert_eq!(printed[1], "      Intro text here.");
+        assert_eq!(printed[2], format!("{:>2}    Install", outline[1].line));
+        assert!(!output.contains("cargo install"));
+    }
+
+    #[test]
+    fn test_frontmatter_modes() {
+        let document = parse_markdown("---\ntitle: Post\ndraft: true\n---\n# Heading\n");
+        let render = |mode| {
+            TerminalRenderer::new("dark")
+                .with_color(false)
+                .with_frontmatter(mode)
+                .render_to_string(&document, false)
+                .unwrap()
+        };
+
+        let hidden = render(FrontMatterMode::Hide);
+        assert!(!hidden.contains("Post"));
+
+        let shown = render(FrontMatterMode::Show);
+        let row = shown.lines().find(|line| line.contains("title")).unwrap();
+        assert!(row.contains("Post"));
+        assert!(
+            shown
+                .lines()
+                .any(|line| line.contains("draft") && line.contains("true"))
+        );
+        assert!(shown.find("Post").unwrap() < shown.find("Heading").unwrap());
+
+        let raw = render(FrontMatterMode::Raw);
+        assert!(raw.contains("yaml"));
+        assert!(raw.contains("title: Post"));
+        assert!(raw.contains("draft: true"));
+        assert!(raw.find("draft: true").unwrap() < raw.find("Heading").unwrap());
+    }
+
+    #[test]
+    fn test_collapse_blank_lines_keeps_escapes() {
+        let text = "a\n\n\x1b[0m\n\nb\n";
+        assert_eq!(collapse_blank_lines(text), "a\n\n\x1b[0mb\n");
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[1m\x1b[38;5;13mbold\x1b[0m text"),
+            "bold text"
+        );
+        assert_eq!(strip_ansi("no escapes │ here"), "no escapes │ here");
+    }
+
+    fn render_plain(markdown: &str) -> String {
+        TerminalRenderer::new("dark")
+            .with_color(false)
+            .render_to_string(&parse_markdown(markdown), false)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_light_theme_colors() {
+        let document = parse_markdown("# Title\n\nSee [docs](https://example.com) and `code`.\n");
+        let render =
+            |renderer: TerminalRenderer| renderer.render_to_string(&document, false).unwrap();
+
+        let dark = render(TerminalRenderer::new("dark"));
+        let h1 = |color| {
+            format!(
+                "{}{}█ ",
+                SetForegroundColor(color),
+                SetAttribute(Attribute::Bold)
+            )
+        };
+        assert!(dark.contains(&h1(Color::Magenta)));
+        assert!(dark.contains(&format!("{}`code`", SetForegroundColor(Color::Yellow))));
+
+        let light = render(TerminalRenderer::new("light"));
+        assert!(light.contains(&h1(Color::DarkMagenta)));
+        assert!(light.contains(&format!("{}docs", SetForegroundColor(Color::DarkBlue))));
+        assert!(light.contains(&format!("{}`code`", SetForegroundColor(Color::DarkRed))));
+
+        let custom = render(TerminalRenderer::new("light").with_colors(TerminalTheme {
+            code: Color::Rgb { r: 1, g: 2, b: 3 },
+            ..TerminalTheme::light()
+        }));
+        assert!(custom.contains(&format!(
+            "{}`code`",
+            SetForegroundColor(Color::Rgb { r: 1, g: 2, b: 3 })
+        )));
+    }
+
+    #[test]
+    fn test_horizontal_rule_styles() {
+        let document = parse_markdown("---\n\nText\n\n---\n\nMore\n");
+        let render = |rule| {
+            TerminalRenderer::new("dark")
+                .with_color(false)
+                .with_width(Some(20))
+                .with_style(TerminalStyle {
+                    rule,
+                    ..TerminalStyle::default()
+                })
+                .render_to_string(&document, false)
+                .unwrap()
+        };
+
+        // No blank line above a leading rule, one on each side otherwise
+        let heavy = "━".repeat(20);
+        assert_eq!(
+            render(RuleStyle::Heavy),
+            format!("{heavy}\n\nText\n\n{heavy}\n\nMore\n\n")
+        );
+        let light = "─".repeat(20);
+        assert!(render(RuleStyle::Light).starts_with(&format!("{light}\n\nText\n\n{light}\n")));
+        assert!(render(RuleStyle::Dots).starts_with("       * * *\n\nText\n"));
+    }
+
+    #[test]
+    fn test_render_element_to_string() {
+        let document = parse_markdown("# Title\n\nSome *changed* text.\n");
+        let renderer = TerminalRenderer::new("dark").with_color(false);
+        let output = renderer
+            .render_element_to_string(&document.elements[1])
+            .unwrap();
+        assert_eq!(output, "Some changed text.\n\n");
+    }
+
+    #[test]
+    fn test_code_styles() {
+        let render = |style| {
+            TerminalRenderer::new("dark")
+                .with_color(false)
+                .with_width(Some(20))
+                .with_code_style(style)
+                .render_to_string(&parse_markdown("```rust\nlet x = 1;\n```\n"), false)
+                .unwrap()
+        };
+
+        assert!(render(CodeStyle::Boxed).starts_with("┌"));
+        assert_eq!(render(CodeStyle::Bordered), "│ rust\n│ let x = 1;\n\n");
+        assert_eq!(render(CodeStyle::Minimal), "rust\nlet x = 1;\n\n");
+        let plain = render(CodeStyle::Plain);
+        assert_eq!(plain, "let x = 1;\n\n");
+        assert!(!plain.contains(['┌', '│', '└', '─']));
+
+        // Piped output drops the box unless a style is asked for
+        assert_eq!(
+            render(CodeStyle::default_for(true)),
+            render(CodeStyle::Boxed)
+        );
+        assert!(!render(CodeStyle::default_for(false)).contains('│'));
+    }
+
+    #[test]
+    fn test_kbd() {
+        let output = render_plain("Press <kbd>Ctrl</kbd>+<kbd>C</kbd>");
+        assert_eq!(output, "Press [Ctrl]+[C]\n\n");
+    }
+
+    #[test]
+    fn test_task_item_with_bold_link() {
+        let document = parse_markdown("- [x] **[Fix #123](https://x.io) now** done\n");
+        let output = TerminalRenderer::new("dark")
+            .render_to_string(&document, false)
+            .unwrap();
+        let line = output.lines().next().unwrap();
+        let styled = &line[line.find('☑').unwrap()..];
+
+        // The checkbox restores only the color, leaving later styling intact
+        assert!(styled.starts_with("☑ \x1b[39m\x1b[1m\x1b[4m\x1b[38;5;12mFix #123"));
+        // Bold comes back after the link's URL, and ends with the strong text
+        assert!(styled.contains("(https://x.io)\x1b[1m\x1b[39m now\x1b[22m done"));
+        assert!(!styled.contains("\x1b[0m"));
+        assert!(!styled.contains("\x1b[21m"));
+        assert_eq!(strip_ansi(line), "• ☑ Fix #123 (https://x.io) now done");
+    }
+
+    #[test]
+    fn test_nested_ordered_list_numbering() {
+        let output = render_plain("- a\n  1. one\n  2. two\n- b\n  1. again\n");
+        assert_eq!(output, "• a\n  1. one\n  2. two\n• b\n  1. again\n\n");
+
+        let output = render_plain("- x\n  - y\n\n    3. three\n    4. four\n");
+        assert_eq!(output, "• x\n  ◦ y\n    3. three\n    4. four\n\n");
+    }
+
+    #[test]
+    fn test_list_item_continuation_paragraphs() {
+        let output = render_plain(
+            "10. first para\n    wraps here\n\n    second para\n\n    - nested\n11. next\n",
+        );
+        assert_eq!(
+            output,
+            "10. first para\n    wraps here\n\n    second para\n    ◦ nested\n11. next\n\n"
+        );
+
+        // Code blocks hang under the item text too
+        let output = render_plain("- item\n\n  ```\n  code\n  ```\n");
+        assert!(output.contains("\n\n  ┌"));
+        assert!(output.contains("\n  │ code\n"));
+    }
+
+    #[test]
+    fn test_nested_bullets_cycle_by_depth() {
+        let output = render_plain("- a\n  - b\n    - c\n      - d\n");
+        assert_eq!(output, "• a\n  ◦ b\n    ▪ c\n      • d\n\n");
+    }
+
+    #[test]
+    fn test_no_color_output_has_no_escapes() {
+        let renderer = TerminalRenderer::new("dark").with_color(false);
+        let document = parse_markdown(
+            "# Title\n\n**bold** and [link](https://example.com)\n\n```rust\nfn main() {}\n```\n\n> quote",
+        );
+        let output = renderer.render_to_string(&document, true).unwrap();
+        assert!(
+            !output.contains("\x1b["),
+            "Plain output must not contain ANSI escapes"
+        );
+        assert!(output.contains("fn main() {}"));
+    }
+
+    #[test]
+    fn test_default_lang_highlights_indented_block() {
+        let document = parse_markdown("Example:\n\n    fn main() {\n        let x = 1;\n    }\n");
+        let count_colors = |output: String| output.matches("\x1b[38;2;").count();
+
+        let plain_output = TerminalRenderer::new("dark")
+            .render_to_string(&document, false)
+            .unwrap();
+        let rust_output = TerminalRenderer::new("dark")
+            .with_default_lang(Some("rust".to_string()))
+            .render_to_string(&document, false)
+            .unwrap();
+
+        assert!(
+            count_colors(rust_output) > count_colors(plain_output),
+            "Indented block should be highlighted as Rust"
+        );
+    }
+
+    #[test]
+    fn test_syntax_theme() {
+        let document = parse_markdown("```rust\nfn main() {}\n```\n");
+        let render =
+            |renderer: TerminalRenderer| renderer.render_to_string(&document, false).unwrap();
+
+        assert!(TerminalRenderer::syntax_themes().contains(&"InspiredGitHub".to_string()));
+        assert_eq!(
+            TerminalRenderer::new("light").syntax_theme,
+            "base16-ocean.light"
+        );
+        let default_output = render(TerminalRenderer::new("dark"));
+        let github =
+            TerminalRenderer::new("dark").with_syntax_theme(Some("InspiredGitHub".to_string()));
+        assert_eq!(github.syntax_theme, "InspiredGitHub");
+        assert_ne!(render(github), default_output);
+
+        // Unknown names keep the default
+        let unknown =
+            TerminalRenderer::new("dark").with_syntax_theme(Some("No Such Theme".to_string()));
+        assert_eq!(unknown.syntax_theme, "base16-ocean.dark");
+        assert_eq!(render(unknown), default_output);
+    }
+
+    #[test]
+    fn test_multi_line_table_cell() {
+        let output = render_plain("| Key | Value |\n|-----|-------|\n| a | one<br>two |\n");
+        assert_eq!(
+            output,
+            concat!(
+                "┌─────┬───────┐\n",
+                "│ Key │ Value │\n",
+                "├─────┼───────┤\n",
+                "│ a   │ one   │\n",
+                "│     │ two   │\n",
+                "└─────┴───────┘\n\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_styled_table_cells() {
+        let document = parse_markdown(
+            "| **Key** | Value |\n|---|--:|\n| `a` | *one* and **two** |\n| b | [link](https://example.com/a/very/long/path) |\n",
+        );
+        let output = TerminalRenderer::new("dark")
+            .with_width(Some(40))
+            .render_to_string(&document, false)
+            .unwrap();
+        assert!(output.contains(&format!("{}two", SetAttribute(Attribute::Bold))));
+
+        // Escape codes take no columns, so the borders still line up
+        let plain = strip_ansi(&output);
+        let lines: Vec<&str> = plain.lines().filter(|l| !l.is_empty()).collect();
+        for line in &lines {
+            assert_eq!(line.width(), lines[0].width(), "{:?}", line);
+        }
+        assert!(plain.contains("│ `a` │"));
+        assert!(plain.contains("one and two │"));
+        assert!(plain.contains("│ link (https://example.com/a/v… │"));
+    }
+
+    #[test]
+    fn test_ragged_table_rows_keep_borders_aligned() {
+        let cells = |v: &[&str]| {
+            v.iter()
+                .map(|c| vec![InlineElement::Text(c.to_string())])
+                .collect::<Vec<_>>()
+        };
+        let document = Document {
+            elements: vec![Element::Table {
+                headers: cells(&["Name", "Kind", "Notes"]),
+                alignments: vec![Alignment::Left; 3],
+                rows: vec![cells(&["a", "b"]), cells(&["c", "d", "e", "f"])],
+            }],
+            ..Default::default()
+        };
+        let output = TerminalRenderer::new("dark")
+            .with_color(false)
+            .render_to_string(&document, false)
+            .unwrap();
+
+        let lines: Vec<&str> = output.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines.len(), 6);
+        let width = lines[0].width();
+        for line in &lines {
+            assert_eq!(line.width(), width, "Misaligned table line: {:?}", line);
+            // Three columns means four vertical borders on every line
+            let borders = ['│', '┌', '┬', '┐', '├', '┼', '┤', '└', '┴', '┘'];
+            assert_eq!(line.matches(borders).count(), 4, "{:?}", line);
+        }
+        assert!(
+            output.contains("e f"),
+            "Extra cells merge into the last column"
+        );
+    }
+
+    #[test]
+    fn test_table_separator_shows_alignment() {
+        let document = parse_markdown("| L | C | R | N |\n|:--|:-:|--:|---|\n| a | b | c | d |");
+        let output = TerminalRenderer::new("dark")
+            .with_color(false)
+            .render_to_string(&document, false)
+            .unwrap();
+        let separator = output.lines().find(|l| l.starts_with('├')).unwrap();
+        assert_eq!(separator, "├━────┼━───━┼────━┼─────┤");
+    }
+
+    #[test]
+    fn test_ascii_mode_output_is_ascii() {
+        let document = parse_markdown(concat!(
+            "# Title\n\n## Section\n\n",
+            "- item\n  - nested\n- [x] done\n- [ ] todo\n\n",
+            "> quoted\n\n",
+            "| a | b |\n|:--|--:|\n| 1 | 2 |\n\n",
+            "```rust\nfn main() {}\n```\n\n",
+            "```mermaid\ngraph TD; A-->B\n```\n\n",
+            "![logo](logo.png)\n\n---\n\nNote[^1]\n\n[^1]: Footnote\n",
+        ));
+        let output = TerminalRenderer::new("dark")
+            .with_color(false)
+            .with_ascii(true)
+            .render_to_string(&document, true)
+            .unwrap();
+        let non_ascii: String = output.chars().filter(|c| !c.is_ascii()).collect();
+        assert!(non_ascii.is_empty(), "Non-ASCII output: {:?}", non_ascii);
+        assert!(output.contains("[TOC] Table of Contents"));
+        assert!(output.contains("# Title"));
+        assert!(output.contains("+:----+----:+"));
+    }
+
+    #[test]
+    fn test_normalize_table_row() {
+        let row = vec!["a".to_string()];
+        assert_eq!(normalize_table_row(&row, 3), vec!["a", "", ""]);
+        let row: Vec<String> = ["a", "b", "c"].iter().map(|c| c.to_string()).collect();
+        assert_eq!(normalize_table_row(&row, 2), vec!["a", "b c"]);
+    }
+
+    #[test]
+    fn test_huge_single_line_renders_quickly() {
+        // About 1 MB of minified JSON on one line
+        let line = r#"{"key": "value", "n": [1, 2, 3]}, "#.repeat(30_000);
+        let renderer = TerminalRenderer::new("dark").with_width(Some(80));
+
+        for markdown in [
+            format!("Text {}\n", line),
+            format!("```json\n{}\n```\n", line),
+            format!("```mermaid\n{}\n```\n", line),
+        ] {
+            let document = parse_markdown(&markdown);
+            let start = std::time::Instant::now();
+            let output = renderer.render_to_string(&document, false).unwrap();
+            let elapsed = start.elapsed();
+            assert!(
+                elapsed < std::time::Duration::from_secs(1),
+                "took {:?} for {}",
+                elapsed,
+                &markdown[..10]
+            );
+            assert!(!output.is_empty());
+        }
+
+        // Wrapped to the width, and the long code line is left unhighlighted
+        let document = parse_markdown(&format!("Text {}\n", line));
+        let output = strip_ansi(&renderer.render_to_string(&document, false).unwrap());
+        assert!(output.lines().all(|l| l.width() <= 80));
+        let document = parse_markdown(&format!("```json\n{}\n```\n", line));
+        let output = renderer.render_to_string(&document, false).unwrap();
+        assert!(!output.contains("\x1b[38;2;"));
+    }
+
+    #[test]
+    fn test_wrap_ansi_breaks_at_width() {
+        let wrapped = wrap_ansi("one two three four", 9, 0, "", 0);
+        assert_eq!(wrapped, "one two\nthree\nfour");
+    }
+
+    #[test]
+    fn test_wrap_ansi_ignores_escapes_and_indents() {
+        let text = "\x1b[1mbold\x1b[22m words here";
+        let wrapped = wrap_ansi(text, 12, 2, "  ", 2);
+        assert_eq!(wrapped, "\x1b[1mbold\x1b[22m words\n  here");
+    }
+
+    #[test]
+    fn test_width_is_clamped_to_terminal() {
+        let renderer = |width| {
+            let mut renderer = TerminalRenderer::new("dark");
+            renderer.screen_detected = true;
+            renderer.screen_width = 100;
+            renderer.with_width(Some(width))
+        };
+        assert_eq!(renderer(100_000).term_width, 100);
+        assert_eq!(renderer(40).term_width, 40);
+    }
+
+    #[test]
+    fn test_screen_width_fallbacks() {
+        assert_eq!(screen_width(Some(100), Some("120")), (100, true));
+        // Not a terminal: COLUMNS, then 80
+        assert_eq!(screen_width(None, Some("120")), (120, false));
+        assert_eq!(screen_width(None, Some("wide")), (80, false));
+        assert_eq!(screen_width(None, None), (80, false));
+
+        // --width wins when there is no terminal to fit
+        let mut renderer = TerminalRenderer::new("dark");
+        renderer.screen_detected = false;
+        renderer.screen_width = 80;
+        assert_eq!(renderer.with_width(Some(120)).term_width, 120);
+    }
+
+    #[test]
+    fn test_center_margin() {
+        let mut renderer = TerminalRenderer::new("dark").with_align(ContentAlign::Center);
+        renderer.screen_width = 100;
+        renderer.term_width = 60;
+        assert_eq!(renderer.margin(), 20);
+        assert_eq!(renderer.with_align(ContentAlign::Left).margin(), 0);
+    }
+}
@@ -1,31 +1,68 @@
 use axum::{
     Json, Router,
     extract::{
-        Query, State,
+        Query, Request, State,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
     http::{HeaderMap, StatusCode, header},
     response::{Html, IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use tokio::sync::{RwLock, broadcast};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OnceCell, RwLock, broadcast};
+use tower_http::trace::TraceLayer;
+use tracing::Span;
 
-use crate::files::FileTree;
-use crate::renderer::html::HtmlRenderer;
+use crate::error::{Error, Result};
+use crate::files::{FileTree, read_lossy, warn_if_lossy};
+use crate::instance::{Instance, OpenRequest, OpenResponse};
+use crate::manifest::Manifest;
+use crate::parser::{FrontMatter, TocEntry, split_frontmatter, toggle_task};
+use crate::renderer::html::{HtmlRenderer, encode_query_value};
 use crate::watcher::watch_file_async;
 
 /// Timeout in seconds before shutting down when all clients disconnect
 const SHUTDOWN_TIMEOUT_SECS: u64 = 3;
 
+/// Interval in seconds between server pings to each WebSocket client
+const PING_INTERVAL_SECS: u64 = 15;
+
+/// Seconds to wait for a pong before treating a client as gone (e.g. a tab killed
+/// by laptop sleep, which never sends a close frame)
+const PONG_TIMEOUT_SECS: u64 = 10;
+
+/// Number of files whose rendered content is kept in memory
+const RENDER_CACHE_CAPACITY: usize = 256;
+
+/// Page shown for unknown routes and files that no longer exist
+const NOT_FOUND_MARKDOWN: &str = "# Page not found
+
+The page you requested does not exist. The file may have been renamed or deleted.
+
+[Back to index](/)
+";
+
+/// Page shown when there are no markdown files to display
+const NO_FILE_MARKDOWN: &str = "# No file selected
+
+There are no markdown files to show here yet.
+";
+
 #[derive(Serialize)]
 pub struct FileInfo {
     pub path: String,
     pub name: String,
     pub is_dir: bool,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    /// Modified within the `--since` window
+    pub recent: bool,
 }
 
 #[derive(Serialize)]
@@ -34,103 +71,630 @@ pub struct FileListResponse {
     pub base_path: String,
 }
 
+/// Folder or file in the nested tree served by `/api/tree`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TreeNode {
+    Folder {
+        name: String,
+        path: String,
+        children: Vec<TreeNode>,
+    },
+    File {
+        name: String,
+        path: String,
+        title: Option<String>,
+        /// Last modification as seconds since the Unix epoch
+        modified: Option<u64>,
+        /// Modified within the `--since` window
+        recent: bool,
+    },
+}
+
+#[derive(Serialize)]
+pub struct TreeResponse {
+    pub children: Vec<TreeNode>,
+    pub base_path: String,
+}
+
+#[derive(Serialize)]
+pub struct ContentJsonResponse {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+}
+
+#[derive(Serialize)]
+pub struct TagInfo {
+    pub name: String,
+    /// Relative paths of the files with this tag
+    pub files: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TagsResponse {
+    pub tags: Vec<TagInfo>,
+}
+
 #[derive(Deserialize)]
 pub struct ViewQuery {
     pub file: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct ScrollToQuery {
+    /// Relative path of the file; any open file when omitted
+    pub file: Option<String>,
+    /// 1-based source line
+    pub line: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ToggleTaskRequest {
+    /// Relative path of the file; the default file when omitted
+    pub file: Option<String>,
+    /// Ordinal of the task checkbox in document order
+    pub index: usize,
+}
+
 /// Message types for WebSocket communication
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum WsMessage {
-    Reload,
+    /// Content changed; carries the relative path of the changed file when known
+    Reload(Option<String>),
     TreeUpdate,
+    /// Scroll to the block covering a 1-based source line, optionally in a given file
+    ScrollTo {
+        line: usize,
+        file: Option<String>,
+    },
+    /// Show a file, handed over by a later `mdp --reuse`
+    Open(Option<String>),
+}
+
+impl WsMessage {
+    /// Text sent over the socket: `reload`, `reload:<path>`, `tree-update`,
+    /// `scroll:<line>` / `scroll:<line>:<path>`, or `open` / `open:<path>`
+    pub fn to_text(&self) -> String {
+        match self {
+            WsMessage::Reload(None) => "reload".to_string(),
+            WsMessage::Reload(Some(path)) => format!("reload:{}", path),
+            WsMessage::TreeUpdate => "tree-update".to_string(),
+            WsMessage::ScrollTo { line, file: None } => format!("scroll:{}", line),
+            WsMessage::ScrollTo {
+                line,
+                file: Some(path),
+            } => format!("scroll:{}:{}", line, path),
+            WsMessage::Open(None) => "open".to_string(),
+            WsMessage::Open(Some(path)) => format!("open:{}", path),
+        }
+    }
 }
 
 pub struct ServerState {
     pub file_tree: RwLock<FileTree>,
     pub base_path: PathBuf,
-    pub title: String,
     pub reload_tx: broadcast::Sender<WsMessage>,
     pub shutdown_tx: broadcast::Sender<()>,
     pub connection_count: AtomicUsize,
-    pub show_toc: bool,
+    /// Bumped on every connect and last disconnect; a shutdown timer only fires
+    /// if it is unchanged since the timer was armed
+    pub connection_generation: AtomicU64,
+    /// Renders every page, configured once from the command line
+    pub renderer: HtmlRenderer,
+    /// Rendered content by relative path and content hash
+    pub render_cache: RenderCache,
+}
+
+/// Content HTML and TOC of a file
+type Rendered = (String, Vec<TocEntry>);
+
+/// Rendered files keyed by relative path and a hash of the content they were
+/// rendered from, so an entry is only reused while the file's bytes are unchanged.
+/// Holds at most `capacity` files, dropping the least recently used. Concurrent
+/// requests for the same content wait for a single render.
+pub struct RenderCache {
+    capacity: usize,
+    inner: Mutex<RenderCacheInner>,
+}
+
+#[derive(Default)]
+struct RenderCacheInner {
+    entries: HashMap<String, RenderCacheEntry>,
+    /// Incremented on every lookup to order entries by last use
+    clock: u64,
+}
+
+struct RenderCacheEntry {
+    hash: u64,
+    last_used: u64,
+    render: Arc<OnceCell<Rendered>>,
+}
+
+impl Default for RenderCache {
+    fn default() -> Self {
+        Self::new(RENDER_CACHE_CAPACITY)
+    }
+}
+
+impl RenderCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(RenderCacheInner::default()),
+        }
+    }
+
+    /// The cached render of `content`, calling `render` if there is none
+    pub async fn get_or_render(
+        &self,
+        relative_path: &str,
+        content: &str,
+        render: impl FnOnce() -> Rendered,
+    ) -> Rendered {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let cell = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.clock += 1;
+            let clock = inner.clock;
+            match inner.entries.get_mut(relative_path) {
+                Some(entry) if entry.hash == hash => {
+                    entry.last_used = clock;
+                    entry.render.clone()
+                }
+                _ => {
+                    let cell = Arc::new(OnceCell::new());
+                    inner.entries.insert(
+                        relative_path.to_string(),
+                        RenderCacheEntry {
+                            hash,
+                            last_used: clock,
+                            render: cell.clone(),
+                        },
+                    );
+                    if inner.entries.len() > self.capacity
+                        && let Some(oldest) = inner
+                            .entries
+                            .iter()
+                            .min_by_key(|(_, entry)| entry.last_used)
+                            .map(|(path, _)| path.clone())
+                    {
+                        inner.entries.remove(&oldest);
+                    }
+                    cell
+                }
+            }
+        };
+        // Lock released here; other requests for this content wait on the cell
+        cell.get_or_init(|| async { render() }).await.clone()
+    }
+
+    /// Drop the cached render of one file, or of every file when `relative_path` is `None`
+    pub fn invalidate(&self, relative_path: Option<&str>) {
+        let mut inner = self.inner.lock().unwrap();
+        match relative_path {
+            Some(path) => {
+                inner.entries.remove(path);
+            }
+            None => inner.entries.clear(),
+        }
+    }
+
+    /// Number of files with a cached render
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl ServerState {
-    async fn render_html(&self, file_path: Option<&str>) -> String {
+    async fn render_html(&self, file_path: Option<&str>) -> (StatusCode, String) {
         // Get file info while holding lock briefly
-        let (absolute_path, relative_path, is_single_file, file_tree_clone) = {
+        let file = {
             let file_tree = self.file_tree.read().await;
             let file = if let Some(path) = file_path {
                 file_tree.find_file(path)
             } else {
-                file_tree.default_file()
+                match file_tree.index_file() {
+                    Some(file) => Some(file),
+                    // No README or index: list the files instead of picking one
+                    None if !file_tree.files.is_empty() => {
+                        let html = self.renderer.landing_page(&file_tree);
+                        drop(file_tree);
+                        return (StatusCode::OK, self.wrap_page(&html, None).await);
+                    }
+                    None => None,
+                }
             };
-
-            if let Some(f) = file {
+            file.map(|f| {
                 (
-                    Some(f.absolute_path.clone()),
-                    Some(f.relative_path.to_string_lossy().to_string()),
-                    file_tree.is_single_file(),
-                    if file_tree.is_single_file() {
-                        None
-                    } else {
-                        Some(file_tree.clone())
-                    },
+                    f.absolute_path.clone(),
+                    f.relative_path.to_string_lossy().to_string(),
                 )
-            } else {
-                (None, None, file_tree.is_single_file(), None)
-            }
+            })
         };
         // Lock released here, now do I/O
 
-        let (content, current_file) = if let Some(path) = absolute_path {
-            let content = std::fs::read_to_string(&path).unwrap_or_default();
-            (content, relative_path)
-        } else {
-            ("# No file selected".to_string(), None)
+        let content = match file {
+            Some((absolute_path, relative_path)) => self
+                .render_file(&relative_path, &absolute_path)
+                .await
+                .map(|(html, _)| (html, relative_path)),
+            None => None,
         };
 
-        let renderer = HtmlRenderer::new(&self.title).with_toc(self.show_toc);
+        match content {
+            Some((html, relative_path)) => (
+                StatusCode::OK,
+                self.wrap_page(&html, Some(&relative_path)).await,
+            ),
+            None if file_path.is_some() => (
+                StatusCode::NOT_FOUND,
+                self.render_page(NOT_FOUND_MARKDOWN, None).await,
+            ),
+            None => (
+                StatusCode::OK,
+                self.render_page(NO_FILE_MARKDOWN, None).await,
+            ),
+        }
+    }
 
-        if is_single_file {
-            renderer.render(&content)
-        } else if let Some(tree) = file_tree_clone {
-            renderer.render_with_sidebar(&content, &tree, current_file.as_deref())
-        } else {
-            renderer.render(&content)
+    /// Render a full page around `markdown`, with the sidebar in directory mode
+    async fn render_page(&self, markdown: &str, current_file: Option<&str>) -> String {
+        let html = self.renderer.render_content(markdown);
+        self.wrap_page(&html, current_file).await
+    }
+
+    /// Wrap rendered content HTML in a full page, with the sidebar in directory mode
+    async fn wrap_page(&self, html: &str, current_file: Option<&str>) -> String {
+        let (file_tree, file_path) = {
+            let file_tree = self.file_tree.read().await;
+            if file_tree.is_single_file() {
+                let path = current_file
+                    .and_then(|path| file_tree.find_file(path))
+                    .map(|file| file.absolute_path.clone());
+                (None, path)
+            } else {
+                (Some(file_tree.clone()), None)
+            }
+        };
+
+        let renderer = &self.renderer;
+        match file_tree {
+            Some(tree) => renderer.page_with_sidebar(html, &tree, current_file),
+            None => {
+                // Read afresh, since the title may have been edited since the tree was built
+                let title =
+                    file_path
+                        .and_then(|path| read_lossy(&path).ok())
+                        .and_then(|(content, _)| {
+                            split_frontmatter(&content)
+                                .0
+                                .and_then(|frontmatter| frontmatter.title().map(String::from))
+                        });
+                renderer.page_titled(title.as_deref().unwrap_or(renderer.title()), html)
+            }
         }
     }
 
+    /// Content HTML and TOC of a file, from the cache while its content is unchanged
+    async fn render_file(&self, relative_path: &str, absolute_path: &Path) -> Option<Rendered> {
+        let (content, lossy) = read_lossy(absolute_path).ok()?;
+        let modified = std::fs::metadata(absolute_path)
+            .and_then(|m| m.modified())
+            .ok();
+        let renderer = &self.renderer;
+        let (content_html, toc) = self
+            .render_cache
+            .get_or_render(relative_path, &content, || {
+                warn_if_lossy(absolute_path, lossy);
+                renderer.render_to_html_string(&content)
+            })
+            .await;
+        let html =
+            renderer.edit_link(relative_path) + &renderer.modified_line(modified) + &content_html;
+        Some((html, toc))
+    }
+
+    /// Drop the cached render of one file, or of every file when `relative_path` is `None`
+    pub fn invalidate_render(&self, relative_path: Option<&str>) {
+        self.render_cache.invalidate(relative_path);
+    }
+
     async fn render_content_only(&self, file_path: &str) -> Option<String> {
         // Get file path while holding lock briefly
-        let absolute_path = {
+        let (absolute_path, relative_path) = {
             let file_tree = self.file_tree.read().await;
-            file_tree.find_file(file_path)?.absolute_path.clone()
+            let file = file_tree.find_file(file_path)?;
+            (
+                file.absolute_path.clone(),
+                file.relative_path.to_string_lossy().to_string(),
+            )
         };
         // Lock released here, now do I/O
 
-        let content = std::fs::read_to_string(&absolute_path).ok()?;
-        let renderer = HtmlRenderer::new(&self.title).with_toc(self.show_toc);
-        Some(renderer.render_content(&content))
+        Some(self.render_file(&relative_path, &absolute_path).await?.0)
+    }
+
+    async fn render_content_json(&self, file_path: &str) -> Option<ContentJsonResponse> {
+        let (absolute_path, relative_path) = {
+            let file_tree = self.file_tree.read().await;
+            let file = file_tree.find_file(file_path)?;
+            (
+                file.absolute_path.clone(),
+                file.relative_path.to_string_lossy().to_string(),
+            )
+        };
+
+        let (html, toc) = self.render_file(&relative_path, &absolute_path).await?;
+        Some(ContentJsonResponse { html, toc })
+    }
+
+    /// The file tree nested by folder: files first in sidebar order, then folders by name
+    async fn tree_nodes(&self) -> Vec<TreeNode> {
+        let file_tree = self.file_tree.read().await;
+        let mut root: Vec<TreeNode> = Vec::new();
+        for file in &file_tree.files {
+            let mut nodes = &mut root;
+            let mut path = PathBuf::new();
+            for dir in file.relative_path.parent().into_iter().flat_map(Path::iter) {
+                path.push(dir);
+                let name = dir.to_string_lossy();
+                let index = match nodes
+                    .iter()
+                    .position(|node| matches!(node, TreeNode::Folder { name: n, .. } if *n == name))
+                {
+                    Some(index) => index,
+                    None => {
+                        nodes.push(TreeNode::Folder {
+                            name: name.to_string(),
+                            path: path.to_string_lossy().to_string(),
+                            children: Vec::new(),
+                        });
+                        nodes.len() - 1
+                    }
+                };
+                let TreeNode::Folder { children, .. } = &mut nodes[index] else {
+                    unreachable!("folder nodes are only matched by name");
+                };
+                nodes = children;
+            }
+            nodes.push(TreeNode::File {
+                name: file.name.clone(),
+                path: file.relative_path.to_string_lossy().to_string(),
+                title: file.title.clone(),
+                modified: file.modified_secs(),
+                recent: self
+                    .renderer
+                    .since()
+                    .is_some_and(|since| file.modified_within(since)),
+            });
+        }
+
+        fn sort(nodes: &mut [TreeNode]) {
+            nodes.sort_by(|a, b| match (a, b) {
+                (TreeNode::File { .. }, TreeNode::File { .. }) => std::cmp::Ordering::Equal,
+                (TreeNode::File { .. }, TreeNode::Folder { .. }) => std::cmp::Ordering::Less,
+                (TreeNode::Folder { .. }, TreeNode::File { .. }) => std::cmp::Ordering::Greater,
+                (TreeNode::Folder { name: a, .. }, TreeNode::Folder { name: b, .. }) => a.cmp(b),
+            });
+            for node in nodes {
+                if let TreeNode::Folder { children, .. } = node {
+                    sort(children);
+                }
+            }
+        }
+        sort(&mut root);
+        root
+    }
+
+    /// All front matter tags across the tree, sorted by name
+    async fn collect_tags(&self) -> Vec<TagInfo> {
+        let files: Vec<(PathBuf, String)> = {
+            let file_tree = self.file_tree.read().await;
+            file_tree
+                .files
+                .iter()
+                .map(|f| {
+                    (
+                        f.absolute_path.clone(),
+                        f.relative_path.to_string_lossy().to_string(),
+                    )
+                })
+                .collect()
+        };
+
+        let mut tags: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (absolute_path, relative_path) in files {
+            let Ok((content, _)) = read_lossy(&absolute_path) else {
+                continue;
+            };
+            let file_tags = split_frontmatter(&content)
+                .0
+                .as_ref()
+                .map(FrontMatter::tags)
+                .unwrap_or_default();
+            for tag in file_tags {
+                let files = tags.entry(tag).or_default();
+                if !files.contains(&relative_path) {
+                    files.push(relative_path.clone());
+                }
+            }
+        }
+
+        tags.into_iter()
+            .map(|(name, files)| TagInfo { name, files })
+            .collect()
+    }
+
+    /// Flip a task checkbox in the source file and notify clients to reload it
+    async fn toggle_task(
+        &self,
+        file_path: Option<&str>,
+        index: usize,
+    ) -> std::result::Result<(), StatusCode> {
+        let (absolute_path, relative_path) = {
+            let file_tree = self.file_tree.read().await;
+            let file = match file_path {
+                Some(path) => file_tree.find_file(path),
+                None => file_tree.default_file(),
+            }
+            .ok_or(StatusCode::NOT_FOUND)?;
+            (
+                file.absolute_path.clone(),
+                file.relative_path.to_string_lossy().to_string(),
+            )
+        };
+
+        // Strict read: writing back a lossily decoded file would corrupt it
+        let content = std::fs::read_to_string(&absolute_path).map_err(|_| StatusCode::NOT_FOUND)?;
+        // The page may be stale if the file changed since it was rendered
+        let updated = toggle_task(&content, index).ok_or(StatusCode::CONFLICT)?;
+        std::fs::write(&absolute_path, updated).map_err(|e| {
+            tracing::error!("Failed to write {}: {}", absolute_path.display(), e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        self.invalidate_render(Some(&relative_path));
+        let _ = self.reload_tx.send(WsMessage::Reload(Some(relative_path)));
+        Ok(())
+    }
+
+    /// Relative path of a served file, or `NOT_FOUND`
+    async fn served_file(
+        &self,
+        file_path: Option<&str>,
+    ) -> std::result::Result<Option<String>, StatusCode> {
+        match file_path {
+            Some(path) => Ok(Some(
+                self.file_tree
+                    .read()
+                    .await
+                    .find_file(path)
+                    .ok_or(StatusCode::NOT_FOUND)?
+                    .relative_path
+                    .to_string_lossy()
+                    .to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Tell clients to scroll to `line`, checking that `file` is being served
+    async fn scroll_to(
+        &self,
+        file_path: Option<&str>,
+        line: usize,
+    ) -> std::result::Result<(), StatusCode> {
+        let file = self.served_file(file_path).await?;
+        let _ = self.reload_tx.send(WsMessage::ScrollTo { line, file });
+        Ok(())
+    }
+
+    /// Reload for an external tool, as the watcher does on a change: just `file`,
+    /// or everything after rescanning the directories for added or removed files
+    async fn reload(&self, file_path: Option<&str>) -> std::result::Result<(), StatusCode> {
+        if let Some(file) = self.served_file(file_path).await? {
+            self.invalidate_render(Some(&file));
+            let _ = self.reload_tx.send(WsMessage::Reload(Some(file)));
+            return Ok(());
+        }
+
+        // A file given on its own has no directories to rescan
+        if !self.file_tree.read().await.roots.is_empty() {
+            self.rebuild_file_tree().await.map_err(|e| {
+                tracing::error!("Failed to rebuild file tree: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            let _ = self.reload_tx.send(WsMessage::TreeUpdate);
+        }
+        self.invalidate_render(None);
+        let _ = self.reload_tx.send(WsMessage::Reload(None));
+        Ok(())
+    }
+
+    /// Show `file` in the open tabs for a later `mdp --reuse` on the same tree.
+    /// `false` when no tab is open, leaving it to the caller to open one.
+    async fn show_file(
+        &self,
+        base_path: &Path,
+        file_path: Option<&str>,
+    ) -> std::result::Result<bool, StatusCode> {
+        if base_path != self.base_path {
+            return Err(StatusCode::CONFLICT);
+        }
+        let file = self.served_file(file_path).await?;
+        if self.connection_count.load(Ordering::SeqCst) == 0 {
+            return Ok(false);
+        }
+        let _ = self.reload_tx.send(WsMessage::Open(file));
+        Ok(true)
+    }
+
+    /// Count a new WebSocket client, cancelling any pending shutdown
+    fn client_connected(&self) {
+        self.connection_generation.fetch_add(1, Ordering::SeqCst);
+        let count = self.connection_count.fetch_add(1, Ordering::SeqCst) + 1;
+        tracing::debug!("WebSocket connected ({} open)", count);
+    }
+
+    /// Count a closed WebSocket client; after the last one, shut down unless
+    /// another client connects within `SHUTDOWN_TIMEOUT_SECS`
+    fn client_disconnected(self: &Arc<Self>) {
+        let prev_count = self.connection_count.fetch_sub(1, Ordering::SeqCst);
+        tracing::debug!("WebSocket disconnected ({} open)", prev_count - 1);
+        if prev_count != 1 {
+            return;
+        }
+
+        // Arming a new timer also invalidates any earlier one still sleeping
+        let generation = self.connection_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let state = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS)).await;
+
+            if state.connection_generation.load(Ordering::SeqCst) == generation
+                && state.connection_count.load(Ordering::SeqCst) == 0
+            {
+                crate::status!("All browser tabs closed. Shutting down...");
+                let _ = state.shutdown_tx.send(());
+            }
+        });
+    }
+
+    /// Update the file tree from changed paths, rescanning only when directories changed
+    pub async fn update_file_tree(&self, changed: &[PathBuf]) -> Result<()> {
+        let updated = self.file_tree.write().await.apply_changes(changed);
+        if updated {
+            Ok(())
+        } else {
+            self.rebuild_file_tree().await
+        }
     }
 
-    /// Rebuild the file tree from the base path
-    pub async fn rebuild_file_tree(&self) -> Result<(), std::io::Error> {
-        let new_tree = FileTree::from_directory(&self.base_path)?;
+    /// Rebuild the file tree by rescanning its directories
+    pub async fn rebuild_file_tree(&self) -> Result<()> {
+        let new_tree = self.file_tree.read().await.rescan()?;
         let mut file_tree = self.file_tree.write().await;
         *file_tree = new_tree;
         Ok(())
     }
 }
 
+/// Serve `file_tree` with pages from `renderer`, which gets source lines for scroll sync
 pub async fn start_server(
     file_tree: FileTree,
-    title: &str,
+    renderer: HtmlRenderer,
     port: u16,
     watch: bool,
-    show_toc: bool,
-) -> std::io::Result<()> {
+    open_file: Option<&str>,
+    open_browser: bool,
+) -> Result<()> {
     let (reload_tx, _) = broadcast::channel::<WsMessage>(16);
     let (shutdown_tx, mut shutdown_rx) = broadcast::channel::<()>(1);
 
@@ -140,11 +704,12 @@ pub async fn start_server(
     let state = Arc::new(ServerState {
         file_tree: RwLock::new(file_tree.clone()),
         base_path: base_path.clone(),
-        title: title.to_string(),
         reload_tx: reload_tx.clone(),
         shutdown_tx: shutdown_tx.clone(),
         connection_count: AtomicUsize::new(0),
-        show_toc,
+        connection_generation: AtomicU64::new(0),
+        renderer: renderer.with_source_lines(true),
+        render_cache: RenderCache::default(),
     });
 
     // Start file watcher if watch mode is enabled
@@ -156,24 +721,24 @@ pub async fn start_server(
                 let watch_tx = reload_tx.clone();
                 tokio::spawn(async move {
                     if let Err(e) = watch_file_async(&watch_path, watch_tx).await {
-                        eprintln!("Failed to start file watcher: {}", e);
+                        tracing::error!("Failed to start file watcher: {}", e);
                     }
                 });
             }
         } else {
-            // Watch entire directory with tree update support
-            let watch_path = base_path.clone();
+            // Watch entire directories with tree update support
+            let watch_paths = file_tree.roots.iter().map(|r| r.path.clone()).collect();
             let watch_tx = reload_tx.clone();
             let watch_state = state.clone();
             tokio::spawn(async move {
                 if let Err(e) = crate::watcher::watch_directory_with_tree_update(
-                    &watch_path,
+                    watch_paths,
                     watch_tx,
                     watch_state,
                 )
                 .await
                 {
-                    eprintln!("Failed to start directory watcher: {}", e);
+                    tracing::error!("Failed to start directory watcher: {}", e);
                 }
             });
         }
@@ -183,47 +748,90 @@ pub async fn start_server(
         .route("/", get(serve_html))
         .route("/view", get(serve_html))
         .route("/api/files", get(serve_file_list))
+        .route("/api/tree", get(serve_tree))
         .route("/api/content", get(serve_content))
+        .route("/api/content_json", get(serve_content_json))
+        .route("/api/toggle-task", post(serve_toggle_task))
+        .route("/api/scroll-to", post(serve_scroll_to))
+        .route("/api/open", post(serve_open))
+        .route("/api/reload", post(serve_reload))
+        .route("/api/tags", get(serve_tags))
+        .route("/api/manifest", get(serve_manifest))
         .route("/assets/github.css", get(serve_css))
+        .route("/assets/custom.css", get(serve_custom_css))
         .route("/ws", get(ws_handler))
+        .fallback(serve_not_found)
+        // Debug-level access log: method, path, status, and latency of every request
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|request: &Request| {
+                    tracing::debug_span!(
+                        "request",
+                        method = %request.method(),
+                        path = %request.uri().path(),
+                    )
+                })
+                .on_request(())
+                .on_response(|response: &Response, latency: Duration, _span: &Span| {
+                    tracing::debug!(
+                        status = response.status().as_u16(),
+                        latency_ms = latency.as_secs_f64() * 1000.0,
+                        "response"
+                    );
+                }),
+        )
         .with_state(state);
 
     let addr = format!("127.0.0.1:{}", port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|source| Error::Bind {
+            addr: addr.clone(),
+            source,
+        })?;
+
+    // Let a later `mdp --reuse` find this server
+    let _registration = Instance::new(port, &base_path)
+        .register(&Instance::default_dir())
+        .inspect_err(|e| tracing::debug!("Failed to write discovery file: {}", e))
+        .ok();
 
-    println!("Server running at http://{}", addr);
+    crate::status!("Server running at http://{}", addr);
     if watch {
-        println!("Live reload enabled - changes will auto-refresh");
+        crate::status!("Live reload enabled - changes will auto-refresh");
     }
-    println!("Press Ctrl+C to stop (or close browser tab)");
+    crate::status!("Press Ctrl+C to stop (or close browser tab)");
 
-    // Open browser
-    if let Err(e) = open::that(format!("http://{}", addr)) {
-        eprintln!("Failed to open browser: {}", e);
-        println!("Please open http://{} in your browser", addr);
-    }
+    // Open browser, directly at the requested file if any
+    open_in_browser(&page_url(&addr, open_file), open_browser);
 
     // Run server with graceful shutdown
     axum::serve(listener, app)
         .with_graceful_shutdown(async move {
             // Wait for shutdown signal
             let _ = shutdown_rx.recv().await;
-            println!("\nShutting down server...");
+            crate::status!("\nShutting down server...");
         })
         .await?;
 
     Ok(())
 }
 
+#[tracing::instrument(level = "debug", skip_all, fields(file = ?query.file))]
 async fn serve_html(
     State(state): State<Arc<ServerState>>,
     Query(query): Query<ViewQuery>,
-) -> (HeaderMap, Html<String>) {
+) -> (StatusCode, HeaderMap, Html<String>) {
     let mut headers = HeaderMap::new();
     headers.insert(header::CACHE_CONTROL, "no-store".parse().unwrap());
+    let (status, html) = state.render_html(query.file.as_deref()).await;
+    (status, headers, Html(html))
+}
+
+async fn serve_not_found(State(state): State<Arc<ServerState>>) -> (StatusCode, Html<String>) {
     (
-        headers,
-        Html(state.render_html(query.file.as_deref()).await),
+        StatusCode::NOT_FOUND,
+        Html(state.render_page(NOT_FOUND_MARKDOWN, None).await),
     )
 }
 
@@ -236,6 +844,12 @@ async fn serve_file_list(State(state): State<Arc<ServerState>>) -> Json<FileList
             path: f.relative_path.to_string_lossy().to_string(),
             name: f.name.clone(),
             is_dir: false,
+            title: f.title.clone(),
+            tags: f.tags.clone(),
+            recent: state
+                .renderer
+                .since()
+                .is_some_and(|since| f.modified_within(since)),
         })
         .collect();
 
@@ -250,6 +864,7 @@ pub struct ContentQuery {
     pub file: String,
 }
 
+#[tracing::instrument(level = "debug", skip_all, fields(file = %query.file))]
 async fn serve_content(
     State(state): State<Arc<ServerState>>,
     Query(query): Query<ContentQuery>,
@@ -264,10 +879,106 @@ async fn serve_content(
             );
             (headers, content).into_response()
         }
-        None => (StatusCode::NOT_FOUND, "File not found").into_response(),
+        None => {
+            // Rendered into the page by loadFile, so send a friendly fragment
+            (
+                StatusCode::NOT_FOUND,
+                Html(state.renderer.render_content(NOT_FOUND_MARKDOWN)),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip_all, fields(file = %query.file))]
+async fn serve_content_json(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<ContentQuery>,
+) -> Response {
+    match state.render_content_json(&query.file).await {
+        Some(content) => {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CACHE_CONTROL, "no-store".parse().unwrap());
+            (headers, Json(content)).into_response()
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "File not found" })),
+        )
+            .into_response(),
+    }
+}
+
+async fn serve_tree(State(state): State<Arc<ServerState>>) -> Json<TreeResponse> {
+    let children = state.tree_nodes().await;
+    let base_path = state.file_tree.read().await.base_path.clone();
+    Json(TreeResponse {
+        children,
+        base_path: base_path.to_string_lossy().to_string(),
+    })
+}
+
+async fn serve_tags(State(state): State<Arc<ServerState>>) -> Json<TagsResponse> {
+    Json(TagsResponse {
+        tags: state.collect_tags().await,
+    })
+}
+
+async fn serve_manifest(
+    State(state): State<Arc<ServerState>>,
+) -> std::result::Result<Json<Manifest>, StatusCode> {
+    let file_tree = state.file_tree.read().await.clone();
+    Manifest::from_tree(&file_tree, state.renderer.parse_options())
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to build manifest: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn serve_toggle_task(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<ToggleTaskRequest>,
+) -> StatusCode {
+    match state
+        .toggle_task(request.file.as_deref(), request.index)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(status) => status,
+    }
+}
+
+async fn serve_scroll_to(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<ScrollToQuery>,
+) -> StatusCode {
+    match state.scroll_to(query.file.as_deref(), query.line).await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(status) => status,
+    }
+}
+
+async fn serve_reload(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<ViewQuery>,
+) -> StatusCode {
+    match state.reload(query.file.as_deref()).await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(status) => status,
     }
 }
 
+async fn serve_open(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<OpenRequest>,
+) -> std::result::Result<Json<OpenResponse>, StatusCode> {
+    let shown = state
+        .show_file(&request.base_path, request.file.as_deref())
+        .await?;
+    Ok(Json(OpenResponse { shown }))
+}
+
 async fn serve_css() -> Response {
     (
         StatusCode::OK,
@@ -277,30 +988,57 @@ async fn serve_css() -> Response {
         .into_response()
 }
 
+async fn serve_custom_css(State(state): State<Arc<ServerState>>) -> Response {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/css")],
+        state.renderer.custom_css().to_string(),
+    )
+        .into_response()
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
 async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<ServerState>>) -> Response {
     ws.on_upgrade(move |socket| handle_socket(socket, state))
 }
 
 async fn handle_socket(mut socket: WebSocket, state: Arc<ServerState>) {
-    // Increment connection count
-    state.connection_count.fetch_add(1, Ordering::SeqCst);
+    state.client_connected();
 
     let mut rx = state.reload_tx.subscribe();
 
     // Send initial connection confirmation
     let _ = socket.send(Message::Text("connected".to_string())).await;
 
+    let ping_interval = Duration::from_secs(PING_INTERVAL_SECS);
+    let mut ping =
+        tokio::time::interval_at(tokio::time::Instant::now() + ping_interval, ping_interval);
+    // Set while a ping is unanswered
+    let mut pong_deadline: Option<tokio::time::Instant> = None;
+
     loop {
         tokio::select! {
+            _ = ping.tick() => {
+                if pong_deadline.is_none() {
+                    pong_deadline = Some(
+                        tokio::time::Instant::now() + Duration::from_secs(PONG_TIMEOUT_SECS),
+                    );
+                }
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+            _ = tokio::time::sleep_until(pong_deadline.unwrap_or_else(tokio::time::Instant::now)),
+                if pong_deadline.is_some() =>
+            {
+                tracing::debug!("WebSocket client stopped answering pings");
+                break;
+            }
             // Wait for reload/tree-update signal
             result = rx.recv() => {
                 match result {
                     Ok(msg) => {
-                        let msg_text = match msg {
-                            WsMessage::Reload => "reload",
-                            WsMessage::TreeUpdate => "tree-update",
-                        };
-                        if socket.send(Message::Text(msg_text.to_string())).await.is_err() {
+                        if socket.send(Message::Text(msg.to_text())).await.is_err() {
                             break;
                         }
                     }
@@ -311,10 +1049,12 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<ServerState>) {
             msg = socket.recv() => {
                 match msg {
                     Some(Ok(Message::Ping(data))) => {
-                        if socket.send(Message::Pong(data)).await.is_err() {
+                        let pong = socket.send(Message::Pong(data)).await;
+                        if pong.is_err() {
                             break;
                         }
                     }
+                    Some(Ok(Message::Pong(_))) => pong_deadline = None,
                     Some(Ok(Message::Close(_))) | None => break,
                     _ => {}
                 }
@@ -322,25 +1062,37 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<ServerState>) {
         }
     }
 
-    // Decrement connection count
-    let prev_count = state.connection_count.fetch_sub(1, Ordering::SeqCst);
+    state.client_disconnected();
+}
 
-    // If this was the last connection, start shutdown timer
-    if prev_count == 1 {
-        let shutdown_tx = state.shutdown_tx.clone();
-        let state_for_timer = state.clone();
+/// URL of the page for `open_file` (or the default file) on the server at `addr`
+pub fn page_url(addr: &str, open_file: Option<&str>) -> String {
+    match open_file {
+        Some(path) => format!("http://{}/view?file={}", addr, encode_query_value(path)),
+        None => format!("http://{}", addr),
+    }
+}
 
-        tokio::spawn(async move {
-            // Wait for timeout
-            tokio::time::sleep(tokio::time::Duration::from_secs(SHUTDOWN_TIMEOUT_SECS)).await;
+/// Open `url` in a browser, or just print it with `open_browser` off or no display
+pub fn open_in_browser(url: &str, open_browser: bool) {
+    if !open_browser || !gui_available(|name| std::env::var_os(name)) {
+        println!("Open {} in your browser", url);
+    } else if let Err(e) = open::that(url) {
+        crate::warning!("Warning: failed to open browser: {}", e);
+        println!("Please open {} in your browser", url);
+    }
+}
 
-            // Check if still no connections
-            if state_for_timer.connection_count.load(Ordering::SeqCst) == 0 {
-                println!("All browser tabs closed. Shutting down...");
-                let _ = shutdown_tx.send(());
-            }
-        });
+/// Whether a browser can be launched: on Unix desktops other than macOS this needs
+/// `$BROWSER` or a display (`$DISPLAY` / `$WAYLAND_DISPLAY`), so SSH sessions and
+/// headless servers only get the URL
+fn gui_available(var: impl Fn(&str) -> Option<std::ffi::OsString>) -> bool {
+    if cfg!(any(windows, target_os = "macos")) {
+        return true;
     }
+    ["BROWSER", "DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|name| var(name).is_some_and(|value| !value.is_empty()))
 }
 
 /// Find an available port starting from the given port
@@ -352,3 +1104,468 @@ pub fn find_available_port(start_port: u16) -> u16 {
     }
     start_port
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::FrontMatterMode;
+
+    fn test_state(file_tree: FileTree) -> ServerState {
+        ServerState {
+            base_path: file_tree.base_path.clone(),
+            file_tree: RwLock::new(file_tree),
+            reload_tx: broadcast::channel(16).0,
+            shutdown_tx: broadcast::channel(1).0,
+            connection_count: AtomicUsize::new(0),
+            connection_generation: AtomicU64::new(0),
+            renderer: HtmlRenderer::new("Test")
+                .with_source_lines(true)
+                .with_frontmatter(FrontMatterMode::Hide),
+            render_cache: RenderCache::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_file_renders_not_found_page() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        std::fs::write(dir.path().join("b.md"), "# B").unwrap();
+        let state = test_state(FileTree::from_directory(dir.path()).unwrap());
+
+        let (status, html) = state.render_html(Some("gone.md")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(html.contains("Page not found"));
+        assert!(html.contains(r#"href="/""#));
+        // Directory mode keeps the sidebar
+        assert!(html.contains(r#"data-path="a.md""#));
+
+        let (status, html) = state.render_html(Some("b.md")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(html.contains(r#"<h1 id="b" data-source-line="1">B</h1>"#));
+        assert!(!html.contains("edit-link"));
+    }
+
+    #[tokio::test]
+    async fn test_landing_page() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.md"), "# B").unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        let mut tree = FileTree::from_directory(dir.path()).unwrap();
+        let state = test_state(tree.clone());
+
+        // No README: a generated list instead of the alphabetically first file
+        let (status, html) = state.render_html(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(html.contains(r#"<div class="landing-page">"#));
+        assert!(html.contains(r#"href="/view?file=b.md""#));
+        assert!(!html.contains(r#"<h1 id="a""#));
+
+        // An explicit index (--index) is shown instead
+        tree.set_default_file("b.md");
+        let state = test_state(tree);
+        let (_, html) = state.render_html(None).await;
+        assert!(html.contains(r#"<h1 id="b" data-source-line="1">B</h1>"#));
+        assert!(!html.contains(r#"<div class="landing-page">"#));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_cancels_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        std::fs::write(&path, "# A").unwrap();
+        let state = Arc::new(test_state(FileTree::from_file(&path).unwrap()));
+        let mut shutdown_rx = state.shutdown_tx.subscribe();
+        let half = Duration::from_secs(SHUTDOWN_TIMEOUT_SECS) / 2;
+
+        // Disconnect, reconnect, then disconnect again just before the first timer ends
+        state.client_connected();
+        state.client_disconnected();
+        tokio::time::sleep(half).await;
+        state.client_connected();
+        tokio::time::sleep(half / 2).await;
+        state.client_disconnected();
+
+        // The first timer expires while the count is zero again, but was cancelled
+        tokio::time::sleep(half).await;
+        assert!(shutdown_rx.try_recv().is_err());
+
+        // The second timer shuts down
+        tokio::time::sleep(half * 2).await;
+        assert!(shutdown_rx.try_recv().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stalled_client_is_dropped() {
+        use futures_util::StreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        std::fs::write(&path, "# A").unwrap();
+        let state = Arc::new(test_state(FileTree::from_file(&path).unwrap()));
+        let app = Router::new()
+            .route("/ws", get(ws_handler))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        let hello = client.next().await.unwrap().unwrap();
+        assert_eq!(hello.into_text().unwrap(), "connected");
+        assert_eq!(state.connection_count.load(Ordering::SeqCst), 1);
+
+        // The client never reads again, so server pings go unanswered
+        tokio::time::sleep(Duration::from_secs(
+            PING_INTERVAL_SECS + PONG_TIMEOUT_SECS + 1,
+        ))
+        .await;
+        assert_eq!(state.connection_count.load(Ordering::SeqCst), 0);
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn test_edit_link() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/a.md"), "# A").unwrap();
+        let mut state = test_state(FileTree::from_directory(dir.path()).unwrap());
+        state.renderer = state
+            .renderer
+            .with_edit_url_base(Some("https://github.com/org/repo/edit/main/".to_string()));
+
+        let html = state.render_content_only("docs/a.md").await.unwrap();
+        assert!(html.starts_with(
+            r#"<div class="edit-link"><a href="https://github.com/org/repo/edit/main/docs/a.md" target="_blank""#
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_custom_css() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        std::fs::write(&path, "# A").unwrap();
+        let mut state = test_state(FileTree::from_file(&path).unwrap());
+        state.renderer = state
+            .renderer
+            .with_custom_css("h1 { color: red; }".to_string());
+        let state = Arc::new(state);
+
+        let (_, html) = state.render_html(None).await;
+        assert!(html.contains(r#"<link rel="stylesheet" href="/assets/custom.css">"#));
+
+        let response = serve_custom_css(State(state)).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "h1 { color: red; }");
+    }
+
+    #[tokio::test]
+    async fn test_frontmatter_page_title() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        std::fs::write(&path, "# A").unwrap();
+        let state = test_state(FileTree::from_file(&path).unwrap());
+
+        let (_, html) = state.render_html(None).await;
+        assert!(html.contains("<title>Test</title>"));
+
+        // An edited title shows without restarting
+        std::fs::write(&path, "---\ntitle: Notes\n---\n# A").unwrap();
+        let (_, html) = state.render_html(None).await;
+        assert!(html.contains("<title>Notes</title>"));
+    }
+
+    #[tokio::test]
+    async fn test_render_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        std::fs::write(&path, "# First").unwrap();
+        let state = test_state(FileTree::from_file(&path).unwrap());
+
+        let first = state.render_content_only("a.md").await.unwrap();
+        assert!(first.contains("First"));
+
+        assert_eq!(state.render_content_only("a.md").await.unwrap(), first);
+        assert_eq!(state.render_cache.len(), 1);
+
+        // Changed content is rendered again even with the same mtime
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, "# Second").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let json = state.render_content_json("a.md").await.unwrap();
+        assert!(json.html.contains("Second"));
+        assert_eq!(json.toc[0].text, "Second");
+        assert_eq!(state.render_cache.len(), 1);
+
+        state.invalidate_render(Some("a.md"));
+        assert!(state.render_cache.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_render_cache_concurrent() {
+        let cache = Arc::new(RenderCache::new(2));
+        let renders = Arc::new(AtomicUsize::new(0));
+
+        let requests: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                let renders = renders.clone();
+                tokio::spawn(async move {
+                    cache
+                        .get_or_render("a.md", "# A", || {
+                            renders.fetch_add(1, Ordering::SeqCst);
+                            std::thread::sleep(Duration::from_millis(50));
+                            ("<h1>A</h1>".to_string(), Vec::new())
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for request in requests {
+            assert_eq!(request.await.unwrap().0, "<h1>A</h1>");
+        }
+        assert_eq!(renders.load(Ordering::SeqCst), 1);
+
+        // Beyond the capacity the least recently used file is dropped
+        let render = || (String::new(), Vec::new());
+        cache.get_or_render("b.md", "# B", render).await;
+        cache.get_or_render("a.md", "# A", render).await;
+        cache.get_or_render("c.md", "# C", render).await;
+        assert_eq!(cache.len(), 2);
+        cache
+            .get_or_render("a.md", "# A", || panic!("a.md was evicted"))
+            .await;
+        cache
+            .get_or_render("b.md", "# B", || ("evicted".to_string(), Vec::new()))
+            .await;
+        assert_eq!(
+            cache.get_or_render("b.md", "# B", render).await.0,
+            "evicted"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tree_nodes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("docs/api")).unwrap();
+        std::fs::create_dir_all(dir.path().join("blog")).unwrap();
+        std::fs::write(dir.path().join("docs/api/ref.md"), "# Ref").unwrap();
+        std::fs::write(dir.path().join("docs/guide.md"), "---\ntitle: Guide\n---\n").unwrap();
+        std::fs::write(dir.path().join("blog/post.md"), "# Post").unwrap();
+        std::fs::write(dir.path().join("z.md"), "# Z").unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Readme").unwrap();
+        let state = test_state(FileTree::from_directory(dir.path()).unwrap());
+
+        let json = serde_json::to_value(state.tree_nodes().await).unwrap();
+        let names = |nodes: &serde_json::Value| -> Vec<String> {
+            nodes
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|node| {
+                    format!(
+                        "{}:{}",
+                        node["type"].as_str().unwrap(),
+                        node["path"].as_str().unwrap()
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            names(&json),
+            ["file:README.md", "file:z.md", "folder:blog", "folder:docs"]
+        );
+        let docs = &json[3];
+        assert_eq!(docs["name"], "docs");
+        assert_eq!(
+            names(&docs["children"]),
+            ["file:docs/guide.md", "folder:docs/api"]
+        );
+        assert_eq!(docs["children"][0]["title"], "Guide");
+        assert!(docs["children"][0]["modified"].as_u64().is_some());
+        assert_eq!(docs["children"][1]["children"][0]["name"], "ref");
+    }
+
+    #[tokio::test]
+    async fn test_collect_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "---\ntags: [rust, cli]\n---\n# A").unwrap();
+        std::fs::write(dir.path().join("b.md"), "---\ntags: [cli]\n---\n# B").unwrap();
+        std::fs::write(dir.path().join("c.md"), "# C").unwrap();
+        let state = test_state(FileTree::from_directory(dir.path()).unwrap());
+
+        let tags = state.collect_tags().await;
+        let summary: Vec<(&str, Vec<&str>)> = tags
+            .iter()
+            .map(|t| {
+                (
+                    t.name.as_str(),
+                    t.files.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [("cli", vec!["a.md", "b.md"]), ("rust", vec!["a.md"])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_toggle_task_writes_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.md");
+        std::fs::write(&path, "- [ ] a\n- [x] b\n").unwrap();
+        let state = test_state(FileTree::from_file(&path).unwrap());
+        let mut rx = state.reload_tx.subscribe();
+
+        assert_eq!(state.toggle_task(None, 1).await, Ok(()));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "- [ ] a\n- [ ] b\n"
+        );
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            WsMessage::Reload(Some("todo.md".to_string()))
+        );
+
+        assert_eq!(state.toggle_task(None, 2).await, Err(StatusCode::CONFLICT));
+        assert_eq!(
+            state.toggle_task(Some("../todo.md"), 0).await,
+            Err(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scroll_to() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        let state = test_state(FileTree::from_directory(dir.path()).unwrap());
+        let mut rx = state.reload_tx.subscribe();
+
+        assert_eq!(state.scroll_to(Some("a.md"), 12).await, Ok(()));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            WsMessage::ScrollTo {
+                line: 12,
+                file: Some("a.md".to_string())
+            }
+        );
+        assert_eq!(
+            state.scroll_to(Some("gone.md"), 1).await,
+            Err(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        let state = test_state(FileTree::from_directory(dir.path()).unwrap());
+        let mut rx = state.reload_tx.subscribe();
+
+        assert_eq!(state.reload(Some("a.md")).await, Ok(()));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            WsMessage::Reload(Some("a.md".to_string()))
+        );
+        assert_eq!(
+            state.reload(Some("gone.md")).await,
+            Err(StatusCode::NOT_FOUND)
+        );
+
+        // Without a file, new files are picked up without a watcher
+        std::fs::write(dir.path().join("b.md"), "# B").unwrap();
+        assert_eq!(state.reload(None).await, Ok(()));
+        assert_eq!(rx.try_recv().unwrap(), WsMessage::TreeUpdate);
+        assert_eq!(rx.try_recv().unwrap(), WsMessage::Reload(None));
+        assert!(state.file_tree.read().await.find_file("b.md").is_some());
+
+        // A single file keeps its tree
+        let state = test_state(FileTree::from_file(&dir.path().join("a.md")).unwrap());
+        let mut rx = state.reload_tx.subscribe();
+        assert_eq!(state.reload(None).await, Ok(()));
+        assert_eq!(rx.try_recv().unwrap(), WsMessage::Reload(None));
+        assert_eq!(state.file_tree.read().await.files.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_open_handover() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A").unwrap();
+        let state = Arc::new(test_state(FileTree::from_directory(dir.path()).unwrap()));
+        let mut rx = state.reload_tx.subscribe();
+        let app = Router::new()
+            .route("/api/open", post(serve_open))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let open = |base_path: PathBuf, file: Option<&'static str>| {
+            let instance = Instance::new(port, &base_path);
+            tokio::task::spawn_blocking(move || instance.open(file).map(|r| r.shown))
+        };
+
+        // No tab open: the caller opens one
+        let base = state.base_path.clone();
+        assert_eq!(open(base.clone(), Some("a.md")).await.unwrap(), Some(false));
+
+        state.client_connected();
+        assert_eq!(open(base.clone(), Some("a.md")).await.unwrap(), Some(true));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            WsMessage::Open(Some("a.md".to_string()))
+        );
+
+        // Files outside the tree, and other trees, need a new server
+        assert_eq!(open(base, Some("gone.md")).await.unwrap(), None);
+        assert_eq!(open(PathBuf::from("/elsewhere"), None).await.unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn test_gui_available() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| std::ffi::OsString::from(value))
+            }
+        };
+        assert!(!gui_available(env(&[])));
+        assert!(!gui_available(env(&[("DISPLAY", "")])));
+        assert!(gui_available(env(&[("DISPLAY", ":0")])));
+        assert!(gui_available(env(&[("WAYLAND_DISPLAY", "wayland-0")])));
+        assert!(gui_available(env(&[("BROWSER", "w3m")])));
+    }
+
+    #[test]
+    fn test_ws_message_text() {
+        assert_eq!(WsMessage::Reload(None).to_text(), "reload");
+        assert_eq!(
+            WsMessage::Reload(Some("docs/api.md".to_string())).to_text(),
+            "reload:docs/api.md"
+        );
+        assert_eq!(WsMessage::TreeUpdate.to_text(), "tree-update");
+        assert_eq!(
+            WsMessage::ScrollTo {
+                line: 3,
+                file: Some("a:b.md".to_string())
+            }
+            .to_text(),
+            "scroll:3:a:b.md"
+        );
+        assert_eq!(
+            WsMessage::Open(Some("docs/a.md".to_string())).to_text(),
+            "open:docs/a.md"
+        );
+    }
+}
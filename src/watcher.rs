@@ -1,12 +1,13 @@
 use notify::RecursiveMode;
 use notify_debouncer_mini::{DebouncedEventKind, new_debouncer};
 use std::collections::HashSet;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 use tokio::sync::broadcast;
 
+use crate::files::is_markdown_path;
 use crate::server::{ServerState, WsMessage};
 
 /// Watch a file for changes and send notifications
@@ -29,7 +30,7 @@ pub fn watch_file<P: AsRef<Path>>(path: P, tx: broadcast::Sender<()>) -> notify:
         .watcher()
         .watch(&parent, RecursiveMode::NonRecursive)?;
 
-    println!("Watching for changes: {}", path.display());
+    tracing::info!("Watching for changes: {}", path.display());
 
     // Process events
     loop {
@@ -42,15 +43,15 @@ pub fn watch_file<P: AsRef<Path>>(path: P, tx: broadcast::Sender<()>) -> notify:
                 });
 
                 if has_target_event {
-                    println!("File changed, reloading...");
+                    tracing::info!("File changed, reloading...");
                     let _ = tx.send(());
                 }
             }
             Ok(Err(e)) => {
-                eprintln!("Watch error: {:?}", e);
+                tracing::error!("Watch error: {:?}", e);
             }
             Err(e) => {
-                eprintln!("Channel error: {:?}", e);
+                tracing::error!("Channel error: {:?}", e);
                 break;
             }
         }
@@ -74,7 +75,7 @@ pub async fn watch_file_async<P: AsRef<Path>>(
     let parent = path.parent().unwrap_or(&path).to_path_buf();
     let file_name = path.file_name().map(|n| n.to_os_string());
 
-    println!("Watching for changes: {}", path.display());
+    tracing::info!("Watching for changes: {}", path.display());
 
     // Spawn blocking task for file watching - debouncer must live inside the blocking task
     tokio::task::spawn_blocking(move || {
@@ -84,7 +85,7 @@ pub async fn watch_file_async<P: AsRef<Path>>(
         let mut debouncer = match new_debouncer(Duration::from_millis(200), debounce_tx) {
             Ok(d) => d,
             Err(e) => {
-                eprintln!("Failed to create debouncer: {}", e);
+                tracing::error!("Failed to create debouncer: {}", e);
                 return;
             }
         };
@@ -94,7 +95,7 @@ pub async fn watch_file_async<P: AsRef<Path>>(
             .watcher()
             .watch(&parent, RecursiveMode::NonRecursive)
         {
-            eprintln!("Failed to watch directory: {}", e);
+            tracing::error!("Failed to watch directory: {}", e);
             return;
         }
 
@@ -108,12 +109,12 @@ pub async fn watch_file_async<P: AsRef<Path>>(
                     });
 
                     if has_target_event {
-                        println!("File changed, reloading...");
-                        let _ = tx.send(WsMessage::Reload);
+                        tracing::info!("File changed, reloading...");
+                        let _ = tx.send(WsMessage::Reload(None));
                     }
                 }
                 Ok(Err(e)) => {
-                    eprintln!("Watch error: {:?}", e);
+                    tracing::error!("Watch error: {:?}", e);
                 }
                 Err(_) => {
                     break;
@@ -128,15 +129,17 @@ pub async fn watch_file_async<P: AsRef<Path>>(
     Ok(())
 }
 
-/// Watch a directory recursively for .md file changes with tree update support
-pub async fn watch_directory_with_tree_update<P: AsRef<Path>>(
-    path: P,
+/// Watch directories recursively for .md file changes with tree update support
+pub async fn watch_directory_with_tree_update(
+    paths: Vec<PathBuf>,
     tx: broadcast::Sender<WsMessage>,
     state: Arc<ServerState>,
 ) -> notify::Result<()> {
-    let path = path.as_ref().to_path_buf();
+    for path in &paths {
+        tracing::info!("Watching directory for changes: {}", path.display());
+    }
 
-    println!("Watching directory for changes: {}", path.display());
+    let extensions = state.file_tree.read().await.extensions.clone();
 
     // Get initial file paths for comparison (detects renames, not just count changes)
     let initial_paths: HashSet<String> = {
@@ -147,11 +150,11 @@ pub async fn watch_directory_with_tree_update<P: AsRef<Path>>(
             .collect()
     };
 
-    // Create channel for sending events from blocking thread to async handler
-    let (event_tx, mut event_rx) = tokio::sync::mpsc::channel::<bool>(16);
+    // Create channel for sending changed paths from blocking thread to async handler
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::channel::<Vec<PathBuf>>(16);
 
     // Spawn blocking task for directory watching (only file system operations)
-    let path_clone = path.clone();
+    let watch_extensions = extensions.clone();
     tokio::task::spawn_blocking(move || {
         let (debounce_tx, debounce_rx) = channel();
 
@@ -159,40 +162,44 @@ pub async fn watch_directory_with_tree_update<P: AsRef<Path>>(
         let mut debouncer = match new_debouncer(Duration::from_millis(200), debounce_tx) {
             Ok(d) => d,
             Err(e) => {
-                eprintln!("Failed to create debouncer: {}", e);
+                tracing::error!("Failed to create debouncer: {}", e);
                 return;
             }
         };
 
-        // Watch the directory recursively
-        if let Err(e) = debouncer
-            .watcher()
-            .watch(&path_clone, RecursiveMode::Recursive)
-        {
-            eprintln!("Failed to watch directory: {}", e);
-            return;
+        // Watch each directory recursively
+        for path in &paths {
+            if let Err(e) = debouncer.watcher().watch(path, RecursiveMode::Recursive) {
+                tracing::error!("Failed to watch directory: {}", e);
+                return;
+            }
         }
 
         loop {
             match debounce_rx.recv() {
                 Ok(Ok(events)) => {
-                    // Filter for markdown files only
-                    let has_md_events = events.iter().any(|e| {
-                        e.kind == DebouncedEventKind::Any
-                            && e.path
-                                .extension()
-                                .is_some_and(|ext| ext == "md" || ext == "markdown")
-                    });
-
-                    if has_md_events {
+                    // Keep markdown files, plus directories and removed paths
+                    // (a removed directory may have contained markdown files)
+                    let paths: Vec<PathBuf> = events
+                        .iter()
+                        .filter(|e| {
+                            e.kind == DebouncedEventKind::Any
+                                && (is_markdown_path(&e.path, &watch_extensions)
+                                    || e.path.is_dir()
+                                    || !e.path.exists())
+                        })
+                        .map(|e| e.path.clone())
+                        .collect();
+
+                    if !paths.is_empty() {
                         // Send event to async handler (non-blocking)
-                        if event_tx.blocking_send(true).is_err() {
+                        if event_tx.blocking_send(paths).is_err() {
                             break;
                         }
                     }
                 }
                 Ok(Err(e)) => {
-                    eprintln!("Watch error: {:?}", e);
+                    tracing::error!("Watch error: {:?}", e);
                 }
                 Err(_) => {
                     break;
@@ -206,10 +213,11 @@ pub async fn watch_directory_with_tree_update<P: AsRef<Path>>(
     // Async handler for processing events (runs on async runtime, not blocking pool)
     let mut last_paths = initial_paths;
     tokio::spawn(async move {
-        while event_rx.recv().await.is_some() {
-            // Rebuild file tree and get new file paths
-            if let Err(e) = state.rebuild_file_tree().await {
-                eprintln!("Failed to rebuild file tree: {}", e);
+        while let Some(changed_paths) = event_rx.recv().await {
+            tracing::debug!("Changed paths: {:?}", changed_paths);
+            // Update file tree and get new file paths
+            if let Err(e) = state.update_file_tree(&changed_paths).await {
+                tracing::error!("Failed to rebuild file tree: {}", e);
                 continue;
             }
 
@@ -223,17 +231,33 @@ pub async fn watch_directory_with_tree_update<P: AsRef<Path>>(
 
             // Check if file paths changed (handles add, remove, and rename)
             if new_paths != last_paths {
-                println!(
+                state.invalidate_render(None);
+                tracing::info!(
                     "File tree changed ({} -> {} files), updating sidebar...",
                     last_paths.len(),
                     new_paths.len()
                 );
                 let _ = tx.send(WsMessage::TreeUpdate);
                 last_paths = new_paths;
-            } else {
-                // Just content changed
-                println!("Markdown file changed, reloading...");
-                let _ = tx.send(WsMessage::Reload);
+            } else if changed_paths
+                .iter()
+                .any(|p| is_markdown_path(p, &extensions))
+            {
+                // Just content changed: tell clients which files, so others don't reload
+                tracing::info!("Markdown file changed, reloading...");
+                for changed in changed_paths
+                    .into_iter()
+                    .filter(|p| is_markdown_path(p, &extensions))
+                {
+                    let relative = state
+                        .file_tree
+                        .read()
+                        .await
+                        .relative_path_of(&changed)
+                        .map(|p| p.to_string_lossy().to_string());
+                    state.invalidate_render(relative.as_deref());
+                    let _ = tx.send(WsMessage::Reload(relative));
+                }
             }
         }
     });